@@ -1,90 +1,681 @@
 //! This module contains executors for running image processing stages in parallel.
 
 use rayon::prelude::*;
-use std::path::Path;
+use std::{
+    borrow::{Borrow, Cow},
+    fmt, fs,
+    io::{self, Cursor},
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+};
 
-use image::{imageops, Rgba};
+use image::{
+    imageops, ColorType, DynamicImage, GenericImage, ImageOutputFormat, ImageResult, Luma, Pixel,
+    Rgb, Rgba,
+};
 use imageproc::definitions::Image;
-use rand::{Rng, SeedableRng};
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+use serde_json::{json, Value};
 
-use crate::{traits::StageBuilder, util::SetEnumerator, TaggedImage, Tags};
+use crate::{
+    inputs::InputPool,
+    traits::{ImageStage, OutputSink, StageBuilder, StageDescriptor},
+    util::{
+        apply_orientation, draw_label, exif_orientation, stable_hash, truncate_with_hash,
+        SetEnumerator,
+    },
+    TaggedImage, Tags,
+};
+
+/// Every stage to execute on an image, in order, as the index of the variation to execute (offset
+/// by one) alongside all of the variations built by that stage's builder.
+type Pipeline<P> = Vec<(usize, Vec<Box<dyn ImageStage<P> + Send + Sync>>)>;
+
+/// An output of a pipeline, along with its name, accumulated tags, and the provenance record of
+/// each stage applied to it.
+type Output<P> = (Image<P>, String, Tags, Vec<Value>);
+
+/// A hook receiving every output image, along with its name and tags, before it's saved.
+type OutputHook<P> = Box<dyn Fn(&mut Image<P>, &str, &Tags) + Send + Sync>;
+
+/// A pixel type an executor can process images in. Images are converted to it when they're loaded,
+/// so stages only ever see this one type, and converted back to a [`DynamicImage`] to be saved.
+///
+/// [`DynamicImage`]: about:blank
+pub(crate) trait ExecutorPixel: Pixel<Subpixel = u8> + Send + Sync + 'static {
+    /// The color type outputs are saved with by default, the one matching the pixel type.
+    const OUTPUT_COLOR: OutputColor;
+
+    /// Converts a freshly loaded image to this pixel type.
+    fn from_dynamic(img: DynamicImage) -> Image<Self>;
+
+    /// Wraps an image of this pixel type to be encoded.
+    fn into_dynamic(img: Image<Self>) -> DynamicImage;
+
+    /// A white pixel (opaque, for pixel types with alpha).
+    fn white() -> Self;
+
+    /// A black pixel (opaque, for pixel types with alpha).
+    fn black() -> Self;
+}
+
+impl ExecutorPixel for Rgba<u8> {
+    const OUTPUT_COLOR: OutputColor = OutputColor::Rgba8;
+
+    fn from_dynamic(img: DynamicImage) -> Image<Self> {
+        img.to_rgba8()
+    }
+
+    fn into_dynamic(img: Image<Self>) -> DynamicImage {
+        DynamicImage::ImageRgba8(img)
+    }
+
+    fn white() -> Self {
+        Rgba([255; 4])
+    }
+
+    fn black() -> Self {
+        Rgba([0, 0, 0, 255])
+    }
+}
+
+impl ExecutorPixel for Rgb<u8> {
+    const OUTPUT_COLOR: OutputColor = OutputColor::Rgb8;
+
+    fn from_dynamic(img: DynamicImage) -> Image<Self> {
+        img.to_rgb8()
+    }
+
+    fn into_dynamic(img: Image<Self>) -> DynamicImage {
+        DynamicImage::ImageRgb8(img)
+    }
+
+    fn white() -> Self {
+        Rgb([255; 3])
+    }
+
+    fn black() -> Self {
+        Rgb([0; 3])
+    }
+}
+
+impl ExecutorPixel for Luma<u8> {
+    const OUTPUT_COLOR: OutputColor = OutputColor::Luma8;
+
+    fn from_dynamic(img: DynamicImage) -> Image<Self> {
+        img.to_luma8()
+    }
+
+    fn into_dynamic(img: Image<Self>) -> DynamicImage {
+        DynamicImage::ImageLuma8(img)
+    }
+
+    fn white() -> Self {
+        Luma([255])
+    }
+
+    fn black() -> Self {
+        Luma([0])
+    }
+}
+
+/// How the outputs of an executor are organized into subdirectories of its output directory.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SubdirStrategy {
+    /// Every output is saved directly in the output directory.
+    Flat,
+    /// The outputs of each source image are saved in their own subdirectory, named after the
+    /// source's file stem followed by a hash of its full path (e.g. `foo-1a2b3c4d/`), so sources
+    /// sharing a stem in different input directories don't collide.
+    BySource,
+}
+
+impl Default for SubdirStrategy {
+    fn default() -> Self {
+        Self::Flat
+    }
+}
+
+/// The color type outputs are saved with. Every image is converted to the executor's pixel type
+/// (8-bit RGBA, unless another is chosen) when it's loaded, whatever its original color type, and
+/// processed as such, so by default a grayscale input yields outputs in the executor's pixel type.
+/// This allows saving space or matching what downstream loaders expect.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum OutputColor {
+    /// 8-bit RGB with an alpha channel.
+    Rgba8,
+    /// 8-bit RGB, dropping the alpha channel.
+    Rgb8,
+    /// 8-bit grayscale, dropping the alpha channel.
+    Luma8,
+    /// 8-bit grayscale with an alpha channel.
+    LumaA8,
+    /// The smallest of the above which losslessly represents each output, i.e. the alpha channel
+    /// is dropped for fully opaque images, and the color channels are merged for gray images.
+    Auto,
+    /// The 8-bit equivalent of the source image's color type, e.g. grayscale inputs yield grayscale
+    /// outputs even if a stage added color to them, and inputs without an alpha channel yield
+    /// outputs without one.
+    Source,
+}
+
+impl Default for OutputColor {
+    fn default() -> Self {
+        Self::Rgba8
+    }
+}
 
 /// Creates series of stages that can then be [`execute`]d to perform every variation and combination
 /// of image transformation requested in parallel.
 ///
+/// Images are processed in the pixel type `P`, 8-bit RGBA by default. Other pixel types (`Rgb<u8>`
+/// for photos, or `Luma<u8>` for grayscale corpora) save the memory and disk space of channels the
+/// images don't have, but only builders implemented for that pixel type can be added, so e.g. hue
+/// shifts can't be added to a grayscale executor.
+///
 /// [`execute`]: about:blank
-pub struct ParallelStageExecutor<R, OP>
+pub struct ParallelStageExecutor<R, OP, P = Rgba<u8>>
 where
     R: SeedableRng + Rng,
     OP: AsRef<Path>,
+    P: ExecutorPixel,
 {
     /// A list of builders, that will be executed in order (when present) on each image.
     /// Note that these are *builders* and the stages themselves are built on demand
     /// when given an image during the execution phase.
     ///
-    /// The static `rgba8` color space is due to a limitation with `Image` that does not
-    /// allow you to convert between color-spaces generically.
-    stages: Vec<Box<dyn StageBuilder<Rgba<u8>, R> + Send + Sync>>,
+    /// All stages share the executor's pixel type, as `Image` doesn't allow converting between
+    /// color spaces generically.
+    stages: Vec<Box<dyn StageBuilder<P, R> + Send + Sync>>,
 
     /// A path to the directory under which to save the output files.
     out_dir: OP,
+
+    /// Whether to rotate/flip each image upright according to its EXIF orientation flag when
+    /// it's loaded, before any stages are executed on it.
+    upright: bool,
+
+    /// A hook run on every output image once all of its stages have executed, right before
+    /// it's saved.
+    post_hook: Option<Box<dyn Fn(&mut Image<P>) + Send + Sync>>,
+
+    /// A hook run on every output image right before it's saved, after `post_hook`, which also
+    /// receives the output's name and accumulated tags.
+    on_output: Option<OutputHook<P>>,
+
+    /// How outputs are organized into subdirectories of `out_dir`.
+    subdir_strategy: SubdirStrategy,
+
+    /// The color type the outputs are saved with.
+    output_color: OutputColor,
+
+    /// The seed used to shuffle the input images before they're processed, if any.
+    shuffle_seed: Option<u64>,
+
+    /// Where the outputs are written to, when `None` they're saved as files under `out_dir`.
+    sink: Option<Box<dyn OutputSink>>,
+
+    /// The most variations used from any single builder, if any.
+    sample_cap: Option<usize>,
+
+    /// The most combinations executed per image, if any.
+    combination_limit: Option<usize>,
+
+    /// Whether to write a provenance record alongside every output.
+    provenance: bool,
+
+    /// The paths of the images being executed on, shared with any stages which need them.
+    inputs: InputPool,
 }
 
-impl<R, OP> ParallelStageExecutor<R, OP>
+impl<R, OP, P> ParallelStageExecutor<R, OP, P>
 where
     R: SeedableRng + Rng,
     OP: AsRef<Path> + 'static + Sync,
+    P: ExecutorPixel,
 {
     /// Creates an empty executor (one with no stages), whose output directory
-    /// is set to `out_dir`.
+    /// is set to `out_dir`. The output directory doesn't need to exist, it's created
+    /// (along with any missing parents) when outputs are saved to it.
     pub fn new(out_dir: OP) -> Self {
         Self {
             stages: vec![],
             out_dir,
+            upright: false,
+            post_hook: None,
+            on_output: None,
+            subdir_strategy: SubdirStrategy::default(),
+            output_color: P::OUTPUT_COLOR,
+            shuffle_seed: None,
+            sink: None,
+            sample_cap: None,
+            combination_limit: None,
+            provenance: false,
+            inputs: InputPool::default(),
         }
     }
 
+    /// Sets whether each image should be normalized to be upright according to its EXIF orientation
+    /// on load, so stages like [`RotationBuilder`] operate relative to the image as it's meant to be
+    /// viewed. Outputs are saved as PNGs without any EXIF data, so the orientation tag never survives
+    /// into the processed images either way.
+    ///
+    /// [`RotationBuilder`]: about:blank
+    pub fn with_exif_orientation(mut self, upright: bool) -> Self {
+        self.upright = upright;
+        self
+    }
+
+    /// Sets a hook which receives every output image, after all of its stages have executed, and
+    /// may modify it in place before it's saved. This is an escape hatch for one-off tweaks that
+    /// don't warrant a full [`StageBuilder`], as such its effects aren't reflected in the image's
+    /// tags or filename.
+    ///
+    /// [`StageBuilder`]: about:blank
+    pub fn with_post_hook(mut self, hook: Box<dyn Fn(&mut Image<P>) + Send + Sync>) -> Self {
+        self.post_hook = Some(hook);
+        self
+    }
+
+    /// Sets a hook which receives every output image right before it's saved, along with its name
+    /// (its filename without the extension) and the tags accumulated by the input and every stage
+    /// applied to it. The hook may modify the image in place, for custom watermarking, provenance
+    /// stamps and the like, and runs after the hook set by [`with_post_hook`] if there is one.
+    ///
+    /// The hook is run from many threads at once, hence it must be `Send + Sync`.
+    ///
+    /// [`with_post_hook`]: about:blank
+    pub(crate) fn on_output(mut self, hook: OutputHook<P>) -> Self {
+        self.on_output = Some(hook);
+        self
+    }
+
+    /// Sets how the outputs are organized into subdirectories of the output directory, subdirectories
+    /// are created as needed when an image is processed.
+    pub fn with_subdir_strategy(mut self, strategy: SubdirStrategy) -> Self {
+        self.subdir_strategy = strategy;
+        self
+    }
+
+    /// Sets the color type the outputs are saved with, by default they're saved in the executor's
+    /// pixel type (e.g. 8-bit RGBA).
+    pub fn with_output_color(mut self, color: OutputColor) -> Self {
+        self.output_color = color;
+        self
+    }
+
+    /// Sets a seed used to shuffle the input images before they're processed, so they're processed
+    /// in a reproducible order that doesn't depend on how the filesystem happened to list them.
+    /// Since images are processed in parallel this order is only roughly followed, but the same
+    /// seed and inputs always yield the same order.
+    pub fn with_shuffle_seed(mut self, seed: Option<u64>) -> Self {
+        self.shuffle_seed = seed;
+        self
+    }
+
+    /// Sets a sink the encoded outputs are written to instead of being saved as loose files under
+    /// the output directory, such as a [`TarSink`] bundling them into a single archive. Outputs
+    /// are written to the sink with paths relative to the output directory.
+    ///
+    /// [`TarSink`]: about:blank
+    pub fn with_sink(mut self, sink: Box<dyn OutputSink>) -> Self {
+        self.sink = Some(sink);
+        self
+    }
+
+    /// Caps the number of variations used from every builder, without having to change each
+    /// builder's configured sample count. For instance, a cap of `Some(1)` makes for a quick
+    /// smoke test of a pipeline, while `None` (the default) uses every variation.
+    pub fn with_sample_cap(mut self, cap: Option<usize>) -> Self {
+        self.sample_cap = cap;
+        self
+    }
+
+    /// Sets whether to write a JSON sidecar alongside every output (e.g. `foo_blur_7.31.json` for
+    /// `foo_blur_7.31.png`) recording its source, tags, and the exact parameters of every stage
+    /// applied to it in order, so augmentations can be reconstructed or inverted. Stages record
+    /// their parameters through [`ImageStage::params`].
+    ///
+    /// [`ImageStage::params`]: about:blank
+    pub fn with_provenance(mut self, provenance: bool) -> Self {
+        self.provenance = provenance;
+        self
+    }
+
+    /// Limits each image to the first `n` combinations of stages, in the order they're enumerated
+    /// in, the first of which is always the unmodified image. Unlike stopping after some number of
+    /// outputs, which would depend on how the parallel workers are scheduled, this always yields
+    /// the same subset of outputs, making for a small but stable dataset.
+    pub fn take_combinations(mut self, n: usize) -> Self {
+        self.combination_limit = Some(n);
+        self
+    }
+
+    /// A handle to the paths of the images this executor is executing on, for builders of stages
+    /// which draw on other inputs than the image they're executed on (such as [`MixupBuilder`]).
+    /// The pool is empty until [`execute`] is called, at which point it's filled with every input.
+    ///
+    /// [`MixupBuilder`]: about:blank
+    /// [`execute`]: about:blank
+    pub(crate) fn input_pool(&self) -> InputPool {
+        self.inputs.clone()
+    }
+
     /// Adds a new stage to the executor, for each image all [`StageBuilder::variations()`]
     /// will be generated, including the variations where this stage isn't executed.
     ///
     /// [`StageBuilder::variations()`]: about:blank
-    pub(crate) fn add_stage(
-        mut self,
-        stage: Box<dyn StageBuilder<Rgba<u8>, R> + Send + Sync>,
-    ) -> Self {
+    pub(crate) fn add_stage(mut self, stage: Box<dyn StageBuilder<P, R> + Send + Sync>) -> Self {
         self.stages.push(stage);
         self
     }
 
+    /// Breaks down how many outputs will be generated for an image with the given `tags`, by how
+    /// much each stage multiplies the number of combinations. Since every stage can also be skipped,
+    /// a stage with `n` variations contributes a factor of `n + 1`, which is why adding stages grows
+    /// the output count so quickly.
+    ///
+    /// Stages in the same [`StageBuilder::conflict_group`] are never combined, so a whole group
+    /// only multiplies the total by the sum of its stages' variations plus one.
+    ///
+    /// [`StageBuilder::conflict_group`]: about:blank
+    pub(crate) fn combination_breakdown(&self, tags: &Tags) -> CombinationBreakdown {
+        let stages: Vec<_> = self
+            .stages
+            .iter()
+            .map(|bd| {
+                let variations = self.variations(&**bd, tags);
+                (bd.name().into_owned(), variations + 1)
+            })
+            .collect();
+        let mut groups: Vec<(Cow<str>, usize)> = vec![];
+        let mut total = 1;
+        for (bd, (_, factor)) in self.stages.iter().zip(&stages) {
+            match bd.conflict_group() {
+                Some(group) => match groups.iter_mut().find(|(name, _)| *name == group) {
+                    Some((_, variations)) => *variations += factor - 1,
+                    None => groups.push((group, factor - 1)),
+                },
+                None => total *= factor,
+            }
+        }
+        let total = total
+            * groups
+                .iter()
+                .map(|(_, variations)| variations + 1)
+                .product::<usize>();
+        let total = total.min(self.combination_limit.unwrap_or(usize::MAX));
+
+        CombinationBreakdown { stages, total }
+    }
+
+    /// Describes every stage this executor can apply to an image, in the order the builders were
+    /// added. See `StageBuilder::describe` for how sampled names are represented.
+    pub(crate) fn stage_descriptors(&self) -> Vec<StageDescriptor> {
+        self.stages.iter().flat_map(|bd| bd.describe()).collect()
+    }
+
+    /// Runs a preflight check of every stage without saving anything, by building each stage's
+    /// variations with a fixed seed and executing the first variation on `sample`. Any panic while
+    /// building or executing is caught and recorded in the report rather than aborting, so
+    /// misconfigured stages can be caught before a long run.
+    ///
+    /// Note that the panics are still reported by the panic hook as usual.
+    pub(crate) fn validate(&self, sample: &Image<P>) -> ValidationReport {
+        let stages = self
+            .stages
+            .par_iter()
+            .map(|bd| {
+                let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                    let mut rng = R::seed_from_u64(0);
+                    if let Some(stage) = bd.build_stage(&mut rng).first() {
+                        stage.execute_many(sample);
+                    }
+                }));
+                let error = result.err().map(|payload| {
+                    match (
+                        payload.downcast_ref::<&str>(),
+                        payload.downcast_ref::<String>(),
+                    ) {
+                        (Some(msg), _) => msg.to_string(),
+                        (_, Some(msg)) => msg.clone(),
+                        _ => "unknown panic".to_owned(),
+                    }
+                });
+                (bd.name().into_owned(), error)
+            })
+            .collect();
+
+        ValidationReport { stages }
+    }
+
     /// Executes the pipeline, with a separate worker for each image, each combination/variation
     /// of stages will then be built out for the image, and then those transformations will happen
     /// in parallel. The RNG when building the image will be set based on the image's name.
-    pub(crate) fn execute<I, P>(&self, images: I)
+    ///
+    /// The images may either be owned or borrowed (e.g. `&Vec<TaggedImage<P>>`), so the same
+    /// inputs can be reused across several executors. Their paths are put in the executor's
+    /// [`input_pool`] before any of them are processed.
+    ///
+    /// Yields the paths of the outputs which were skipped because the output directory is so deep
+    /// that there's no room left for even a shortened file name (see [`file_name`]). Pipelines are
+    /// checked by their stages' names before being executed, so the work isn't wasted.
+    ///
+    /// [`input_pool`]: about:blank
+    /// [`file_name`]: about:blank
+    pub(crate) fn execute<I, T, IP>(&self, images: I) -> Vec<PathBuf>
     where
-        I: IntoParallelIterator<Item = TaggedImage<P>>,
-        P: AsRef<Path>,
+        I: IntoParallelIterator<Item = T>,
+        T: Borrow<TaggedImage<IP>> + Send,
+        IP: AsRef<Path>,
     {
-        images.into_par_iter().for_each(|img| {
-            let loaded = match image::open(&img.img) {
+        let process = |img: T| {
+            let img = img.borrow();
+            let (loaded, source_color) = match self.load(img.img.as_ref()) {
                 Ok(loaded) => loaded,
-                Err(_) => return,
+                Err(_) => return vec![],
             };
-            let name = img.img.as_ref().file_stem().unwrap();
-            self.all_pipelines(&img.tags, loaded.to_rgba8(), name.to_str().unwrap())
-        });
+            let name = img.img.as_ref().file_stem().unwrap().to_str().unwrap();
+            let subdir = self.source_dir(img.img.as_ref(), name);
+            self.all_pipelines(
+                &img.tags,
+                loaded,
+                img.img.as_ref(),
+                source_color,
+                name,
+                &subdir,
+            )
+        };
+
+        let mut images: Vec<_> = images.into_par_iter().collect();
+        // Sorted so stages drawing on the pool don't depend on the order the inputs were listed in
+        let mut paths: Vec<_> = images
+            .iter()
+            .map(|img| img.borrow().img.as_ref().to_path_buf())
+            .collect();
+        paths.sort();
+        self.inputs.set(paths);
+        if let Some(seed) = self.shuffle_seed {
+            images.shuffle(&mut R::seed_from_u64(seed));
+        }
+        images.into_par_iter().flat_map(process).collect()
     }
 
-    /// Executes all pipelines for a single image, this is the workhorse that generates
-    /// all stage variations and then schedules them on rayon workers.
-    fn all_pipelines(&self, tags: &Tags, img: Image<Rgba<u8>>, name: &str) {
+    /// Renders a contact sheet previewing the outputs for `sample`, so the stages' parameters can
+    /// be judged without running the whole pipeline. At most `cols * cols` combinations are executed
+    /// (in the same order they would be saved in), and each is downscaled into a tile of a grid
+    /// `cols` tiles wide, labelled with the names of the stages that were applied.
+    pub(crate) fn contact_sheet<IP: AsRef<Path>>(
+        &self,
+        sample: &TaggedImage<IP>,
+        cols: u32,
+    ) -> ImageResult<Image<P>> {
+        /// The width and height of the area each output is downscaled to fit in.
+        const TILE: u32 = 160;
+        /// The height of the area under each tile holding its label.
+        const LABEL: u32 = 14;
+
+        let (img, _) = self.load(sample.img.as_ref())?;
+        let name = sample.img.as_ref().file_stem().unwrap().to_str().unwrap();
+        let tags = &sample.tags;
+        let cols = cols.max(1);
+
+        let tiles: Vec<_> = self
+            .pipelines(tags, Self::seed(name))
+            .take((cols * cols) as usize)
+            .collect::<Vec<_>>()
+            .into_par_iter()
+            .flat_map_iter(|pipeline| Self::run_pipeline(&img, String::new(), tags, pipeline))
+            .map(|(img, label, _, _)| {
+                let label = match label.strip_prefix('_') {
+                    Some(label) => label.to_owned(),
+                    None => "original".to_owned(),
+                };
+                (imageops::thumbnail(&img, TILE, TILE), label)
+            })
+            .collect();
+        let tiles = &tiles[..tiles.len().min((cols * cols) as usize)];
+
+        let rows = (tiles.len() as u32 + cols - 1) / cols;
+        let mut sheet = Image::from_pixel(cols * TILE, rows * (TILE + LABEL), P::white());
+        for (idx, (tile, label)) in tiles.iter().enumerate() {
+            let (x, y) = (idx as u32 % cols * TILE, idx as u32 / cols * (TILE + LABEL));
+            sheet.copy_from(
+                tile,
+                x + (TILE - tile.width()) / 2,
+                y + (TILE - tile.height()) / 2,
+            )?;
+            draw_label(&mut sheet, label, x + 2, y + TILE + 2, 2, P::black());
+        }
+
+        Ok(sheet)
+    }
+
+    /// Loads the image at `path` into the executor's color space, turning it upright first
+    /// if requested. The color type the image was originally in is returned alongside it.
+    fn load(&self, path: &Path) -> ImageResult<(Image<P>, ColorType)> {
+        let loaded = image::open(path)?;
+        let loaded = match self.upright.then(|| exif_orientation(path)).flatten() {
+            Some(orientation) => apply_orientation(loaded, orientation),
+            None => loaded,
+        };
+        let color = loaded.color();
+        Ok((P::from_dynamic(loaded), color))
+    }
+
+    /// The directory, relative to the output directory, the outputs of the source image at `path`
+    /// (whose file stem is `name`) are saved under according to the executor's [`SubdirStrategy`].
+    ///
+    /// [`SubdirStrategy`]: about:blank
+    fn source_dir(&self, path: &Path, name: &str) -> PathBuf {
+        let mut dir = PathBuf::new();
+        if self.subdir_strategy == SubdirStrategy::BySource {
+            let hash = stable_hash(path.to_string_lossy().as_bytes());
+            dir.push(format!("{}-{:08x}", name, hash as u32));
+        }
+        dir
+    }
+
+    /// The file name of the output named `name` saved under `subdir`, shortened with
+    /// [`truncate_with_hash`] when the full name would exceed the filesystem's limits, which long
+    /// combinations of stages easily do. File names are limited to 255 bytes, and on Windows the
+    /// whole path is also limited to 260 characters (unless a sink is used, which is free to name
+    /// its entries however it likes). Yields `None` if the output directory is so deep there's no
+    /// room left for even a shortened name.
+    ///
+    /// [`truncate_with_hash`]: about:blank
+    fn file_name(&self, name: &str, subdir: &Path) -> Option<String> {
+        /// The most bytes in a file name on most filesystems.
+        const MAX_NAME: usize = 255;
+        /// The most characters in a path on Windows.
+        const MAX_PATH: usize = 260;
+        /// The extension of every output.
+        const EXT: &str = ".png";
+
+        let mut max_len = MAX_NAME;
+        if cfg!(windows) && self.sink.is_none() {
+            let dir = self.out_dir.as_ref().join(subdir);
+            max_len = max_len.min(MAX_PATH.saturating_sub(dir.as_os_str().len() + 1));
+        }
+        truncate_with_hash(name, max_len.saturating_sub(EXT.len()))
+            .map(|name| name.into_owned() + EXT)
+    }
+
+    /// Encodes `img` as a PNG with the executor's [`OutputColor`], and writes it to `path` (relative
+    /// to the output directory) or to the executor's [`OutputSink`] if it has one. `source_color`
+    /// is the color type of the image `img` was generated from.
+    ///
+    /// [`OutputColor`]: about:blank
+    /// [`OutputSink`]: about:blank
+    fn save(&self, img: Image<P>, source_color: ColorType, path: &Path) -> ImageResult<()> {
+        let img = P::into_dynamic(img);
+        let img = match resolve_color(self.output_color, &img, source_color) {
+            OutputColor::Rgba8 if img.color() != ColorType::Rgba8 => {
+                DynamicImage::ImageRgba8(img.to_rgba8())
+            }
+            OutputColor::Rgb8 if img.color() != ColorType::Rgb8 => {
+                DynamicImage::ImageRgb8(img.to_rgb8())
+            }
+            OutputColor::Luma8 if img.color() != ColorType::L8 => {
+                DynamicImage::ImageLuma8(img.to_luma8())
+            }
+            OutputColor::LumaA8 if img.color() != ColorType::La8 => {
+                DynamicImage::ImageLumaA8(img.to_luma_alpha8())
+            }
+            _ => img,
+        };
+
+        let mut data = vec![];
+        img.write_to(&mut Cursor::new(&mut data), ImageOutputFormat::Png)?;
+        Ok(self.write(path, &data)?)
+    }
+
+    /// Writes `data` to `path` (relative to the output directory), or to the executor's
+    /// [`OutputSink`] if it has one.
+    ///
+    /// [`OutputSink`]: about:blank
+    fn write(&self, path: &Path, data: &[u8]) -> io::Result<()> {
+        match &self.sink {
+            Some(sink) => sink.write(path, data),
+            None => fs::write(self.out_dir.as_ref().join(path), data),
+        }
+    }
+
+    /// The seed used for the RNG when building the stages of the image named `name`.
+    fn seed(name: &str) -> u64 {
         // TMP, do a better seed fixing
-        let seed = name.chars().map(|c| c as u64).sum();
+        name.chars().map(|c| c as u64).sum()
+    }
 
+    /// The number of variations of `bd` to use for an image with the given `tags`, limited by the
+    /// executor's sample cap.
+    fn variations(&self, bd: &(dyn StageBuilder<P, R> + Send + Sync), tags: &Tags) -> usize {
+        let variations = bd.variations() * (bd.should_execute(tags) as usize);
+        variations.min(self.sample_cap.unwrap_or(usize::MAX))
+    }
+
+    /// Lazily builds out every combination of stages to execute on an image with the given
+    /// `tags`, building each stage with an RNG seeded by `seed`.
+    fn pipelines<'a>(&'a self, tags: &Tags, seed: u64) -> impl Iterator<Item = Pipeline<P>> + 'a {
+        let groups: Vec<_> = self.stages.iter().map(|bd| bd.conflict_group()).collect();
         self.stages
             .iter()
-            .map(|bd| bd.variations() * (bd.should_execute(tags) as usize))
+            .map(|bd| self.variations(&**bd, tags))
             .possibilities()
-            .map(|set| {
+            // Skip combinations executing several stages of the same conflict group
+            .filter(move |set| {
+                let mut executed = set
+                    .iter()
+                    .zip(&groups)
+                    .filter_map(|(&variant, group)| group.as_ref().filter(|_| variant > 0));
+                let mut seen = vec![];
+                executed.all(|group| {
+                    let conflict = seen.contains(&group);
+                    seen.push(group);
+                    !conflict
+                })
+            })
+            .map(move |set| {
                 set.into_iter()
                     .enumerate()
                     // This generates way more stages than used because we regenerate the variant every time,
@@ -95,24 +686,582 @@ where
                         if variant > 0 {
                             // I tried to make this `[variant]` at the end but for some bizarre reason
                             // it won't let me move out of the vector
-                            Some((variant, self.stages[idx].build_stage(&mut rng)))
+                            let mut stages = self.stages[idx].build_stage(&mut rng);
+                            stages.truncate(self.sample_cap.unwrap_or(usize::MAX));
+                            Some((variant, stages))
                         } else {
                             None
                         }
                     })
                     .collect::<Vec<_>>()
             })
+            .take(self.combination_limit.unwrap_or(usize::MAX))
+    }
+
+    /// Executes every stage of `pipeline` on `img` in order, yielding the outputs along with
+    /// `name` suffixed by the names of each stage, `tags` joined with the tags added by each
+    /// stage, and a record of each stage's name and exact parameters. Stages fanning out into
+    /// several outputs are expanded, with each of their outputs going through the rest of the
+    /// pipeline.
+    fn run_pipeline(
+        img: &Image<P>,
+        name: String,
+        tags: &Tags,
+        pipeline: Pipeline<P>,
+    ) -> Vec<Output<P>> {
+        let mut outputs = vec![(img.clone(), name, tags.clone(), vec![])];
+        for (variant, stage) in pipeline {
+            let stage = &stage[variant - 1];
+            outputs = outputs
+                .into_iter()
+                .flat_map(|(img, name, tags, records)| {
+                    let run = stage.run(&img);
+                    let stage_name = run.name.into_owned();
+                    let name = name + "_" + &stage_name;
+                    let params = run.params;
+                    let fan_out = run.outputs.len() > 1;
+                    run.outputs
+                        .into_iter()
+                        .enumerate()
+                        .map(move |(idx, (img, new_tags))| {
+                            let mut tags = tags.clone();
+                            tags.0.extend(new_tags.0);
+                            let mut records = records.clone();
+                            let mut record = json!({
+                                "stage": stage_name,
+                                "params": params,
+                            });
+                            if fan_out {
+                                record["output"] = idx.into();
+                                records.push(record);
+                                (img, format!("{}_{}", name, idx), tags, records)
+                            } else {
+                                records.push(record);
+                                (img, name.clone(), tags, records)
+                            }
+                        })
+                })
+                .collect();
+        }
+        outputs
+    }
+
+    /// Executes all pipelines for a single image, this is the workhorse that generates
+    /// all stage variations and then schedules them on rayon workers. Yields the paths of the
+    /// outputs skipped for lack of room for their file names.
+    fn all_pipelines(
+        &self,
+        tags: &Tags,
+        img: Image<P>,
+        source: &Path,
+        source_color: ColorType,
+        name: &str,
+        subdir: &Path,
+    ) -> Vec<PathBuf> {
+        // The output directory (or the image's subdirectory of it) may not exist yet
+        if self.sink.is_none() {
+            fs::create_dir_all(self.out_dir.as_ref().join(subdir)).unwrap();
+        }
+
+        let skipped = |name: &str| self.out_dir.as_ref().join(subdir).join(name);
+        self.pipelines(tags, Self::seed(name))
             .par_bridge()
-            .for_each(|stages| {
-                let mut name = name[..name.len().min(10)].to_owned();
-                let mut img = img.clone();
-                for (variant, stage) in stages {
-                    img = stage[variant - 1].execute(&img).0;
-                    name = name + "_" + &*stage[variant - 1].name();
+            .flat_map(|pipeline| {
+                let name = name[..name.len().min(10)].to_owned();
+                // Most stages are named the same whatever the image, so a name that can't fit is
+                // caught before executing anything
+                let planned = pipeline
+                    .iter()
+                    .fold(name.clone(), |planned, (variant, stage)| {
+                        planned + "_" + &stage[variant - 1].name()
+                    });
+                if self.file_name(&planned, subdir).is_none() {
+                    return vec![skipped(&planned)];
                 }
-                let mut path = self.out_dir.as_ref().to_path_buf();
-                path.push(name + ".png");
-                imageops::thumbnail(&img, 512, 512).save(path).unwrap();
-            });
+
+                let mut skips = vec![];
+                let outputs = Self::run_pipeline(&img, name, tags, pipeline);
+                for (mut img, name, tags, records) in outputs {
+                    if let Some(hook) = &self.post_hook {
+                        hook(&mut img);
+                    }
+                    if let Some(hook) = &self.on_output {
+                        hook(&mut img, &name, &tags);
+                    }
+                    let file_name = match self.file_name(&name, subdir) {
+                        Some(file_name) => file_name,
+                        None => {
+                            skips.push(skipped(&name));
+                            continue;
+                        }
+                    };
+                    let path = subdir.join(file_name);
+                    if self.provenance {
+                        let mut labels: Vec<_> = tags.0.iter().map(|label| label.text()).collect();
+                        labels.sort();
+                        let provenance = json!({
+                            "source": source.to_string_lossy(),
+                            "name": name,
+                            "tags": labels,
+                            "stages": records,
+                        });
+                        let data = serde_json::to_vec_pretty(&provenance).unwrap();
+                        self.write(&path.with_extension("json"), &data).unwrap();
+                    }
+                    self.save(imageops::thumbnail(&img, 512, 512), source_color, &path)
+                        .unwrap();
+                }
+                skips
+            })
+            .collect()
+    }
+}
+
+/// Resolves `color` into the concrete color type to save `img` with, i.e. anything but `Auto` or
+/// `Source`, where `source_color` is the color type of the image `img` was generated from.
+fn resolve_color(color: OutputColor, img: &DynamicImage, source_color: ColorType) -> OutputColor {
+    let (opaque, gray) = match color {
+        OutputColor::Auto => match img {
+            DynamicImage::ImageLuma8(_) => (true, true),
+            DynamicImage::ImageRgb8(img) => (
+                true,
+                img.pixels().all(|px| px[0] == px[1] && px[1] == px[2]),
+            ),
+            DynamicImage::ImageRgba8(img) => (
+                img.pixels().all(|px| px[3] == u8::MAX),
+                img.pixels().all(|px| px[0] == px[1] && px[1] == px[2]),
+            ),
+            img => {
+                let img = img.to_rgba8();
+                (
+                    img.pixels().all(|px| px[3] == u8::MAX),
+                    img.pixels().all(|px| px[0] == px[1] && px[1] == px[2]),
+                )
+            }
+        },
+        OutputColor::Source => (!source_color.has_alpha(), !source_color.has_color()),
+        color => return color,
+    };
+    match (opaque, gray) {
+        (true, true) => OutputColor::Luma8,
+        (true, false) => OutputColor::Rgb8,
+        (false, true) => OutputColor::LumaA8,
+        (false, false) => OutputColor::Rgba8,
+    }
+}
+
+/// The per-stage contributions to the number of outputs generated for a single image, as returned
+/// by [`ParallelStageExecutor::combination_breakdown`]. Its `Display` implementation yields a summary
+/// such as `"BlurBuilder: x3, RotationBuilder: x4, total: 12 per image"`.
+///
+/// [`ParallelStageExecutor::combination_breakdown`]: about:blank
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CombinationBreakdown {
+    /// The name of each stage builder, along with the factor it multiplies the output count by.
+    pub stages: Vec<(String, usize)>,
+    /// The total number of outputs per image, including the unmodified image.
+    pub total: usize,
+}
+
+impl fmt::Display for CombinationBreakdown {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, factor) in &self.stages {
+            write!(f, "{}: x{}, ", name, factor)?;
+        }
+        write!(f, "total: {} per image", self.total)
+    }
+}
+
+/// The outcome of validating each stage of an executor on a sample image, as returned by
+/// [`ParallelStageExecutor::validate`]. Its `Display` implementation yields a line per stage, such
+/// as `"BlurBuilder: ok"` or `"OffAxisRotationBuilder: panicked: <message>"`.
+///
+/// [`ParallelStageExecutor::validate`]: about:blank
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ValidationReport {
+    /// The name of each stage builder, along with the panic message if building or executing
+    /// its stage panicked.
+    pub stages: Vec<(String, Option<String>)>,
+}
+
+impl ValidationReport {
+    /// Whether every stage was built and executed without panicking.
+    pub fn is_ok(&self) -> bool {
+        self.stages.iter().all(|(_, error)| error.is_none())
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, error) in &self.stages {
+            match error {
+                Some(error) => writeln!(f, "{}: panicked: {}", name, error)?,
+                None => writeln!(f, "{}: ok", name)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{env, fs};
+
+    use image::{ColorType, DynamicImage, Luma, Rgb, Rgba};
+    use imageproc::{definitions::Image, geometric_transformations::Interpolation};
+    use rand::rngs::StdRng;
+    use serde_json::{json, Value};
+
+    use crate::executors::{resolve_color, OutputColor, ParallelStageExecutor};
+    use crate::stages::{
+        AspectDistortBuilder, BitCrushBuilder, BlurBuilder, DeskewBuilder, ElasticDeformBuilder,
+        Fill, FixedAngleRotationBuilder, GaussianNoiseBuilder, LevelsBuilder, MedianFilterBuilder,
+        MirrorBuilder, OffAxisRotationBuilder, PixelateBuilder, RandomResizedCropBuilder,
+        ReflectPadBuilder, RotationBuilder, ShadowHighlightBuilder, SharpenBuilder, SwirlBuilder,
+        ZoomBuilder,
+    };
+    use crate::{TaggedImage, Tags};
+
+    #[test]
+    fn rgb_jpeg_round_trip() {
+        let dir = env::temp_dir().join(format!("image-permute-rgb-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("photo.jpg");
+        Image::from_fn(48, 32, |x, y| Rgb([x as u8 * 5, y as u8 * 7, 128]))
+            .save(&input)
+            .unwrap();
+        let fill = Rgb([0, 0, 0]);
+
+        // Every stage builder generic over the pixel type, which mustn't assume an alpha channel
+        let executor: ParallelStageExecutor<StdRng, _, Rgb<u8>> =
+            ParallelStageExecutor::new(dir.join("unused"))
+                .add_stage(Box::new(OffAxisRotationBuilder {
+                    samples: 1,
+                    deg_limit: 20.,
+                    center: None,
+                    expand: true,
+                    fill,
+                }))
+                .add_stage(Box::new(FixedAngleRotationBuilder::new(
+                    vec![-10., 10.],
+                    Interpolation::Bilinear,
+                    fill,
+                )))
+                .add_stage(Box::new(DeskewBuilder {
+                    threshold_deg: 0.,
+                    fill,
+                }))
+                .add_stage(Box::new(RotationBuilder::default()))
+                .add_stage(Box::new(MirrorBuilder))
+                .add_stage(Box::new(RandomResizedCropBuilder {
+                    samples: 1,
+                    scale_range: 0.3..=0.9,
+                    ratio_range: 0.75..=1.33,
+                    filter: image::imageops::FilterType::Triangle,
+                }))
+                .add_stage(Box::new(AspectDistortBuilder::new(1, 0.2, false, fill)))
+                .add_stage(Box::new(ReflectPadBuilder {
+                    samples: 1,
+                    min_pad_frac: 0.1,
+                    max_pad_frac: 0.3,
+                    symmetric: false,
+                }))
+                .add_stage(Box::new(ZoomBuilder {
+                    samples: 1,
+                    min_factor: 0.8,
+                    max_factor: 1.2,
+                    fill: Fill::Reflect,
+                }))
+                .add_stage(Box::new(BlurBuilder {
+                    samples: 1,
+                    min_sigma: 1.,
+                    max_sigma: 2.,
+                }))
+                .add_stage(Box::new(SharpenBuilder {
+                    samples: 1,
+                    min_sigma: 1.,
+                    max_sigma: 2.,
+                    min_amount: 0.5,
+                    max_amount: 1.,
+                }))
+                .add_stage(Box::new(MedianFilterBuilder {
+                    samples: 1,
+                    min_radius: 1,
+                    max_radius: 2,
+                }))
+                .add_stage(Box::new(PixelateBuilder {
+                    samples: 1,
+                    min_block: 2,
+                    max_block: 4,
+                }))
+                .add_stage(Box::new(GaussianNoiseBuilder {
+                    samples: 1,
+                    min_stddev: 5.,
+                    max_stddev: 10.,
+                }))
+                .add_stage(Box::new(ElasticDeformBuilder {
+                    samples: 1,
+                    alpha_range: 5.0..=10.,
+                    sigma_range: 3.0..=4.,
+                }))
+                .add_stage(Box::new(SwirlBuilder {
+                    samples: 1,
+                    min_strength: 1.,
+                    max_strength: 2.,
+                    radius_frac: 0.5,
+                }))
+                .add_stage(Box::new(BitCrushBuilder::new(1, 2, 5)))
+                .add_stage(Box::new(ShadowHighlightBuilder {
+                    samples: 1,
+                    shadow_range: -20..=20,
+                    highlight_range: -20..=20,
+                }))
+                .add_stage(Box::new(LevelsBuilder::new(1, 20, 20)));
+        let (sample, color) = executor.load(&input).unwrap();
+        assert_eq!(color, ColorType::Rgb8);
+        let report = executor.validate(&sample);
+        assert!(report.is_ok(), "{}", report);
+
+        let out_dir = dir.join("out");
+        let executor: ParallelStageExecutor<StdRng, _, Rgb<u8>> =
+            ParallelStageExecutor::new(out_dir.clone())
+                .add_stage(Box::new(MirrorBuilder))
+                .add_stage(Box::new(OffAxisRotationBuilder {
+                    samples: 1,
+                    deg_limit: 20.,
+                    center: None,
+                    expand: false,
+                    fill,
+                }));
+        executor.execute(vec![TaggedImage {
+            img: input,
+            tags: Tags::default(),
+        }]);
+
+        let outputs: Vec<_> = fs::read_dir(&out_dir).unwrap().collect();
+        assert_eq!(outputs.len(), 6);
+        for output in outputs {
+            let output = image::open(output.unwrap().path()).unwrap();
+            assert_eq!(output.color(), ColorType::Rgb8);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn grayscale_executor_end_to_end() {
+        let dir = env::temp_dir().join(format!("image-permute-luma-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("gray.png");
+        Image::from_fn(16, 16, |x, y| Luma([(x * 16 + y) as u8]))
+            .save(&input)
+            .unwrap();
+
+        let out_dir = dir.join("out");
+        let executor: ParallelStageExecutor<StdRng, _, Luma<u8>> =
+            ParallelStageExecutor::new(out_dir.clone()).add_stage(Box::new(BlurBuilder {
+                samples: 1,
+                min_sigma: 1.,
+                max_sigma: 2.,
+            }));
+        executor.execute(vec![TaggedImage {
+            img: input,
+            tags: Tags::default(),
+        }]);
+
+        let outputs: Vec<_> = fs::read_dir(&out_dir).unwrap().collect();
+        assert_eq!(outputs.len(), 2);
+        for output in outputs {
+            let output = image::open(output.unwrap().path()).unwrap();
+            assert_eq!(output.color(), ColorType::L8);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn conflicting_stages_never_combined() {
+        let executor: ParallelStageExecutor<StdRng, _, Rgba<u8>> =
+            ParallelStageExecutor::new(env::temp_dir())
+                .add_stage(Box::new(BlurBuilder {
+                    samples: 2,
+                    min_sigma: 1.,
+                    max_sigma: 2.,
+                }))
+                .add_stage(Box::new(SharpenBuilder {
+                    samples: 2,
+                    min_sigma: 1.,
+                    max_sigma: 2.,
+                    min_amount: 0.5,
+                    max_amount: 1.,
+                }))
+                .add_stage(Box::new(MirrorBuilder));
+
+        let pipelines: Vec<Vec<String>> = executor
+            .pipelines(&Tags::default(), 0)
+            .map(|pipeline| {
+                pipeline
+                    .iter()
+                    .map(|(variant, stage)| stage[variant - 1].name().into_owned())
+                    .collect()
+            })
+            .collect();
+        for names in &pipelines {
+            let blurred = names.iter().any(|name| name.starts_with("blur_"));
+            let sharpened = names.iter().any(|name| name.starts_with("sharp_"));
+            assert!(!(blurred && sharpened), "{:?}", names);
+        }
+        // Each of the 2 + 2 sharpness stages or neither, times each of the 2 mirrors or neither
+        assert_eq!(pipelines.len(), 15);
+        assert_eq!(
+            executor.combination_breakdown(&Tags::default()).total,
+            pipelines.len()
+        );
+    }
+
+    #[test]
+    fn combination_limit_is_stable() {
+        let dir = env::temp_dir().join(format!("image-permute-limit-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("photo.png");
+        Image::from_fn(16, 16, |x, y| Rgba([x as u8 * 16, y as u8 * 16, 128, 255]))
+            .save(&input)
+            .unwrap();
+
+        let outputs = |run: &str, n: usize| {
+            let out_dir = dir.join(run);
+            let executor: ParallelStageExecutor<StdRng, _, Rgba<u8>> =
+                ParallelStageExecutor::new(out_dir.clone())
+                    .add_stage(Box::new(BlurBuilder {
+                        samples: 3,
+                        min_sigma: 1.,
+                        max_sigma: 2.,
+                    }))
+                    .add_stage(Box::new(MirrorBuilder))
+                    .take_combinations(n);
+            executor.execute(vec![TaggedImage {
+                img: input.clone(),
+                tags: Tags::default(),
+            }]);
+            let mut names: Vec<_> = fs::read_dir(&out_dir)
+                .unwrap()
+                .map(|entry| entry.unwrap().file_name())
+                .collect();
+            names.sort();
+            names
+        };
+
+        let first = outputs("first", 5);
+        assert_eq!(first.len(), 5);
+        assert_eq!(outputs("second", 5), first);
+        let more = outputs("more", 9);
+        assert_eq!(more.len(), 9);
+        assert!(first.iter().all(|name| more.contains(name)));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn sample_cap_limits_variations() {
+        let executor: ParallelStageExecutor<StdRng, _, Rgba<u8>> =
+            ParallelStageExecutor::new(env::temp_dir())
+                .add_stage(Box::new(BlurBuilder {
+                    samples: 5,
+                    min_sigma: 1.,
+                    max_sigma: 2.,
+                }))
+                .with_sample_cap(Some(2));
+
+        let breakdown = executor.combination_breakdown(&Tags::default());
+        assert_eq!(breakdown.stages[0].1, 3);
+        assert_eq!(breakdown.total, 3);
+        let pipelines: Vec<_> = executor.pipelines(&Tags::default(), 0).collect();
+        assert_eq!(pipelines.len(), 3);
+        for (_, stages) in pipelines.iter().flatten() {
+            assert_eq!(stages.len(), 2);
+        }
+    }
+
+    #[test]
+    fn provenance_sidecars() {
+        let dir = env::temp_dir().join(format!("image-permute-prov-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("photo.png");
+        Image::from_pixel(8, 8, Rgba([200u8, 100, 50, 255]))
+            .save(&input)
+            .unwrap();
+
+        let out_dir = dir.join("out");
+        let executor: ParallelStageExecutor<StdRng, _, Rgba<u8>> =
+            ParallelStageExecutor::new(out_dir.clone())
+                .add_stage(Box::new(BlurBuilder {
+                    samples: 1,
+                    min_sigma: 1.,
+                    max_sigma: 2.,
+                }))
+                .with_provenance(true);
+        executor.execute(vec![TaggedImage {
+            img: input.clone(),
+            tags: Tags::default(),
+        }]);
+
+        let mut sidecars: Vec<_> = fs::read_dir(&out_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().map_or(false, |ext| ext == "json"))
+            .collect();
+        sidecars.sort();
+        assert_eq!(sidecars.len(), 2);
+        let records: Vec<Value> = sidecars
+            .iter()
+            .map(|path| serde_json::from_slice(&fs::read(path).unwrap()).unwrap())
+            .collect();
+        for record in &records {
+            assert_eq!(record["source"], input.to_string_lossy().as_ref());
+        }
+
+        // The unmodified image, then its blurred variant
+        assert_eq!(records[0]["tags"], json!([]));
+        assert_eq!(records[0]["stages"], json!([]));
+        assert_eq!(records[1]["tags"], json!(["Blurred"]));
+        let stages = records[1]["stages"].as_array().unwrap();
+        assert_eq!(stages.len(), 1);
+        let sigma = stages[0]["params"]["sigma"].as_f64().unwrap();
+        assert!((1. ..2.).contains(&sigma));
+        assert_eq!(stages[0]["stage"], format!("blur_{:0.2}", sigma));
+        assert!(sidecars[1].with_extension("png").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn grayscale_coerced_to_rgba() {
+        let img = DynamicImage::ImageRgba8(Image::from_pixel(2, 2, Rgba([40, 40, 40, 255])));
+        assert_eq!(
+            resolve_color(OutputColor::default(), &img, ColorType::L8),
+            OutputColor::Rgba8
+        );
+        assert_eq!(
+            resolve_color(OutputColor::Auto, &img, ColorType::L8),
+            OutputColor::Luma8
+        );
+    }
+
+    #[test]
+    fn source_color_preserved() {
+        // Color added by a stage is dropped to match the source
+        let img = DynamicImage::ImageRgba8(Image::from_pixel(2, 2, Rgba([255, 0, 0, 128])));
+        assert_eq!(
+            resolve_color(OutputColor::Source, &img, ColorType::L8),
+            OutputColor::Luma8
+        );
+        assert_eq!(
+            resolve_color(OutputColor::Source, &img, ColorType::La8),
+            OutputColor::LumaA8
+        );
+        assert_eq!(
+            resolve_color(OutputColor::Source, &img, ColorType::Rgb16),
+            OutputColor::Rgb8
+        );
+        assert_eq!(
+            resolve_color(OutputColor::Source, &img, ColorType::Rgba8),
+            OutputColor::Rgba8
+        );
     }
 }
@@ -0,0 +1,40 @@
+//! A shared view of the images an executor is processing, for stages which combine several inputs
+//! (such as mixup) rather than transforming each one on its own.
+
+use std::{
+    path::PathBuf,
+    sync::{Arc, RwLock},
+};
+
+/// The paths of every input image given to an executor, filled in by the executor when it starts
+/// executing. Handles are cheap to clone and all share the same list, so builders can be handed one
+/// (via [`ParallelStageExecutor::input_pool`]) before the inputs are known.
+///
+/// [`ParallelStageExecutor::input_pool`]: about:blank
+#[derive(Clone, Default, Debug)]
+pub struct InputPool {
+    /// The paths of the input images, sorted.
+    paths: Arc<RwLock<Vec<PathBuf>>>,
+}
+
+impl InputPool {
+    /// Replaces the paths in the pool with `paths`.
+    pub(crate) fn set<I: IntoIterator<Item = PathBuf>>(&self, paths: I) {
+        *self.paths.write().unwrap() = paths.into_iter().collect();
+    }
+
+    /// The number of input images.
+    pub fn len(&self) -> usize {
+        self.paths.read().unwrap().len()
+    }
+
+    /// Whether there are no input images, as is the case before the executor starts executing.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The path of the input image at `idx`, if there is one.
+    pub fn get(&self, idx: usize) -> Option<PathBuf> {
+        self.paths.read().unwrap().get(idx).cloned()
+    }
+}
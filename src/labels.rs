@@ -0,0 +1,256 @@
+//! The labels which make up an image's [`Tags`], used to determine whether a stage should execute.
+//!
+//! [`Tags`]: about:blank
+
+use std::{borrow::Cow, fmt};
+
+/// Declares the `Label` enum, with a variant for each built-in label along with its human
+/// readable text, plus a `Custom` variant for any other label.
+macro_rules! labels {
+    ($($(#[$doc:meta])* $variant:ident => $text:literal,)*) => {
+        /// A label applied to an image, either by a stage or by the user when the image is loaded.
+        /// Built-in labels are typed so that checking for them is refactor-friendly and typos are
+        /// caught by the compiler, while `Custom` covers anything else.
+        #[derive(Clone, PartialEq, Eq, Hash, Debug)]
+        pub enum Label {
+            $($(#[$doc])* $variant,)*
+            /// Compressed as a JPEG with the given quality.
+            JpegQuality(u8),
+            /// Exposure shifted by the given number of stops.
+            Exposure(i8),
+            /// A label which isn't built in.
+            Custom(String),
+        }
+
+        impl Label {
+            /// Every built-in label without any parameters, i.e. everything but `JpegQuality`,
+            /// `Exposure` and `Custom` labels.
+            pub const BUILTIN: &'static [Label] = &[$(Label::$variant,)*];
+
+            /// The human readable text of the label.
+            pub fn text(&self) -> Cow<str> {
+                match self {
+                    $(Label::$variant => $text.into(),)*
+                    Label::JpegQuality(quality) => format!("JPEG q{}", quality).into(),
+                    Label::Exposure(ev) => format!("Exposure {}", ev).into(),
+                    Label::Custom(text) => text.as_str().into(),
+                }
+            }
+        }
+
+        impl From<String> for Label {
+            /// Parses the text of a label, yielding the matching built-in label if there is one,
+            /// or a `Custom` label otherwise.
+            fn from(text: String) -> Self {
+                match text.as_str() {
+                    $($text => Label::$variant,)*
+                    _ => {
+                        if let Some(quality) = text.strip_prefix("JPEG q").and_then(|q| q.parse().ok()) {
+                            Label::JpegQuality(quality)
+                        } else if let Some(ev) = text.strip_prefix("Exposure ").and_then(|ev| ev.parse().ok()) {
+                            Label::Exposure(ev)
+                        } else {
+                            Label::Custom(text)
+                        }
+                    }
+                }
+            }
+        }
+    };
+}
+
+labels! {
+    /// Rotated 90 degrees clockwise.
+    Clockwise => "Rotated 90 degrees clockwise",
+    /// Rotated 90 degrees counterclockwise.
+    Cclockwise => "Rotated 90 degrees counterclockwise",
+    /// Rotated 180 degrees.
+    UpsideDown => "Upside-down",
+    /// Mirrored left to right.
+    MirroredH => "Mirrored horizontally",
+    /// Mirrored top to bottom.
+    MirroredV => "Mirrored vertically",
+    /// Rotated by an arbitrary angle.
+    OffAxis => "Rotated off-axis",
+    /// Cropped to a region of the image.
+    Cropped => "Cropped",
+    /// Cropped of its uniform margins.
+    AutoCropped => "Auto-cropped",
+    /// Padded with bars to a different aspect ratio.
+    Padded => "Padded",
+    /// Scaled to a different resolution.
+    Resized => "Resized",
+    /// Stretched or squashed, changing the aspect ratio of its content.
+    AspectDistorted => "Aspect distorted",
+    /// Degraded to a lower resolution, keeping its dimensions.
+    LowRes => "Low resolution",
+    /// Shifted along either axis.
+    Translated => "Translated",
+    /// Slanted along either axis.
+    Sheared => "Sheared",
+    /// Warped by a smooth random displacement field.
+    Elastic => "Elastically deformed",
+    /// Warped by randomly perturbing a grid laid over it.
+    GridDistorted => "Grid distorted",
+    /// Twisted into a whirlpool about its center.
+    Swirled => "Swirled",
+    /// Rippled by a sinusoidal wave.
+    Waved => "Waved",
+    /// Distorted radially, as if by a barrel or pincushion lens.
+    LensDistorted => "Lens distorted",
+    /// Projected through a fisheye lens.
+    Fisheye => "Fisheye",
+    /// Scaled about its center, keeping its dimensions.
+    Zoomed => "Zoomed",
+    /// Brightened.
+    Bright => "Bright",
+    /// Darkened.
+    Dark => "Dark",
+    /// Brightened or darkened by a different amount in each color channel.
+    ChannelShifted => "Channel shifted",
+    /// Had its red and blue channels offset in opposite directions.
+    ChannelOffset => "Channel offset",
+    /// Streaked with falling rain.
+    Rain => "Rain",
+    /// Hazed over with fog.
+    Fog => "Fog",
+    /// Washed with the flare of the sun shining into the lens.
+    SunFlare => "Sun flare",
+    /// Had its black and white points moved.
+    Levels => "Levels",
+    /// Adjusted in shadows and highlights with a tone curve.
+    ToneCurve => "Tone curve",
+    /// Blurred as a whole.
+    Blurred => "Blurred",
+    /// Sharpened with an unsharp mask.
+    Sharpened => "Sharpened",
+    /// Pixelated into a mosaic of blocks.
+    Pixelated => "Pixelated",
+    /// Smoothed with a median filter.
+    MedianFiltered => "Median filtered",
+    /// Replaced by a map of its edges.
+    SobelEdges => "Sobel edges",
+    /// Reduced to black and white at a luma threshold.
+    Binarized => "Binarized",
+    /// Flattened into smooth regions outlined in dark edges, like a cartoon.
+    Cartoon => "Cartoon",
+    /// Partially covered by shapes.
+    Occluded => "Occluded",
+    /// Had rectangular holes cut out of it.
+    Cutout => "Cut out",
+    /// Overlaid with a watermark.
+    Watermarked => "Watermarked",
+    /// Overlaid with text.
+    TextOverlay => "Text overlay",
+    /// Framed with a solid border.
+    Bordered => "Bordered",
+    /// Split into a grid of patches which were shuffled.
+    PatchShuffled => "Patch shuffled",
+    /// Blended with another input image.
+    Mixed => "Mixed up",
+    /// Tiled into a 2x2 mosaic with other input images.
+    Mosaic => "Mosaic",
+    /// Striped with scanlines, as on a CRT or interlaced video.
+    Scanlines => "Scanlines",
+    /// Corrupted with shifted slices, like a glitching video stream.
+    Glitched => "Glitched",
+    /// Tinted with a rainbow.
+    Rainbow => "Rainbow",
+    /// Overlaid with a reference grid.
+    Grid => "Grid",
+    /// Blurred in a region.
+    RegionBlurred => "Region blurred",
+    /// Color fringed, as if by a cheap lens.
+    ChromaticAberration => "Chromatic aberration",
+    /// Split into tiles.
+    Tiled => "Tiled",
+    /// Posterized with an ordered dither, for a lo-fi look.
+    Retro => "Retro",
+    /// Reduced to a small palette of colors.
+    Quantized => "Quantized",
+    /// Reduced in bit depth, banding smooth gradients.
+    Banded => "Banded",
+    /// Jittered in hue, saturation and value.
+    HsvJittered => "HSV jittered",
+    /// Raised or lowered in contrast.
+    Contrast => "Contrast adjusted",
+    /// Shifted in hue.
+    HueShifted => "Hue shifted",
+    /// Saturated or desaturated.
+    SaturationJittered => "Saturation jittered",
+    /// Shifted in white balance, as if lit warmer or cooler.
+    WhiteBalanced => "White balance shifted",
+    /// Given a color cast by blending it toward a single color.
+    Tinted => "Tinted",
+    /// Toned sepia, like an old photograph.
+    Sepia => "Sepia",
+    /// Toned with one or more colors in place of its own, like a duotone.
+    Toned => "Toned",
+    /// Made more or less transparent.
+    AlphaJittered => "Alpha jittered",
+    /// Composited over a solid background, leaving it fully opaque.
+    Flattened => "Flattened",
+    /// Overlaid with film grain.
+    Grain => "Film grain",
+    /// Scratched and specked with dust, like a scan of damaged film.
+    FilmDamage => "Film damage",
+    /// Speckled with additive noise.
+    Noisy => "Noisy",
+    /// Speckled with noise, as if by a camera sensor in low light.
+    SensorNoise => "Sensor noise",
+}
+
+impl From<&str> for Label {
+    fn from(text: &str) -> Self {
+        text.to_owned().into()
+    }
+}
+
+impl fmt::Display for Label {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::labels::Label;
+
+    #[test]
+    fn builtin_round_trip() {
+        for label in Label::BUILTIN {
+            assert_eq!(&Label::from(label.text().into_owned()), label);
+        }
+    }
+
+    #[test]
+    fn jpeg_quality_round_trip() {
+        assert_eq!(Label::JpegQuality(30).text(), "JPEG q30");
+        assert_eq!(Label::from("JPEG q30"), Label::JpegQuality(30));
+        assert_eq!(
+            Label::from("JPEG q300"),
+            Label::Custom("JPEG q300".to_owned())
+        );
+    }
+
+    #[test]
+    fn exposure_round_trip() {
+        assert_eq!(Label::Exposure(-2).text(), "Exposure -2");
+        assert_eq!(Label::from("Exposure -2"), Label::Exposure(-2));
+        assert_eq!(Label::from("Exposure 1"), Label::Exposure(1));
+        assert_eq!(
+            Label::from("Exposure x"),
+            Label::Custom("Exposure x".to_owned())
+        );
+    }
+
+    #[test]
+    fn custom_label() {
+        assert_eq!(
+            Label::from("Vignetted"),
+            Label::Custom("Vignetted".to_owned())
+        );
+        assert_eq!(Label::from("Sepia"), Label::Sepia);
+        assert_eq!(Label::from("Blurred"), Label::Blurred);
+    }
+}
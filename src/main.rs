@@ -7,22 +7,32 @@ use glob::glob;
 use rand::prelude::*;
 
 mod executors;
+mod inputs;
+mod labels;
+mod sinks;
 mod stages;
 mod traits;
 mod util;
 
 use std::{collections::HashSet, fs, iter::Iterator, path::Path};
 
+use crate::labels::Label;
 use crate::stages::BlurBuilder;
 
 /// A newtype over a `HashSet` meant to contain image labels used
 /// to determine if a stage should be executed on an image or not.
 #[derive(Clone, PartialEq, Eq, Default, Debug)]
-struct Tags(pub HashSet<String>);
+struct Tags(pub HashSet<Label>);
+
+impl From<HashSet<Label>> for Tags {
+    fn from(el: HashSet<Label>) -> Self {
+        Self(el)
+    }
+}
 
 impl From<HashSet<String>> for Tags {
     fn from(el: HashSet<String>) -> Self {
-        Self(el)
+        Self(el.into_iter().map(Label::from).collect())
     }
 }
 
@@ -44,7 +54,7 @@ impl<P: AsRef<Path>> TaggedImage<P> {
     fn from_iter<I: IntoIterator<Item = String>>(path: P, tags: I) -> Self {
         Self {
             img: path,
-            tags: Tags(tags.into_iter().collect()),
+            tags: Tags(tags.into_iter().map(Label::from).collect()),
         }
     }
 }
@@ -59,15 +69,15 @@ fn main() {
         .collect();
 
     let transformer: ParallelStageExecutor<StdRng, _> = ParallelStageExecutor::new("./processed")
+        .with_exif_orientation(true)
         .add_stage(Box::new(BlurBuilder {
             samples: 1,
             min_sigma: 5.,
             max_sigma: 10.,
         }))
-        .add_stage(Box::new(RotationBuilder));
+        .add_stage(Box::new(RotationBuilder::default()));
 
     fs::remove_dir_all("./processed").unwrap_or(());
-    fs::create_dir("./processed").unwrap_or(());
 
     transformer.execute(files);
 }
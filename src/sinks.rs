@@ -0,0 +1,86 @@
+//! Output sinks that executors can write their encoded outputs to, instead of saving them as
+//! loose files.
+
+use std::{fs::File, io, path::Path, sync::Mutex};
+
+use crate::traits::OutputSink;
+
+/// Writes every output into a single (uncompressed) tar archive, which is far easier to move around
+/// than hundreds of thousands of loose files. Writes are serialized by a mutex, since only one entry
+/// can be appended to the archive at a time.
+///
+/// The archive is finished when the sink is dropped, or explicitly with [`finish`] to handle errors.
+///
+/// [`finish`]: about:blank
+pub struct TarSink {
+    /// The archive being written to.
+    builder: Mutex<tar::Builder<File>>,
+}
+
+impl TarSink {
+    /// Creates a new archive at `path`, truncating it if it already exists.
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(Self {
+            builder: Mutex::new(tar::Builder::new(File::create(path)?)),
+        })
+    }
+
+    /// Finishes writing the archive.
+    pub fn finish(self) -> io::Result<()> {
+        self.builder.into_inner().unwrap().finish()
+    }
+}
+
+impl OutputSink for TarSink {
+    fn write(&self, path: &Path, data: &[u8]) -> io::Result<()> {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+
+        self.builder
+            .lock()
+            .unwrap()
+            .append_data(&mut header, path, data)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{env, fs, io::Read, path::Path};
+
+    use crate::sinks::TarSink;
+    use crate::traits::OutputSink;
+
+    #[test]
+    fn tar_round_trip() {
+        let path = env::temp_dir().join(format!("image-permute-sink-{}.tar", std::process::id()));
+        let sink = TarSink::create(&path).unwrap();
+        sink.write(Path::new("photo.png"), b"first").unwrap();
+        sink.write(Path::new("photo-1234abcd/photo_blur_1.50.png"), b"second")
+            .unwrap();
+        sink.finish().unwrap();
+
+        let mut archive = tar::Archive::new(fs::File::open(&path).unwrap());
+        let entries: Vec<_> = archive
+            .entries()
+            .unwrap()
+            .map(|entry| {
+                let mut entry = entry.unwrap();
+                let mut data = vec![];
+                entry.read_to_end(&mut data).unwrap();
+                (entry.path().unwrap().into_owned(), data)
+            })
+            .collect();
+        assert_eq!(
+            entries,
+            vec![
+                (Path::new("photo.png").to_owned(), b"first".to_vec()),
+                (
+                    Path::new("photo-1234abcd/photo_blur_1.50.png").to_owned(),
+                    b"second".to_vec()
+                ),
+            ]
+        );
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -3,38 +3,53 @@
 
 use std::f64::consts::PI;
 use std::iter::FromIterator;
+use std::ops::{Range, RangeInclusive};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::{borrow::Cow, collections::HashSet};
+use std::{fs, io};
 
 use conv::ValueInto;
-use image::imageops::colorops;
-use image::{imageops, Pixel};
+use image::codecs::jpeg::JpegEncoder;
+use image::error::{ImageError, ParameterError, ParameterErrorKind};
+use image::imageops::{colorops, FilterType};
+use image::{
+    imageops, ColorType, DynamicImage, GenericImage, GrayImage, ImageFormat, ImageResult, Luma,
+    Pixel, Rgb, Rgba,
+};
 use imageproc::{
+    contrast::otsu_level,
     definitions::{Clamp, Image},
+    drawing::{
+        draw_antialiased_line_segment_mut, draw_filled_circle_mut, draw_filled_ellipse_mut,
+        draw_filled_rect_mut, draw_polygon_mut, draw_text_mut,
+    },
+    filter::median_filter,
     geometric_transformations,
-    geometric_transformations::Interpolation,
+    geometric_transformations::{Interpolation, Projection},
+    gradients::sobel_gradients,
+    pixelops::interpolate,
+    point::Point,
+    rect::Rect,
 };
+use num::Bounded;
 use rand::distributions::Uniform;
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rand_distr::{Distribution, Normal, Poisson};
+use rusttype::{point, Font, Scale};
+use serde_json::{json, Value};
 
-use crate::traits::{ImageStage, StageBuilder};
+use crate::inputs::InputPool;
+use crate::labels::Label;
+use crate::traits::{ImageStage, StageBuilder, StageDescriptor, StageRun};
+use crate::util::{
+    color_channels, hsv_to_rgb, lerp_pixel, linear_to_srgb, rgb_to_hsv, rotated_bounds,
+    sample_bilinear, scale_saturation, smoothstep, srgb_to_linear, stable_hash,
+};
 use crate::Tags;
 
-/* Label constants for different tags, should be moved into a config file eventually */
-
-mod consts {
-    #![allow(clippy::missing_docs_in_private_items)]
-
-    pub(super) const CWISE_LABEL: &str = "Rotated 90 degrees clockwise";
-    pub(super) const CCWISE_LABEL: &str = "Rotated 90 degrees counterclockwise";
-    pub(super) const UPSIDE_DOWN_LABEL: &str = "Upside-down";
-    pub(super) const OFF_AXIS_LABEL: &str = "Rotated off-axis";
-    pub(super) const BRIGHTEN_LABEL: &str = "Bright";
-    pub(super) const DARKEN_LABEL: &str = "Dark";
-    pub(super) const BLURRED_LABEL: &str = "Blurred";
-}
-
-use consts::*;
-
 /// Converts the radians `rad` to degrees.
 fn rad_to_deg(rad: f64) -> f64 {
     rad * 180. / PI
@@ -49,21 +64,33 @@ fn deg_to_rad(deg: f64) -> f64 {
 /// (without changing the dimensions) between `-deg_limit` and `deg_limit` degrees. It's recommended
 /// this value be less than 90, and to combine this stage with `RotationBuilder` for off-axis rotations
 /// larger than that. In practice, generally a less extreme value (probably under 30 degrees) is preferable.
-pub struct OffAxisRotationBuilder {
+///
+/// Images are rotated about their center, unless another `center` is given for subjects which
+/// aren't centered. With `expand` set, the canvas instead grows to fit the whole rotated image, so
+/// no corners are lost (and `center` makes no difference). Either way, the uncovered background is
+/// filled with `fill`.
+pub struct OffAxisRotationBuilder<P: Pixel> {
     /// The number of variations to build when `build_stage` is called.
     pub samples: usize,
     /// The maximum number of degrees in either direction which a generated stage may rotate an image.
     pub deg_limit: f64,
+    /// The point to rotate about, relative to the image's width and height (so `(0.5, 0.5)` is
+    /// its center and `(0.0, 0.0)` its top-left corner), or `None` to rotate about the center.
+    pub center: Option<(f32, f32)>,
+    /// Whether to enlarge the canvas to the bounds of the rotated image.
+    pub expand: bool,
+    /// The color of the background uncovered by the rotation.
+    pub fill: P,
 }
 
-impl<P, R> StageBuilder<P, R> for OffAxisRotationBuilder
+impl<P, R> StageBuilder<P, R> for OffAxisRotationBuilder<P>
 where
     P: Pixel + Send + Sync + 'static,
-    <P as Pixel>::Subpixel: Default + Send + Sync + ValueInto<f32> + Clamp<f32>,
+    <P as Pixel>::Subpixel: Send + Sync + ValueInto<f32> + Clamp<f32>,
     R: Rng,
 {
     fn should_execute(&self, tags: &Tags) -> bool {
-        !tags.0.contains(OFF_AXIS_LABEL)
+        !tags.0.contains(&Label::OffAxis)
     }
 
     fn variations(&self) -> usize {
@@ -77,51 +104,425 @@ where
         rng.sample_iter(Uniform::from(range))
             .take(self.samples)
             .map(|radians| {
-                Box::new(OffAxisStage { radians }) as Box<dyn ImageStage<_> + Send + Sync>
+                Box::new(OffAxisStage {
+                    radians,
+                    center: self.center,
+                    expand: self.expand,
+                    interpolation: Interpolation::Bicubic,
+                    fill: self.fill,
+                }) as Box<dyn ImageStage<_> + Send + Sync>
             })
             .collect()
     }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        let name = if self.expand {
+            "rot_{degrees}_deg_exp"
+        } else {
+            "rot_{degrees}_deg"
+        };
+        vec![StageDescriptor::new(name, [Label::OffAxis])]
+    }
 }
 
 /// The actual stage that rotates the image, upon `execute` it will return a new image
-/// rotated about the center by `radians` degrees.
-pub struct OffAxisStage {
+/// rotated about `center` (or the image's center) by `radians` degrees. When `expand` is set the
+/// image is always rotated about its center, onto a canvas large enough to hold all of it.
+pub struct OffAxisStage<P: Pixel> {
     /// The number of radians to rotate by.
     radians: f64,
+    /// The point to rotate about relative to the image's dimensions, if not its center.
+    center: Option<(f32, f32)>,
+    /// Whether to enlarge the canvas to the bounds of the rotated image.
+    expand: bool,
+    /// How the rotated image is resampled.
+    interpolation: Interpolation,
+    /// The color of the uncovered background.
+    fill: P,
 }
 
-impl<P> ImageStage<P> for OffAxisStage
+impl<P> ImageStage<P> for OffAxisStage<P>
 where
     P: Pixel + Send + Sync + 'static,
-    <P as Pixel>::Subpixel: Default + Send + Sync + ValueInto<f32> + Clamp<f32>,
+    <P as Pixel>::Subpixel: Send + Sync + ValueInto<f32> + Clamp<f32>,
 {
     fn execute(&self, img: &Image<P>) -> (Image<P>, Tags) {
-        (
-            geometric_transformations::rotate_about_center(
+        let rotated = if self.expand {
+            let (width, height) = rotated_bounds(img.width(), img.height(), self.radians);
+            // Rotate about the image's center, then move that center to the canvas's
+            let projection = Projection::translate(width as f32 / 2., height as f32 / 2.)
+                * Projection::rotate(self.radians as f32)
+                * Projection::translate(-(img.width() as f32) / 2., -(img.height() as f32) / 2.);
+            let mut out = Image::new(width, height);
+            geometric_transformations::warp_into(
                 img,
-                self.radians as f32,
-                Interpolation::Bicubic,
-                P::from_slice(&[Default::default(); 4]).to_owned(),
-            ),
-            Tags(HashSet::from_iter([OFF_AXIS_LABEL.to_owned()])),
-        )
+                &projection,
+                self.interpolation,
+                self.fill,
+                &mut out,
+            );
+            out
+        } else {
+            match self.center {
+                Some((x, y)) => geometric_transformations::rotate(
+                    img,
+                    (x * img.width() as f32, y * img.height() as f32),
+                    self.radians as f32,
+                    self.interpolation,
+                    self.fill,
+                ),
+                None => geometric_transformations::rotate_about_center(
+                    img,
+                    self.radians as f32,
+                    self.interpolation,
+                    self.fill,
+                ),
+            }
+        };
+
+        (rotated, Tags(HashSet::from_iter([Label::OffAxis])))
+    }
+
+    fn name(&self) -> Cow<str> {
+        let suffix = if self.expand { "_exp" } else { "" };
+        format!("rot_{:.2}_deg{}", rad_to_deg(self.radians), suffix).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({
+            "radians": self.radians,
+            "center": self.center,
+            "expand": self.expand,
+            "interpolation": format!("{:?}", self.interpolation),
+        }))
+    }
+}
+
+/// A builder that will create one stage for each angle in `angles_deg` (in degrees, positive being
+/// clockwise), which rotates the image about its center by exactly that angle with `interpolation`,
+/// filling the uncovered background with `fill`. Unlike [`OffAxisRotationBuilder`] nothing is
+/// sampled, so the stages are the same no matter the RNG.
+///
+/// [`OffAxisRotationBuilder`]: about:blank
+pub struct FixedAngleRotationBuilder<P: Pixel> {
+    /// The angles to rotate by, in degrees.
+    angles_deg: Vec<f64>,
+    /// How the rotated image is resampled.
+    interpolation: Interpolation,
+    /// The color of the background uncovered by the rotation.
+    fill: P,
+}
+
+impl<P: Pixel> FixedAngleRotationBuilder<P> {
+    /// Creates a builder rotating by each of `angles_deg`.
+    ///
+    /// # Panics
+    ///
+    /// If any of the angles is zero (which would leave the image as it is), or any angle is listed
+    /// more than once.
+    pub fn new(angles_deg: Vec<f64>, interpolation: Interpolation, fill: P) -> Self {
+        for (i, angle) in angles_deg.iter().enumerate() {
+            assert!(*angle != 0., "rotating by 0 degrees is a no-op");
+            assert!(
+                !angles_deg[..i].contains(angle),
+                "the angle {} is listed more than once",
+                angle
+            );
+        }
+
+        Self {
+            angles_deg,
+            interpolation,
+            fill,
+        }
+    }
+}
+
+impl<P, R> StageBuilder<P, R> for FixedAngleRotationBuilder<P>
+where
+    P: Pixel + Send + Sync + 'static,
+    <P as Pixel>::Subpixel: Send + Sync + ValueInto<f32> + Clamp<f32>,
+    R: Rng,
+{
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !tags.0.contains(&Label::OffAxis)
+    }
+
+    fn variations(&self) -> usize {
+        self.angles_deg.len()
+    }
+
+    fn build_stage(&self, _: &mut R) -> Vec<Box<dyn ImageStage<P> + Send + Sync>> {
+        self.angles_deg
+            .iter()
+            .map(|&degrees| {
+                Box::new(OffAxisStage {
+                    radians: deg_to_rad(degrees),
+                    center: None,
+                    expand: false,
+                    interpolation: self.interpolation,
+                    fill: self.fill,
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        self.angles_deg
+            .iter()
+            .map(|degrees| {
+                StageDescriptor::new(format!("rot_{:.2}_deg", degrees), [Label::OffAxis])
+            })
+            .collect()
+    }
+}
+
+/// A builder that will yield a single stage leveling the image, by estimating how far its text or
+/// edges are rotated (up to `DeskewStage::MAX_DEGREES` either way) and rotating it back, filling the
+/// uncovered background with `fill`. Images rotated less than `threshold_deg` are considered level,
+/// and yield no output. As the output is tagged as rotated off-axis, it's not rotated off-axis again.
+pub struct DeskewBuilder<P: Pixel> {
+    /// The smallest estimated rotation worth correcting, in degrees.
+    pub threshold_deg: f64,
+    /// The color of the background uncovered by the rotation.
+    pub fill: P,
+}
+
+impl<P, R> StageBuilder<P, R> for DeskewBuilder<P>
+where
+    P: Pixel + Send + Sync + 'static,
+    <P as Pixel>::Subpixel: Send + Sync + ValueInto<f32> + Clamp<f32>,
+    R: Rng,
+{
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !tags.0.contains(&Label::OffAxis)
+    }
+
+    fn variations(&self) -> usize {
+        1
+    }
+
+    fn build_stage(&self, _: &mut R) -> Vec<Box<dyn ImageStage<P> + Send + Sync>> {
+        vec![Box::new(DeskewStage::new(self.threshold_deg, self.fill))]
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new(
+            "deskew_{degrees}deg",
+            [Label::OffAxis],
+        )]
+    }
+}
+
+/// The actual stage which levels the image. The rotation is estimated with a projection profile:
+/// the dark pixels of the image (or light ones, if most of it is dark) are projected onto the
+/// vertical axis at each candidate angle, and the angle whose projection is the most sharply
+/// peaked is the one lines of text or edges lie along. The image is then rotated about its center
+/// by the opposite angle. Images whose estimated rotation is below `threshold_deg` yield no output.
+/// As the estimate is costly, running the stage makes it once for the output, its name and the
+/// parameters recorded.
+pub struct DeskewStage<P: Pixel> {
+    /// The smallest estimated rotation worth correcting, in degrees.
+    threshold_deg: f64,
+    /// The color of the uncovered background.
+    fill: P,
+}
+
+impl<P> DeskewStage<P>
+where
+    P: Pixel + 'static,
+    P::Subpixel: ValueInto<f32>,
+{
+    /// The largest rotation considered either way, in degrees.
+    pub const MAX_DEGREES: f64 = 15.;
+    /// The most pixels projected, larger images are sampled with a stride.
+    const MAX_POINTS: usize = 20_000;
+
+    /// Creates a stage correcting rotations of at least `threshold_deg` either way, uncovering
+    /// `fill`.
+    fn new(threshold_deg: f64, fill: P) -> Self {
+        Self {
+            threshold_deg: threshold_deg.abs(),
+            fill,
+        }
+    }
+
+    /// Estimates the rotation of `img` in degrees, clockwise.
+    fn estimate(&self, img: &Image<P>) -> f64 {
+        let (width, height) = img.dimensions();
+        if width == 0 || height == 0 {
+            return 0.;
+        }
+
+        let max: f32 = <P::Subpixel as Bounded>::max_value()
+            .value_into()
+            .unwrap_or(255.);
+        let gray = GrayImage::from_fn(width, height, |x, y| {
+            let luma: f32 = img.get_pixel(x, y).to_luma()[0]
+                .value_into()
+                .unwrap_or_default();
+            Luma([(luma / max * 255.).round().max(0.).min(255.) as u8])
+        });
+        let level = otsu_level(&gray);
+        let dark = gray.pixels().filter(|p| p[0] < level).count();
+        // Whichever side of the threshold is the minority is the ink
+        let ink_is_dark = dark * 2 <= gray.pixels().len();
+        let stride = (gray.pixels().len() / Self::MAX_POINTS).max(1);
+        let points: Vec<(f64, f64)> = gray
+            .enumerate_pixels()
+            .filter(|(_, _, p)| (p[0] < level) == ink_is_dark)
+            .step_by(stride)
+            .map(|(x, y, _)| (x as f64, y as f64))
+            .collect();
+        if points.is_empty() {
+            return 0.;
+        }
+
+        let offset = width as f64 + 1.;
+        let mut bins = vec![0u32; (width + height) as usize * 2 + 4];
+        let mut sharpness = |degrees: f64| {
+            let (sin, cos) = deg_to_rad(degrees).sin_cos();
+            bins.iter_mut().for_each(|bin| *bin = 0);
+            for &(x, y) in &points {
+                bins[(y * cos - x * sin + offset) as usize] += 1;
+            }
+            bins.iter().map(|&bin| (bin as f64).powi(2)).sum::<f64>()
+        };
+
+        // A coarse search, then a finer one around the best coarse angle
+        let steps = (Self::MAX_DEGREES * 2.) as i32;
+        let mut best = (0., f64::MIN);
+        for degrees in (-steps..=steps).map(|step| step as f64 / 2.) {
+            let score = sharpness(degrees);
+            if score > best.1 {
+                best = (degrees, score);
+            }
+        }
+        let coarse = best.0;
+        for degrees in (-5..=5).map(|step| coarse + step as f64 / 10.) {
+            let score = sharpness(degrees);
+            if score > best.1 {
+                best = (degrees, score);
+            }
+        }
+        best.0
+    }
+
+    /// Rotates `img` back by `degrees`, clockwise, unless that's below the threshold.
+    fn leveled(&self, img: &Image<P>, degrees: f64) -> Vec<(Image<P>, Tags)>
+    where
+        P: Send + Sync,
+        P::Subpixel: Send + Sync + Clamp<f32>,
+    {
+        if degrees.abs() < self.threshold_deg {
+            vec![]
+        } else {
+            vec![self.level(img, degrees)]
+        }
+    }
+
+    /// Rotates `img` back by `degrees`, clockwise.
+    fn level(&self, img: &Image<P>, degrees: f64) -> (Image<P>, Tags)
+    where
+        P: Send + Sync,
+        P::Subpixel: Send + Sync + Clamp<f32>,
+    {
+        let rotated = geometric_transformations::rotate_about_center(
+            img,
+            -deg_to_rad(degrees) as f32,
+            Interpolation::Bicubic,
+            self.fill,
+        );
+
+        (rotated, Tags(HashSet::from_iter([Label::OffAxis])))
+    }
+}
+
+impl<P> ImageStage<P> for DeskewStage<P>
+where
+    P: Pixel + Send + Sync + 'static,
+    <P as Pixel>::Subpixel: Send + Sync + ValueInto<f32> + Clamp<f32>,
+{
+    fn execute(&self, img: &Image<P>) -> (Image<P>, Tags) {
+        self.level(img, self.estimate(img))
+    }
+
+    fn execute_many(&self, img: &Image<P>) -> Vec<(Image<P>, Tags)> {
+        self.leveled(img, self.estimate(img))
     }
 
     fn name(&self) -> Cow<str> {
-        format!("rot_{:.2}_deg", rad_to_deg(self.radians)).into()
+        "deskew".into()
+    }
+
+    fn name_for(&self, img: &Image<P>) -> Cow<str> {
+        format!("deskew_{:.1}deg", self.estimate(img)).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({ "threshold_deg": self.threshold_deg }))
+    }
+
+    fn run(&self, img: &Image<P>) -> StageRun<'_, P> {
+        let degrees = self.estimate(img);
+        StageRun {
+            name: format!("deskew_{:.1}deg", degrees).into(),
+            params: Some(json!({ "threshold_deg": self.threshold_deg, "degrees": degrees })),
+            outputs: self.leveled(img, degrees),
+        }
     }
 }
 
 /// Not to be confused with `OffAxisRotationBuilder`, this "rotates" the image
 /// as if you were to change its exif orientation data - that is to say it simply will
 /// create three stages that rotate the image by multiples of 90, 180, and 270 degrees.
-pub struct RotationBuilder;
+///
+/// The names the stages append to filenames default to `"rot90cw"`, `"rot90ccw"`, and `"rot180"`,
+/// but can be overridden to suit your dataset.
+pub struct RotationBuilder {
+    /// The name of the clockwise rotation stage.
+    clockwise_name: Cow<'static, str>,
+    /// The name of the counterclockwise rotation stage.
+    cclockwise_name: Cow<'static, str>,
+    /// The name of the upside-down rotation stage.
+    upside_down_name: Cow<'static, str>,
+}
+
+impl Default for RotationBuilder {
+    fn default() -> Self {
+        Self {
+            clockwise_name: "rot90cw".into(),
+            cclockwise_name: "rot90ccw".into(),
+            upside_down_name: "rot180".into(),
+        }
+    }
+}
+
+impl RotationBuilder {
+    /// Overrides the name of the stage rotating images 90 degrees clockwise.
+    pub fn with_clockwise_name<S: Into<Cow<'static, str>>>(mut self, name: S) -> Self {
+        self.clockwise_name = name.into();
+        self
+    }
+
+    /// Overrides the name of the stage rotating images 90 degrees counterclockwise.
+    pub fn with_cclockwise_name<S: Into<Cow<'static, str>>>(mut self, name: S) -> Self {
+        self.cclockwise_name = name.into();
+        self
+    }
+
+    /// Overrides the name of the stage flipping images upside down.
+    pub fn with_upside_down_name<S: Into<Cow<'static, str>>>(mut self, name: S) -> Self {
+        self.upside_down_name = name.into();
+        self
+    }
+}
 
 impl<P: Pixel + 'static, R: Rng> StageBuilder<P, R> for RotationBuilder {
     fn should_execute(&self, tags: &Tags) -> bool {
-        !(tags.0.contains(CWISE_LABEL)
-            || tags.0.contains(CCWISE_LABEL)
-            || tags.0.contains(UPSIDE_DOWN_LABEL))
+        !(tags.0.contains(&Label::Clockwise)
+            || tags.0.contains(&Label::Cclockwise)
+            || tags.0.contains(&Label::UpsideDown))
     }
 
     fn variations(&self) -> usize {
@@ -130,168 +531,8543 @@ impl<P: Pixel + 'static, R: Rng> StageBuilder<P, R> for RotationBuilder {
 
     fn build_stage(&self, _: &mut R) -> Vec<Box<dyn ImageStage<P> + Send + Sync>> {
         vec![
-            Box::new(ClockwiseStage),
-            Box::new(CclockwiseStage),
-            Box::new(UpsideDownStage),
+            Box::new(ClockwiseStage {
+                name: self.clockwise_name.clone(),
+            }),
+            Box::new(CclockwiseStage {
+                name: self.cclockwise_name.clone(),
+            }),
+            Box::new(UpsideDownStage {
+                name: self.upside_down_name.clone(),
+            }),
+        ]
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![
+            StageDescriptor::new(self.clockwise_name.clone(), [Label::Clockwise]),
+            StageDescriptor::new(self.cclockwise_name.clone(), [Label::Cclockwise]),
+            StageDescriptor::new(self.upside_down_name.clone(), [Label::UpsideDown]),
         ]
     }
 }
 
 /// A stage that rotates an image 90 degrees clockwise.
-pub struct ClockwiseStage;
+pub struct ClockwiseStage {
+    /// The name appended to the image's filename.
+    name: Cow<'static, str>,
+}
 
 impl<P: Pixel + 'static> ImageStage<P> for ClockwiseStage {
     fn execute(&self, img: &Image<P>) -> (Image<P>, Tags) {
         (
             imageops::rotate90(img),
-            Tags(HashSet::from_iter([CWISE_LABEL.to_owned()])),
+            Tags(HashSet::from_iter([Label::Clockwise])),
         )
     }
 
     fn name(&self) -> Cow<str> {
-        "clowise".into()
+        self.name.as_ref().into()
     }
 }
 
 /// A stage that rotates an image 90 degrees counterclockwise.
-pub struct CclockwiseStage;
+pub struct CclockwiseStage {
+    /// The name appended to the image's filename.
+    name: Cow<'static, str>,
+}
 
 impl<P: Pixel + 'static> ImageStage<P> for CclockwiseStage {
     fn execute(&self, img: &Image<P>) -> (Image<P>, Tags) {
         (
             imageops::rotate270(img),
-            Tags(HashSet::from_iter([CCWISE_LABEL.to_owned()])),
+            Tags(HashSet::from_iter([Label::Cclockwise])),
         )
     }
 
     fn name(&self) -> Cow<str> {
-        "couwise".into()
+        self.name.as_ref().into()
     }
 }
 
 /// A stage that flips an image upside down.
-pub struct UpsideDownStage;
+pub struct UpsideDownStage {
+    /// The name appended to the image's filename.
+    name: Cow<'static, str>,
+}
 
 impl<P: Pixel + 'static> ImageStage<P> for UpsideDownStage {
     fn execute(&self, img: &Image<P>) -> (Image<P>, Tags) {
         (
             imageops::rotate180(img),
-            Tags(HashSet::from_iter([UPSIDE_DOWN_LABEL.to_owned()])),
+            Tags(HashSet::from_iter([Label::UpsideDown])),
         )
     }
 
     fn name(&self) -> Cow<str> {
-        "up_down".into()
+        self.name.as_ref().into()
     }
 }
 
-/// A builder that will yield two stages: a brighten and darken stage, which will change the image
-/// pixel intensity across all channels by a random value between `min_luma` and `max_luma`. Note that
-/// `i32` is significantly higher than the 8-bit channel value, so this range should be fairly small or
-/// all pixels will end up becoming black/white.
-pub struct LuminosityBuilder {
-    /// The minimum degree of intensity we can brighten/darken by.
-    pub min_luma: i32,
-    /// The maximum degree of intensity we can brighten/daren by.
-    pub max_luma: i32,
-}
+/// A builder that will yield two stages mirroring the image, one horizontally (left to right) and
+/// one vertically (top to bottom). Combine it with `RotationBuilder` for every orientation of
+/// the image.
+pub struct MirrorBuilder;
+
+impl<P: Pixel + 'static, R: Rng> StageBuilder<P, R> for MirrorBuilder {
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !(tags.0.contains(&Label::MirroredH) || tags.0.contains(&Label::MirroredV))
+    }
 
-impl<P: Pixel + 'static, R: Rng> StageBuilder<P, R> for LuminosityBuilder {
     fn variations(&self) -> usize {
         2
     }
 
-    fn should_execute(&self, tags: &Tags) -> bool {
-        !(tags.0.contains(BRIGHTEN_LABEL) || tags.0.contains(DARKEN_LABEL))
+    fn build_stage(&self, _: &mut R) -> Vec<Box<dyn ImageStage<P> + Send + Sync>> {
+        vec![Box::new(MirrorHStage), Box::new(MirrorVStage)]
     }
 
-    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<P> + Send + Sync>> {
+    fn describe(&self) -> Vec<StageDescriptor> {
         vec![
-            Box::new(LuminosityStage {
-                value: rng.gen_range(self.min_luma..self.max_luma),
-            }),
-            Box::new(LuminosityStage {
-                value: rng.gen_range(-self.max_luma..-self.min_luma),
-            }),
+            StageDescriptor::new("flip_h", [Label::MirroredH]),
+            StageDescriptor::new("flip_v", [Label::MirroredV]),
         ]
     }
 }
 
-/// The actual stage that alters brightness and darkness in an image. It will shift all pixels
-/// by a constant `value`, negative for darkening and positive for brightening.
-pub struct LuminosityStage {
-    /// The number to add to all pixel channels in the image.
-    value: i32,
+/// The actual stage that mirrors the image horizontally.
+pub struct MirrorHStage;
+
+impl<P: Pixel + 'static> ImageStage<P> for MirrorHStage {
+    fn execute(&self, img: &Image<P>) -> (Image<P>, Tags) {
+        (
+            imageops::flip_horizontal(img),
+            Tags(HashSet::from_iter([Label::MirroredH])),
+        )
+    }
+
+    fn name(&self) -> Cow<str> {
+        "flip_h".into()
+    }
 }
 
-impl<P: Pixel + 'static> ImageStage<P> for LuminosityStage {
+/// The actual stage that mirrors the image vertically.
+pub struct MirrorVStage;
+
+impl<P: Pixel + 'static> ImageStage<P> for MirrorVStage {
     fn execute(&self, img: &Image<P>) -> (Image<P>, Tags) {
-        let mut img = img.clone();
-        colorops::brighten_in_place(&mut img, self.value);
         (
-            img,
-            Tags(HashSet::from_iter([if self.value < 0 {
-                DARKEN_LABEL.to_owned()
-            } else {
-                BRIGHTEN_LABEL.to_owned()
-            }])),
+            imageops::flip_vertical(img),
+            Tags(HashSet::from_iter([Label::MirroredV])),
         )
     }
 
     fn name(&self) -> Cow<str> {
-        if self.value < 0 {
-            format!("dark_{}", self.value).into()
-        } else {
-            format!("bright_{}", self.value).into()
-        }
+        "flip_v".into()
     }
 }
 
-/// A builder that will create `samples` stages that will perform a gaussian blur on the image
-/// with a standard deviation between `min_sigma` and `max_sigma` (this is esssentially a uniform
-/// distribution over a normal distribution of blurred versions of the image).
-pub struct BlurBuilder {
-    /// The number of blurred variants to create
+/// A builder that will create `samples` stages, each of which crops the image to a randomly placed
+/// region covering between `min_fraction` and `max_fraction` of its area, with the same aspect
+/// ratio as the image.
+pub struct RandomCropBuilder {
+    /// The number of crops to generate.
     pub samples: usize,
-    /// The minimum standard deviation in the gaussian blur kernel
-    pub min_sigma: f32,
-    /// The maximum standard deviation in the gaussian blur kernel
-    pub max_sigma: f32,
+    /// The minimum fraction of the image's area a crop covers, between `0` and `1`.
+    pub min_fraction: f32,
+    /// The maximum fraction of the image's area a crop covers, between `0` and `1`.
+    pub max_fraction: f32,
 }
 
-impl<P: Pixel + 'static, R: Rng> StageBuilder<P, R> for BlurBuilder {
+impl<P: Pixel + 'static, R: Rng> StageBuilder<P, R> for RandomCropBuilder {
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !tags.0.contains(&Label::Cropped)
+    }
+
     fn variations(&self) -> usize {
         self.samples
     }
 
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<P> + Send + Sync>> {
+        (0..self.samples)
+            .map(|_| {
+                let fraction = rng
+                    .gen_range(self.min_fraction..=self.max_fraction)
+                    .max(0.)
+                    .min(1.);
+                let scale = fraction.sqrt();
+                Box::new(CropStage {
+                    fraction,
+                    offset: (
+                        rng.gen_range(0f32..=1.) * (1. - scale),
+                        rng.gen_range(0f32..=1.) * (1. - scale),
+                    ),
+                    scale,
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new(
+            "crop_{fraction}_{x}_{y}",
+            [Label::Cropped],
+        )]
+    }
+}
+
+/// The actual stage that crops the image, to a region whose top-left corner is at `offset` and whose
+/// sides are `scale` times those of the image. Crops are clamped to the image's bounds, and always
+/// at least a pixel wide and high (unless the image is empty).
+pub struct CropStage {
+    /// The fraction of the image's area the crop covers, only used to name the stage.
+    fraction: f32,
+    /// The top-left corner of the crop, as fractions of the image's width and height.
+    offset: (f32, f32),
+    /// The size of the crop, as a fraction of the image's width and height.
+    scale: f32,
+}
+
+impl<P: Pixel + 'static> ImageStage<P> for CropStage {
+    fn execute(&self, img: &Image<P>) -> (Image<P>, Tags) {
+        let (width, height) = img.dimensions();
+        let x = ((self.offset.0 * width as f32) as u32).min(width.saturating_sub(1));
+        let y = ((self.offset.1 * height as f32) as u32).min(height.saturating_sub(1));
+        let crop_width = ((self.scale * width as f32).round() as u32)
+            .max(1)
+            .min(width - x);
+        let crop_height = ((self.scale * height as f32).round() as u32)
+            .max(1)
+            .min(height - y);
+
+        (
+            imageops::crop_imm(img, x, y, crop_width, crop_height).to_image(),
+            Tags(HashSet::from_iter([Label::Cropped])),
+        )
+    }
+
+    fn name(&self) -> Cow<str> {
+        format!(
+            "crop_{:.2}_{:.2}_{:.2}",
+            self.fraction, self.offset.0, self.offset.1
+        )
+        .into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({ "offset": self.offset, "scale": self.scale }))
+    }
+}
+
+/// A builder that will yield a stage for each of `aspects`, cropping the largest centered region of
+/// the image with that aspect ratio, e.g. `(1, 1)` for square crops or `(16, 9)` for widescreen.
+pub struct CenterCropBuilder {
+    /// The aspect ratios to crop to, as the width and height of the ratio.
+    pub aspects: Vec<(u32, u32)>,
+}
+
+impl<P: Pixel + 'static, R: Rng> StageBuilder<P, R> for CenterCropBuilder {
     fn should_execute(&self, tags: &Tags) -> bool {
-        !(tags.0.contains(BLURRED_LABEL))
+        !tags.0.contains(&Label::Cropped)
     }
 
-    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<P> + Send + Sync>> {
-        rng.sample_iter(Uniform::from(self.min_sigma..self.max_sigma))
-            .take(self.samples)
-            .map(|sigma| Box::new(BlurStage { sigma }) as Box<dyn ImageStage<_> + Send + Sync>)
+    fn variations(&self) -> usize {
+        self.aspects.len()
+    }
+
+    fn build_stage(&self, _: &mut R) -> Vec<Box<dyn ImageStage<P> + Send + Sync>> {
+        self.aspects
+            .iter()
+            .map(|&(w, h)| {
+                Box::new(CenterCropStage {
+                    aspect: (w.max(1), h.max(1)),
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        self.aspects
+            .iter()
+            .map(|&(w, h)| {
+                StageDescriptor::new(format!("crop_{}x{}", w.max(1), h.max(1)), [Label::Cropped])
+            })
             .collect()
     }
 }
 
-/// The actual stage which blurs the image, it will blur the input image with a gaussian blur
-/// whose kernel's standard deviation is `sigma`.
-pub struct BlurStage {
-    /// The standard deviation of the gaussian blur kernel.
-    pub sigma: f32,
+/// The actual stage that crops the largest centered region of the image with an `aspect` ratio.
+/// Crops are always at least a pixel wide and high (unless the image is empty), so degenerate
+/// images such as 1xN strips are cropped as close to the aspect ratio as possible.
+pub struct CenterCropStage {
+    /// The aspect ratio, as the width and height of the ratio (both non-zero).
+    aspect: (u32, u32),
 }
 
-impl<P: Pixel + 'static> ImageStage<P> for BlurStage {
+impl<P: Pixel + 'static> ImageStage<P> for CenterCropStage {
     fn execute(&self, img: &Image<P>) -> (Image<P>, Tags) {
+        let (width, height) = img.dimensions();
+        let (aw, ah) = (self.aspect.0 as u64, self.aspect.1 as u64);
+        // Whichever of the width or height is the limiting side keeps its full length
+        let (crop_width, crop_height) = if width as u64 * ah > height as u64 * aw {
+            ((height as u64 * aw / ah) as u32, height)
+        } else {
+            (width, (width as u64 * ah / aw) as u32)
+        };
+        let crop_width = crop_width.max(1).min(width);
+        let crop_height = crop_height.max(1).min(height);
+
         (
-            imageops::blur(img, self.sigma),
-            Tags(HashSet::from_iter([BLURRED_LABEL.to_owned()])),
+            imageops::crop_imm(
+                img,
+                (width - crop_width) / 2,
+                (height - crop_height) / 2,
+                crop_width,
+                crop_height,
+            )
+            .to_image(),
+            Tags(HashSet::from_iter([Label::Cropped])),
         )
     }
 
     fn name(&self) -> Cow<str> {
-        format!("blur_{:0.2}", self.sigma).into()
+        format!("crop_{}x{}", self.aspect.0, self.aspect.1).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({ "aspect": self.aspect }))
+    }
+}
+
+/// A builder that will create `samples` stages, each of which crops a randomly placed region of the
+/// image and resizes it back to the image's dimensions with `filter` (the "random resized crop" of
+/// torchvision and friends). The region covers a random fraction of the image's area from
+/// `scale_range`, and has a random aspect ratio (width over height) from `ratio_range`, sampled
+/// log-uniformly so that e.g. `3:4` and `4:3` are as likely. As the canvas keeps its size, this can
+/// stand in for a crop followed by a resize.
+pub struct RandomResizedCropBuilder {
+    /// The number of crops to generate.
+    pub samples: usize,
+    /// The range of the fraction of the image's area the region covers.
+    pub scale_range: RangeInclusive<f32>,
+    /// The range of the aspect ratio of the region.
+    pub ratio_range: RangeInclusive<f32>,
+    /// The filter used to resize the region.
+    pub filter: FilterType,
+}
+
+impl<P: Pixel + 'static, R: Rng> StageBuilder<P, R> for RandomResizedCropBuilder {
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !tags.0.contains(&Label::Cropped)
+    }
+
+    fn variations(&self) -> usize {
+        self.samples
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<P> + Send + Sync>> {
+        let scale =
+            self.scale_range.start().max(0.).min(1.)..=self.scale_range.end().max(0.).min(1.);
+        let log_ratio = self.ratio_range.start().max(f32::EPSILON).ln()
+            ..=self.ratio_range.end().max(f32::EPSILON).ln();
+        (0..self.samples)
+            .map(|_| {
+                Box::new(RandomResizedCropStage {
+                    scale: rng.gen_range(scale.clone()),
+                    ratio: rng.gen_range(log_ratio.clone()).exp(),
+                    offset: (rng.gen_range(0f32..=1.), rng.gen_range(0f32..=1.)),
+                    filter: self.filter,
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new(
+            "rrc_{scale}_{ratio}",
+            [Label::Cropped],
+        )]
+    }
+}
+
+/// The actual stage which crops a region covering `scale` of the image's area with an aspect ratio
+/// of `ratio`, placed at `offset` (as fractions of the room left around it), and resizes it back to
+/// the image's dimensions with `filter`. Regions too wide or tall for the image (which can only be
+/// known once it's given) fall back to the largest centered crop with the same aspect ratio.
+pub struct RandomResizedCropStage {
+    /// The fraction of the image's area the region covers.
+    scale: f32,
+    /// The aspect ratio of the region, its width over its height.
+    ratio: f32,
+    /// Where the region is placed, as fractions of the horizontal and vertical room left.
+    offset: (f32, f32),
+    /// The filter used to resize the region.
+    filter: FilterType,
+}
+
+impl<P: Pixel + 'static> ImageStage<P> for RandomResizedCropStage {
+    fn execute(&self, img: &Image<P>) -> (Image<P>, Tags) {
+        let (width, height) = img.dimensions();
+        let tags = Tags(HashSet::from_iter([Label::Cropped]));
+        if width == 0 || height == 0 {
+            return (img.clone(), tags);
+        }
+
+        let area = width as f32 * height as f32 * self.scale;
+        let (crop_width, crop_height) = ((area * self.ratio).sqrt(), (area / self.ratio).sqrt());
+        let (crop_width, crop_height, offset) =
+            if crop_width <= width as f32 && crop_height <= height as f32 {
+                (crop_width, crop_height, self.offset)
+            } else {
+                let shrink = (width as f32 / crop_width).min(height as f32 / crop_height);
+                (crop_width * shrink, crop_height * shrink, (0.5, 0.5))
+            };
+        let crop_width = (crop_width.round() as u32).max(1).min(width);
+        let crop_height = (crop_height.round() as u32).max(1).min(height);
+        let x = ((width - crop_width) as f32 * offset.0).round() as u32;
+        let y = ((height - crop_height) as f32 * offset.1).round() as u32;
+
+        let region = imageops::crop_imm(img, x, y, crop_width, crop_height).to_image();
+        (imageops::resize(&region, width, height, self.filter), tags)
+    }
+
+    fn name(&self) -> Cow<str> {
+        format!("rrc_{:.2}_{:.2}", self.scale, self.ratio).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({
+            "scale": self.scale,
+            "ratio": self.ratio,
+            "offset": self.offset,
+            "filter": filter_name(self.filter),
+        }))
+    }
+}
+
+/// A builder that will yield a single stage cropping away the uniform margins of the image, such as
+/// the white or black borders of scans, so the rest of the pipeline works on the content alone. A
+/// margin is any run of rows or columns at the edges whose pixels are all within `tolerance` of the
+/// color of the top-left corner, in every channel. Images without margins yield no output.
+pub struct AutoCropBuilder {
+    /// How far a channel may be from the corner's and still count as margin, in 8-bit levels.
+    pub tolerance: u8,
+    /// The smallest fraction of the image's width and height which may remain, below which the
+    /// image is left alone (as e.g. a nearly blank page would be cropped down to a speck).
+    pub min_remaining_frac: f32,
+}
+
+impl<P, R> StageBuilder<P, R> for AutoCropBuilder
+where
+    P: Pixel + 'static,
+    P::Subpixel: ValueInto<f32>,
+    R: Rng,
+{
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !(tags.0.contains(&Label::AutoCropped) || tags.0.contains(&Label::Cropped))
+    }
+
+    fn variations(&self) -> usize {
+        1
+    }
+
+    fn build_stage(&self, _: &mut R) -> Vec<Box<dyn ImageStage<P> + Send + Sync>> {
+        vec![Box::new(AutoCropStage {
+            tolerance: self.tolerance as f32,
+            min_remaining_frac: self.min_remaining_frac.max(0.).min(1.),
+        })]
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new("autocrop", [Label::AutoCropped])]
+    }
+}
+
+/// The actual stage which crops away the rows and columns at the edges of the image whose channels
+/// are all within `tolerance` of the top-left corner's (scaled to the channel's range, for
+/// non-8-bit images). It yields no output at all when there's nothing to crop, or when less than
+/// `min_remaining_frac` of the width or height would remain.
+pub struct AutoCropStage {
+    /// How far a channel may be from the corner's, in 8-bit levels.
+    tolerance: f32,
+    /// The smallest fraction of the width and height which may remain.
+    min_remaining_frac: f32,
+}
+
+impl AutoCropStage {
+    /// The bounds of the content of `img`, as its left, top, right and bottom edges (exclusive of
+    /// the latter two), or `None` if it has no margins or they're too wide.
+    fn bounds<P>(&self, img: &Image<P>) -> Option<(u32, u32, u32, u32)>
+    where
+        P: Pixel + 'static,
+        P::Subpixel: ValueInto<f32>,
+    {
+        let (width, height) = img.dimensions();
+        if width == 0 || height == 0 {
+            return None;
+        }
+
+        let max: f32 = <P::Subpixel as Bounded>::max_value()
+            .value_into()
+            .unwrap_or(255.);
+        let tolerance = self.tolerance / 255. * max;
+        let corner = *img.get_pixel(0, 0);
+        let is_margin = |x: u32, y: u32| {
+            img.get_pixel(x, y)
+                .channels()
+                .iter()
+                .zip(corner.channels())
+                .all(|(&a, &b)| {
+                    let (a, b): (f32, f32) = (
+                        a.value_into().unwrap_or_default(),
+                        b.value_into().unwrap_or_default(),
+                    );
+                    (a - b).abs() <= tolerance
+                })
+        };
+        let row = |y: u32| (0..width).all(|x| is_margin(x, y));
+        let column = |x: u32, top: u32, bottom: u32| (top..bottom).all(|y| is_margin(x, y));
+
+        let top = (0..height).find(|&y| !row(y))?;
+        let bottom = (top..height).rev().find(|&y| !row(y))? + 1;
+        let left = (0..width).find(|&x| !column(x, top, bottom))?;
+        let right = (left..width).rev().find(|&x| !column(x, top, bottom))? + 1;
+
+        let (cropped_width, cropped_height) = (right - left, bottom - top);
+        if (cropped_width, cropped_height) == (width, height)
+            || (cropped_width as f32) < width as f32 * self.min_remaining_frac
+            || (cropped_height as f32) < height as f32 * self.min_remaining_frac
+        {
+            return None;
+        }
+
+        Some((left, top, right, bottom))
+    }
+}
+
+impl<P> ImageStage<P> for AutoCropStage
+where
+    P: Pixel + 'static,
+    P::Subpixel: ValueInto<f32>,
+{
+    fn execute(&self, img: &Image<P>) -> (Image<P>, Tags) {
+        match self.bounds(img) {
+            Some((left, top, right, bottom)) => (
+                imageops::crop_imm(img, left, top, right - left, bottom - top).to_image(),
+                Tags(HashSet::from_iter([Label::AutoCropped])),
+            ),
+            None => (img.clone(), Tags::default()),
+        }
+    }
+
+    fn execute_many(&self, img: &Image<P>) -> Vec<(Image<P>, Tags)> {
+        match self.bounds(img) {
+            Some(_) => vec![self.execute(img)],
+            None => vec![],
+        }
+    }
+
+    fn name(&self) -> Cow<str> {
+        "autocrop".into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({
+            "tolerance": self.tolerance,
+            "min_remaining_frac": self.min_remaining_frac,
+        }))
+    }
+}
+
+/// A builder that will create a stage for each aspect ratio in `target_aspects`, which pads the
+/// image with bars of `fill` to reach it, keeping the image centered and unscaled.
+pub struct LetterboxBuilder<P: Pixel> {
+    /// The aspect ratios to pad to, as the width and height of the ratio.
+    pub target_aspects: Vec<(u32, u32)>,
+    /// The color of the bars.
+    pub fill: P,
+}
+
+impl<P: Pixel + Send + Sync + 'static, R: Rng> StageBuilder<P, R> for LetterboxBuilder<P> {
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !tags.0.contains(&Label::Padded)
+    }
+
+    fn variations(&self) -> usize {
+        self.target_aspects.len()
+    }
+
+    fn build_stage(&self, _: &mut R) -> Vec<Box<dyn ImageStage<P> + Send + Sync>> {
+        self.target_aspects
+            .iter()
+            .map(|&(w, h)| {
+                Box::new(LetterboxStage {
+                    aspect: (w.max(1), h.max(1)),
+                    fill: self.fill,
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        self.target_aspects
+            .iter()
+            .map(|&(w, h)| {
+                StageDescriptor::new(format!("pad_{}x{}", w.max(1), h.max(1)), [Label::Padded])
+            })
+            .collect()
+    }
+}
+
+/// The actual stage that pads the image with bars of `fill` to the smallest canvas with an `aspect`
+/// ratio which contains it. When the padding can't be split evenly, the extra pixel goes to the
+/// right or bottom bar. Images already within a pixel of the aspect ratio are skipped.
+pub struct LetterboxStage<P: Pixel> {
+    /// The aspect ratio, as the width and height of the ratio (both non-zero).
+    aspect: (u32, u32),
+    /// The color of the bars.
+    fill: P,
+}
+
+impl<P: Pixel> LetterboxStage<P> {
+    /// The dimensions `img` is padded to, or `None` if it's already within a pixel of the aspect
+    /// ratio.
+    fn padded_dimensions(&self, img: &Image<P>) -> Option<(u32, u32)> {
+        let (width, height) = img.dimensions();
+        let (padded_width, padded_height) = letterbox_dimensions(width, height, self.aspect);
+        if padded_width - width <= 1 && padded_height - height <= 1 {
+            None
+        } else {
+            Some((padded_width, padded_height))
+        }
+    }
+}
+
+impl<P: Pixel + 'static> ImageStage<P> for LetterboxStage<P> {
+    fn execute(&self, img: &Image<P>) -> (Image<P>, Tags) {
+        match self.padded_dimensions(img) {
+            Some((width, height)) => (
+                pad_centered(img, width, height, self.fill),
+                Tags(HashSet::from_iter([Label::Padded])),
+            ),
+            None => (img.clone(), Tags::default()),
+        }
+    }
+
+    fn execute_many(&self, img: &Image<P>) -> Vec<(Image<P>, Tags)> {
+        match self.padded_dimensions(img) {
+            Some(_) => vec![self.execute(img)],
+            None => vec![],
+        }
+    }
+
+    fn name(&self) -> Cow<str> {
+        format!("pad_{}x{}", self.aspect.0, self.aspect.1).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({ "aspect": self.aspect }))
+    }
+}
+
+/// The dimensions of the smallest canvas with an `aspect` ratio (both non-zero) which contains a
+/// `width` by `height` image, i.e. what a letterbox pads it to.
+fn letterbox_dimensions(width: u32, height: u32, aspect: (u32, u32)) -> (u32, u32) {
+    let (aw, ah) = (aspect.0 as u64, aspect.1 as u64);
+    // Whichever of the width or height is the limiting side gets padded, rounding up so the canvas
+    // never cuts into the image
+    if width as u64 * ah < height as u64 * aw {
+        (((height as u64 * aw + ah - 1) / ah) as u32, height)
+    } else {
+        (width, ((width as u64 * ah + aw - 1) / aw) as u32)
+    }
+}
+
+/// Pads `img` with `fill` to `width` by `height` pixels (neither smaller than the image), keeping it
+/// centered. When the padding can't be split evenly, the extra pixel goes to the right or bottom.
+fn pad_centered<P: Pixel + 'static>(img: &Image<P>, width: u32, height: u32, fill: P) -> Image<P> {
+    let mut padded = Image::from_pixel(width, height, fill);
+    padded
+        .copy_from(img, (width - img.width()) / 2, (height - img.height()) / 2)
+        .unwrap();
+    padded
+}
+
+/// A builder that will create `samples` stages, each of which stretches or squashes the image by
+/// scaling its width and height by independent random factors within `max_stretch` of `1`, then
+/// brings it back to its original dimensions keeping the new aspect ratio. With `crop` set, the
+/// image is scaled to cover the canvas and cropped, otherwise it's scaled to fit and letterboxed
+/// with `fill`.
+pub struct AspectDistortBuilder<P: Pixel> {
+    /// The number of distorted variants to create.
+    samples: usize,
+    /// The largest fraction either dimension is scaled up or down by.
+    max_stretch: f32,
+    /// Whether to crop the image back to its dimensions rather than padding it.
+    crop: bool,
+    /// The color of the padding.
+    fill: P,
+}
+
+impl<P: Pixel> AspectDistortBuilder<P> {
+    /// Creates a builder sampling `samples` pairs of factors within `max_stretch` of `1`.
+    ///
+    /// # Panics
+    ///
+    /// If `max_stretch` isn't between `0.01` and `1` (exclusive), as smaller stretches are
+    /// indistinguishable from the original image.
+    pub fn new(samples: usize, max_stretch: f32, crop: bool, fill: P) -> Self {
+        assert!(
+            (0.01..1.).contains(&max_stretch),
+            "the maximum stretch must be at least 0.01 and below 1, got {}",
+            max_stretch
+        );
+
+        Self {
+            samples,
+            max_stretch,
+            crop,
+            fill,
+        }
+    }
+}
+
+impl<P: Pixel + Send + Sync + 'static, R: Rng> StageBuilder<P, R> for AspectDistortBuilder<P> {
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !tags.0.contains(&Label::AspectDistorted)
+    }
+
+    fn variations(&self) -> usize {
+        self.samples
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<P> + Send + Sync>> {
+        let range = 1. - self.max_stretch..=1. + self.max_stretch;
+        (0..self.samples)
+            .map(|_| {
+                let x_scale: f32 = rng.gen_range(range.clone());
+                // Scaling both dimensions alike would leave the image as it is once back at its
+                // size, so the height takes the opposite extreme in that (vanishingly rare) case
+                let mut y_scale: f32 = rng.gen_range(range.clone());
+                if y_scale == x_scale {
+                    y_scale = if x_scale < 1. {
+                        *range.end()
+                    } else {
+                        *range.start()
+                    };
+                }
+                Box::new(AspectDistortStage {
+                    x_scale,
+                    y_scale,
+                    crop: self.crop,
+                    fill: self.fill,
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new(
+            "stretch_x{x_scale}_y{y_scale}",
+            [Label::AspectDistorted],
+        )]
+    }
+}
+
+/// The actual stage which scales the image's width by `x_scale` and its height by `y_scale`, along
+/// with a common factor to bring it back to its original dimensions: just covering them before
+/// cropping the center if `crop` is set, or just fitting them before letterboxing it with `fill`
+/// otherwise.
+pub struct AspectDistortStage<P: Pixel> {
+    /// The factor the width is scaled by.
+    x_scale: f32,
+    /// The factor the height is scaled by.
+    y_scale: f32,
+    /// Whether to crop the image back to its dimensions rather than padding it.
+    crop: bool,
+    /// The color of the padding.
+    fill: P,
+}
+
+impl<P: Pixel + 'static> ImageStage<P> for AspectDistortStage<P> {
+    fn execute(&self, img: &Image<P>) -> (Image<P>, Tags) {
+        let (width, height) = img.dimensions();
+        let tags = Tags(HashSet::from_iter([Label::AspectDistorted]));
+        if width == 0 || height == 0 {
+            return (img.clone(), tags);
+        }
+
+        let (x_scale, y_scale) = if self.crop {
+            let k = 1. / self.x_scale.min(self.y_scale);
+            (self.x_scale * k, self.y_scale * k)
+        } else {
+            let k = 1. / self.x_scale.max(self.y_scale);
+            (self.x_scale * k, self.y_scale * k)
+        };
+        let scaled_width = ((width as f32 * x_scale).round() as u32).max(1);
+        let scaled_height = ((height as f32 * y_scale).round() as u32).max(1);
+        let scaled = imageops::resize(img, scaled_width, scaled_height, FilterType::Triangle);
+
+        let out = if self.crop {
+            let (crop_width, crop_height) = (width.min(scaled_width), height.min(scaled_height));
+            imageops::crop_imm(
+                &scaled,
+                (scaled_width - crop_width) / 2,
+                (scaled_height - crop_height) / 2,
+                crop_width,
+                crop_height,
+            )
+            .to_image()
+        } else {
+            let scaled = imageops::crop_imm(
+                &scaled,
+                0,
+                0,
+                scaled_width.min(width),
+                scaled_height.min(height),
+            )
+            .to_image();
+            // One side already matches the canvas, so letterboxing to its aspect ratio restores it
+            let (padded_width, padded_height) =
+                letterbox_dimensions(scaled.width(), scaled.height(), (width, height));
+            pad_centered(&scaled, padded_width, padded_height, self.fill)
+        };
+
+        (out, tags)
+    }
+
+    fn name(&self) -> Cow<str> {
+        format!("stretch_x{:.2}_y{:.2}", self.x_scale, self.y_scale).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({
+            "x_scale": self.x_scale,
+            "y_scale": self.y_scale,
+            "crop": self.crop,
+        }))
+    }
+}
+
+/// A builder that will create `samples` stages, each of which resizes the image by a random factor
+/// between `min_scale` and `max_scale` (so e.g. `0.5` halves its width and height), resampling it
+/// with `filter`.
+pub struct ResizeBuilder {
+    /// The number of scales to generate.
+    pub samples: usize,
+    /// The minimum factor to scale the image's dimensions by.
+    pub min_scale: f32,
+    /// The maximum factor to scale the image's dimensions by.
+    pub max_scale: f32,
+    /// The filter used to resample the image.
+    pub filter: FilterType,
+}
+
+impl<P: Pixel + 'static, R: Rng> StageBuilder<P, R> for ResizeBuilder {
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !tags.0.contains(&Label::Resized)
+    }
+
+    fn variations(&self) -> usize {
+        self.samples
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<P> + Send + Sync>> {
+        (0..self.samples)
+            .map(|_| {
+                Box::new(ResizeStage {
+                    scale: rng.gen_range(self.min_scale..=self.max_scale).max(0.),
+                    filter: self.filter,
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new(
+            format!("scale_{{scale}}_{}", filter_name(self.filter)),
+            [Label::Resized],
+        )]
+    }
+}
+
+/// A short name for the resampling `filter`, used in stage names.
+fn filter_name(filter: FilterType) -> &'static str {
+    match filter {
+        FilterType::Nearest => "nearest",
+        FilterType::Triangle => "triangle",
+        FilterType::CatmullRom => "catmullrom",
+        FilterType::Gaussian => "gaussian",
+        FilterType::Lanczos3 => "lanczos",
+    }
+}
+
+/// The actual stage that resizes the image, scaling its dimensions by `scale` with `filter`. The
+/// dimensions are never scaled below a pixel, so small images and scales don't yield empty images.
+pub struct ResizeStage {
+    /// The factor to scale the image's dimensions by.
+    scale: f32,
+    /// The filter used to resample the image.
+    filter: FilterType,
+}
+
+impl<P: Pixel + 'static> ImageStage<P> for ResizeStage {
+    fn execute(&self, img: &Image<P>) -> (Image<P>, Tags) {
+        let (width, height) = img.dimensions();
+        let tags = Tags(HashSet::from_iter([Label::Resized]));
+        if width == 0 || height == 0 {
+            return (img.clone(), tags);
+        }
+
+        let width = ((width as f32 * self.scale).round() as u32).max(1);
+        let height = ((height as f32 * self.scale).round() as u32).max(1);
+        (imageops::resize(img, width, height, self.filter), tags)
+    }
+
+    fn name(&self) -> Cow<str> {
+        format!("scale_{:.2}_{}", self.scale, filter_name(self.filter)).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({ "scale": self.scale, "filter": filter_name(self.filter) }))
+    }
+}
+
+/// An abbreviation of the resampling `filter`, for stage names combining several filters.
+fn filter_code(filter: FilterType) -> &'static str {
+    match filter {
+        FilterType::Nearest => "nn",
+        FilterType::Triangle => "bl",
+        FilterType::CatmullRom => "cr",
+        FilterType::Gaussian => "gs",
+        FilterType::Lanczos3 => "l3",
+    }
+}
+
+/// A builder that will create `samples` stages, each of which degrades the image as if it came from a
+/// lower resolution source, by scaling it down by a random factor between `min_factor` and
+/// `max_factor` with `down_filter`, then back up to its original size with `up_filter`.
+pub struct DownUpBuilder {
+    /// The number of degraded variants to create.
+    pub samples: usize,
+    /// The minimum factor to scale the image down by, e.g. `0.25` for a quarter of its size.
+    pub min_factor: f32,
+    /// The maximum factor to scale the image down by.
+    pub max_factor: f32,
+    /// The filter used to scale the image down.
+    pub down_filter: FilterType,
+    /// The filter used to scale the image back up.
+    pub up_filter: FilterType,
+}
+
+impl<P: Pixel + 'static, R: Rng> StageBuilder<P, R> for DownUpBuilder {
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !tags.0.contains(&Label::LowRes)
+    }
+
+    fn variations(&self) -> usize {
+        self.samples
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<P> + Send + Sync>> {
+        let min = self.min_factor.max(f32::EPSILON).min(1.);
+        let max = self.max_factor.max(min).min(1.);
+        (0..self.samples)
+            .map(|_| {
+                Box::new(DownUpStage {
+                    factor: rng.gen_range(min..=max),
+                    down_filter: self.down_filter,
+                    up_filter: self.up_filter,
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        let name = format!(
+            "lowres_{{factor}}_{}_{}",
+            filter_code(self.down_filter),
+            filter_code(self.up_filter)
+        );
+        vec![StageDescriptor::new(name, [Label::LowRes])]
+    }
+}
+
+/// The actual stage that scales the image down by `factor` with `down_filter` and back up with
+/// `up_filter`, so it keeps exactly the same dimensions. The intermediate image is never scaled
+/// below a pixel.
+pub struct DownUpStage {
+    /// The factor to scale the image down by.
+    factor: f32,
+    /// The filter used to scale the image down.
+    down_filter: FilterType,
+    /// The filter used to scale the image back up.
+    up_filter: FilterType,
+}
+
+impl<P: Pixel + 'static> ImageStage<P> for DownUpStage {
+    fn execute(&self, img: &Image<P>) -> (Image<P>, Tags) {
+        let tags = Tags(HashSet::from_iter([Label::LowRes]));
+        let (width, height) = img.dimensions();
+        if width == 0 || height == 0 {
+            return (img.clone(), tags);
+        }
+
+        let small_width = ((width as f32 * self.factor).round() as u32).max(1);
+        let small_height = ((height as f32 * self.factor).round() as u32).max(1);
+        let small = imageops::resize(img, small_width, small_height, self.down_filter);
+
+        (
+            imageops::resize(&small, width, height, self.up_filter),
+            tags,
+        )
+    }
+
+    fn name(&self) -> Cow<str> {
+        format!(
+            "lowres_{:.2}_{}_{}",
+            self.factor,
+            filter_code(self.down_filter),
+            filter_code(self.up_filter)
+        )
+        .into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({
+            "factor": self.factor,
+            "down_filter": filter_name(self.down_filter),
+            "up_filter": filter_name(self.up_filter),
+        }))
+    }
+}
+
+/// A builder that will yield two stages: a brighten and darken stage, which will change the image
+/// pixel intensity across all channels by a random value between `min_luma` and `max_luma`. Note that
+/// `i32` is significantly higher than the 8-bit channel value, so this range should be fairly small or
+/// all pixels will end up becoming black/white.
+pub struct LuminosityBuilder {
+    /// The minimum degree of intensity we can brighten/darken by.
+    pub min_luma: i32,
+    /// The maximum degree of intensity we can brighten/daren by.
+    pub max_luma: i32,
+}
+
+impl<P: Pixel + 'static, R: Rng> StageBuilder<P, R> for LuminosityBuilder {
+    fn variations(&self) -> usize {
+        2
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !(tags.0.contains(&Label::Bright) || tags.0.contains(&Label::Dark))
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<P> + Send + Sync>> {
+        vec![
+            Box::new(LuminosityStage {
+                value: rng.gen_range(self.min_luma..self.max_luma),
+            }),
+            Box::new(LuminosityStage {
+                value: rng.gen_range(-self.max_luma..-self.min_luma),
+            }),
+        ]
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![
+            StageDescriptor::new("bright_{value}", [Label::Bright]),
+            StageDescriptor::new("dark_{value}", [Label::Dark]),
+        ]
+    }
+}
+
+/// The actual stage that alters brightness and darkness in an image. It will shift all pixels
+/// by a constant `value`, negative for darkening and positive for brightening.
+pub struct LuminosityStage {
+    /// The number to add to all pixel channels in the image.
+    value: i32,
+}
+
+impl<P: Pixel + 'static> ImageStage<P> for LuminosityStage {
+    fn execute(&self, img: &Image<P>) -> (Image<P>, Tags) {
+        let mut img = img.clone();
+        colorops::brighten_in_place(&mut img, self.value);
+        (
+            img,
+            Tags(HashSet::from_iter([if self.value < 0 {
+                Label::Dark
+            } else {
+                Label::Bright
+            }])),
+        )
+    }
+
+    fn name(&self) -> Cow<str> {
+        if self.value < 0 {
+            format!("dark_{}", self.value).into()
+        } else {
+            format!("bright_{}", self.value).into()
+        }
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({ "value": self.value }))
+    }
+}
+
+/// A builder that will create `samples` stages, each of which shifts the red, green and blue channels
+/// of the image by independent random deltas between `min_delta` and `max_delta`. Unlike
+/// [`LuminosityBuilder`] this tints the image with a subtle color cast rather than just brightening
+/// or darkening it.
+///
+/// [`LuminosityBuilder`]: about:blank
+pub struct PerChannelLuminosityBuilder {
+    /// The number of shifted variants to create.
+    samples: usize,
+    /// The minimum delta of any channel.
+    min_delta: i16,
+    /// The maximum delta of any channel.
+    max_delta: i16,
+}
+
+impl PerChannelLuminosityBuilder {
+    /// Creates a builder sampling `samples` sets of deltas between `min_delta` and `max_delta`.
+    ///
+    /// # Panics
+    ///
+    /// If `min_delta` isn't below `max_delta`.
+    pub fn new(samples: usize, min_delta: i16, max_delta: i16) -> Self {
+        assert!(
+            min_delta < max_delta,
+            "the minimum channel delta {} must be below the maximum {}",
+            min_delta,
+            max_delta
+        );
+
+        Self {
+            samples,
+            min_delta,
+            max_delta,
+        }
+    }
+}
+
+impl<R: Rng> StageBuilder<Rgba<u8>, R> for PerChannelLuminosityBuilder {
+    fn variations(&self) -> usize {
+        self.samples
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !tags.0.contains(&Label::ChannelShifted)
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<Rgba<u8>> + Send + Sync>> {
+        let range = self.min_delta..=self.max_delta;
+        (0..self.samples)
+            .map(|_| {
+                Box::new(PerChannelLuminosityStage {
+                    deltas: [
+                        rng.gen_range(range.clone()),
+                        rng.gen_range(range.clone()),
+                        rng.gen_range(range.clone()),
+                    ],
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new(
+            "chan_{red}_{green}_{blue}",
+            [Label::ChannelShifted],
+        )]
+    }
+}
+
+/// The actual stage which adds `deltas` to the red, green and blue channels of every pixel,
+/// saturating at black and white. Alpha is left alone.
+pub struct PerChannelLuminosityStage {
+    /// The deltas of the red, green and blue channels.
+    deltas: [i16; 3],
+}
+
+impl ImageStage<Rgba<u8>> for PerChannelLuminosityStage {
+    fn execute(&self, img: &Image<Rgba<u8>>) -> (Image<Rgba<u8>>, Tags) {
+        let mut img = img.clone();
+        for pixel in img.pixels_mut() {
+            for (channel, delta) in pixel.0.iter_mut().zip(&self.deltas) {
+                *channel = (*channel as i16 + delta).max(0).min(255) as u8;
+            }
+        }
+
+        (img, Tags(HashSet::from_iter([Label::ChannelShifted])))
+    }
+
+    fn name(&self) -> Cow<str> {
+        let [r, g, b] = self.deltas;
+        format!("chan_{:+}_{:+}_{:+}", r, g, b).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({ "deltas": self.deltas }))
+    }
+}
+
+/// A builder that will create `samples` stages that will perform a gaussian blur on the image
+/// with a standard deviation between `min_sigma` and `max_sigma` (this is esssentially a uniform
+/// distribution over a normal distribution of blurred versions of the image).
+pub struct BlurBuilder {
+    /// The number of blurred variants to create
+    pub samples: usize,
+    /// The minimum standard deviation in the gaussian blur kernel
+    pub min_sigma: f32,
+    /// The maximum standard deviation in the gaussian blur kernel
+    pub max_sigma: f32,
+}
+
+impl<P: Pixel + 'static, R: Rng> StageBuilder<P, R> for BlurBuilder {
+    fn variations(&self) -> usize {
+        self.samples
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !(tags.0.contains(&Label::Blurred))
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<P> + Send + Sync>> {
+        rng.sample_iter(Uniform::from(self.min_sigma..self.max_sigma))
+            .take(self.samples)
+            .map(|sigma| Box::new(BlurStage { sigma }) as Box<dyn ImageStage<_> + Send + Sync>)
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new("blur_{sigma}", [Label::Blurred])]
+    }
+
+    fn conflict_group(&self) -> Option<Cow<str>> {
+        Some("sharpness".into())
+    }
+}
+
+/// The actual stage which blurs the image, it will blur the input image with a gaussian blur
+/// whose kernel's standard deviation is `sigma`.
+pub struct BlurStage {
+    /// The standard deviation of the gaussian blur kernel.
+    pub sigma: f32,
+}
+
+impl<P: Pixel + 'static> ImageStage<P> for BlurStage {
+    fn execute(&self, img: &Image<P>) -> (Image<P>, Tags) {
+        (
+            imageops::blur(img, self.sigma),
+            Tags(HashSet::from_iter([Label::Blurred])),
+        )
+    }
+
+    fn name(&self) -> Cow<str> {
+        format!("blur_{:0.2}", self.sigma).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({ "sigma": self.sigma }))
+    }
+}
+
+/// A builder that will create `samples` stages, each of which blurs the image as an out of focus lens
+/// does, by averaging over a disk with a random radius between `min_radius` and `max_radius` pixels.
+/// Like [`BlurBuilder`] its outputs are tagged as blurred, so the two are never combined.
+///
+/// [`BlurBuilder`]: about:blank
+pub struct BokehBlurBuilder {
+    /// The number of blurred variants to create.
+    pub samples: usize,
+    /// The minimum radius of the disk, in pixels.
+    pub min_radius: u32,
+    /// The maximum radius of the disk, in pixels.
+    pub max_radius: u32,
+}
+
+impl<P, R> StageBuilder<P, R> for BokehBlurBuilder
+where
+    P: Pixel + 'static,
+    P::Subpixel: ValueInto<f32> + Clamp<f32>,
+    R: Rng,
+{
+    fn variations(&self) -> usize {
+        self.samples
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !tags.0.contains(&Label::Blurred)
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<P> + Send + Sync>> {
+        let min = self.min_radius.max(1);
+        let max = self.max_radius.max(min);
+        (0..self.samples)
+            .map(|_| {
+                Box::new(BokehBlurStage {
+                    radius: rng.gen_range(min..=max),
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new("bokeh_{radius}", [Label::Blurred])]
+    }
+
+    fn conflict_group(&self) -> Option<Cow<str>> {
+        Some("sharpness".into())
+    }
+}
+
+/// The actual stage which replaces every pixel with the mean of the disk of `radius` pixels around
+/// it, with pixels beyond the image's edges clamped to them. The radius is limited to the image's
+/// smaller dimension.
+pub struct BokehBlurStage {
+    /// The radius of the disk, in pixels.
+    radius: u32,
+}
+
+impl<P> ImageStage<P> for BokehBlurStage
+where
+    P: Pixel + 'static,
+    P::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    fn execute(&self, img: &Image<P>) -> (Image<P>, Tags) {
+        let tags = Tags(HashSet::from_iter([Label::Blurred]));
+        let (width, height) = img.dimensions();
+        let radius = self.radius.min(width.min(height)) as i64;
+        if radius == 0 {
+            return (img.clone(), tags);
+        }
+
+        let offsets: Vec<_> = (-radius..=radius)
+            .flat_map(|dy| (-radius..=radius).map(move |dx| (dx, dy)))
+            .filter(|(dx, dy)| dx * dx + dy * dy <= radius * radius)
+            .collect();
+        let channels = P::CHANNEL_COUNT as usize;
+        let weight = 1. / offsets.len() as f32;
+
+        let blurred = Image::from_fn(width, height, |x, y| {
+            let mut sums = vec![0f32; channels];
+            for (dx, dy) in &offsets {
+                let sx = (x as i64 + dx).max(0).min(width as i64 - 1) as u32;
+                let sy = (y as i64 + dy).max(0).min(height as i64 - 1) as u32;
+                for (sum, c) in sums.iter_mut().zip(img.get_pixel(sx, sy).channels()) {
+                    let value: f32 = (*c).value_into().unwrap_or_default();
+                    *sum += value;
+                }
+            }
+            let mut out = *img.get_pixel(x, y);
+            for (c, sum) in out.channels_mut().iter_mut().zip(sums) {
+                *c = P::Subpixel::clamp(sum * weight);
+            }
+            out
+        });
+
+        (blurred, tags)
+    }
+
+    fn name(&self) -> Cow<str> {
+        format!("bokeh_{}", self.radius).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({ "radius": self.radius }))
+    }
+}
+
+/// The path a [`RadialBlurBuilder`] smears pixels along.
+///
+/// [`RadialBlurBuilder`]: about:blank
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RadialBlurMode {
+    /// Along circles about the center, as if the camera spun during the exposure.
+    Rotational,
+    /// Along rays from the center, as if the camera zoomed during the exposure.
+    Zoom,
+}
+
+/// A builder that will create `samples` stages, each of which blurs the image along paths about its
+/// center according to `mode`, with a random strength between `min_strength` and `max_strength`.
+/// For rotational blurs the strength is the angle swept in radians, while for zoom blurs it's the
+/// fraction of each pixel's distance from the center it's smeared towards it. With `jitter_center`
+/// set the center is moved by up to a tenth of the image's size in either direction.
+///
+/// Its outputs are tagged as blurred like those of [`BlurBuilder`], so the two are never combined.
+///
+/// [`BlurBuilder`]: about:blank
+pub struct RadialBlurBuilder {
+    /// The number of blurred variants to create.
+    pub samples: usize,
+    /// The minimum strength of the blur.
+    pub min_strength: f32,
+    /// The maximum strength of the blur.
+    pub max_strength: f32,
+    /// The path pixels are blurred along.
+    pub mode: RadialBlurMode,
+    /// Whether to move the center of the blur away from the center of the image.
+    pub jitter_center: bool,
+}
+
+impl<P, R> StageBuilder<P, R> for RadialBlurBuilder
+where
+    P: Pixel + 'static,
+    P::Subpixel: ValueInto<f32> + Clamp<f32>,
+    R: Rng,
+{
+    fn variations(&self) -> usize {
+        self.samples
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !tags.0.contains(&Label::Blurred)
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<P> + Send + Sync>> {
+        let min = self.min_strength.max(0.);
+        let max = self.max_strength.max(min);
+        (0..self.samples)
+            .map(|_| {
+                let strength = rng.gen_range(min..=max);
+                let center = if self.jitter_center {
+                    (
+                        0.5 + rng.gen_range(-0.1..=0.1),
+                        0.5 + rng.gen_range(-0.1..=0.1),
+                    )
+                } else {
+                    (0.5, 0.5)
+                };
+                Box::new(RadialBlurStage {
+                    strength,
+                    mode: self.mode,
+                    center,
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        let name = match self.mode {
+            RadialBlurMode::Rotational => "radial_{strength}",
+            RadialBlurMode::Zoom => "zoomblur_{strength}",
+        };
+        vec![StageDescriptor::new(name, [Label::Blurred])]
+    }
+
+    fn conflict_group(&self) -> Option<Cow<str>> {
+        Some("sharpness".into())
+    }
+}
+
+/// The actual stage which blurs the image along paths about `center` (relative to its size)
+/// according to `mode`. Each pixel is the mean of bilinear samples spread evenly along its path,
+/// with more samples the stronger the blur.
+pub struct RadialBlurStage {
+    /// The angle swept in radians for rotational blurs, or the fraction of the distance to the
+    /// center covered for zoom blurs.
+    strength: f32,
+    /// The path pixels are blurred along.
+    mode: RadialBlurMode,
+    /// The center of the blur, relative to the image's width and height.
+    center: (f32, f32),
+}
+
+impl RadialBlurStage {
+    /// The fewest samples taken along each path.
+    const MIN_SAMPLES: usize = 4;
+    /// The most samples taken along each path.
+    const MAX_SAMPLES: usize = 64;
+}
+
+impl<P> ImageStage<P> for RadialBlurStage
+where
+    P: Pixel + 'static,
+    P::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    fn execute(&self, img: &Image<P>) -> (Image<P>, Tags) {
+        let tags = Tags(HashSet::from_iter([Label::Blurred]));
+        let (width, height) = img.dimensions();
+        if width == 0 || height == 0 || self.strength == 0. {
+            return (img.clone(), tags);
+        }
+
+        let (cx, cy) = (self.center.0 * width as f32, self.center.1 * height as f32);
+        // The longest path is swept by the corners, so take enough samples to cover it
+        let reach = (width.max(height) as f32) * self.strength;
+        let samples = (reach.ceil() as usize)
+            .max(Self::MIN_SAMPLES)
+            .min(Self::MAX_SAMPLES);
+        let channels = P::CHANNEL_COUNT as usize;
+
+        let blurred = Image::from_fn(width, height, |x, y| {
+            let (dx, dy) = (x as f32 - cx, y as f32 - cy);
+            let mut sums = vec![0f32; channels];
+            for i in 0..samples {
+                // Spread the samples symmetrically about the pixel for rotations, and towards the
+                // center for zooms
+                let t = i as f32 / (samples - 1) as f32;
+                let (sx, sy) = match self.mode {
+                    RadialBlurMode::Rotational => {
+                        let (sin, cos) = ((t - 0.5) * self.strength).sin_cos();
+                        (cx + dx * cos - dy * sin, cy + dx * sin + dy * cos)
+                    }
+                    RadialBlurMode::Zoom => {
+                        let scale = 1. - t * self.strength.min(1.);
+                        (cx + dx * scale, cy + dy * scale)
+                    }
+                };
+                let sample = sample_bilinear(img, sx, sy);
+                for (sum, c) in sums.iter_mut().zip(sample.channels()) {
+                    let value: f32 = (*c).value_into().unwrap_or_default();
+                    *sum += value;
+                }
+            }
+            let mut out = *img.get_pixel(x, y);
+            for (c, sum) in out.channels_mut().iter_mut().zip(sums) {
+                *c = P::Subpixel::clamp(sum / samples as f32);
+            }
+            out
+        });
+
+        (blurred, tags)
+    }
+
+    fn name(&self) -> Cow<str> {
+        match self.mode {
+            RadialBlurMode::Rotational => format!("radial_{:.1}", self.strength).into(),
+            RadialBlurMode::Zoom => format!("zoomblur_{:.1}", self.strength).into(),
+        }
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({
+            "strength": self.strength,
+            "mode": format!("{:?}", self.mode),
+            "center": self.center,
+        }))
+    }
+}
+
+/// A builder that will create `samples` stages, each of which imitates a tilt-shift lens, keeping a
+/// horizontal band of the image sharp and blurring it progressively towards the top and bottom, up
+/// to a gaussian blur with a standard deviation of `max_sigma`. The band is placed at random, and its
+/// height is a random fraction of the image's height from `band_height_range`.
+///
+/// Its outputs are tagged as blurred like those of [`BlurBuilder`], so the two are never combined.
+///
+/// [`BlurBuilder`]: about:blank
+pub struct TiltShiftBuilder {
+    /// The number of blurred variants to create.
+    pub samples: usize,
+    /// The range of the height of the sharp band, relative to the image's height.
+    pub band_height_range: RangeInclusive<f32>,
+    /// The standard deviation of the blur at the top and bottom of the image.
+    pub max_sigma: f32,
+}
+
+impl<P, R> StageBuilder<P, R> for TiltShiftBuilder
+where
+    P: Pixel + 'static,
+    P::Subpixel: ValueInto<f32> + Clamp<f32>,
+    R: Rng,
+{
+    fn variations(&self) -> usize {
+        self.samples
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !tags.0.contains(&Label::Blurred)
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<P> + Send + Sync>> {
+        let min = self.band_height_range.start().max(0.).min(1.);
+        let max = self.band_height_range.end().max(min).min(1.);
+        (0..self.samples)
+            .map(|_| {
+                let band_height = rng.gen_range(min..=max);
+                let band_center = rng.gen_range(band_height / 2.0..=1. - band_height / 2.);
+                Box::new(TiltShiftStage {
+                    band_center,
+                    band_height,
+                    sigma: self.max_sigma.max(0.),
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new(
+            "tiltshift_y{center}_h{height}",
+            [Label::Blurred],
+        )]
+    }
+
+    fn conflict_group(&self) -> Option<Cow<str>> {
+        Some("sharpness".into())
+    }
+}
+
+/// The actual stage which keeps the band of rows `band_height` high (relative to the image's height)
+/// centered on `band_center` sharp, and blurs the rows outside it more the further they are from
+/// it, reaching a standard deviation of `sigma` at whichever edge of the image is furthest. The
+/// image is blurred at half and full strength once, and each row blends between those levels.
+pub struct TiltShiftStage {
+    /// The vertical center of the sharp band, relative to the image's height.
+    band_center: f32,
+    /// The height of the sharp band, relative to the image's height.
+    band_height: f32,
+    /// The standard deviation of the strongest blur.
+    sigma: f32,
+}
+
+impl<P> ImageStage<P> for TiltShiftStage
+where
+    P: Pixel + 'static,
+    P::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    fn execute(&self, img: &Image<P>) -> (Image<P>, Tags) {
+        let tags = Tags(HashSet::from_iter([Label::Blurred]));
+        let height = img.height() as f32;
+        let top = (self.band_center - self.band_height / 2.) * height;
+        let bottom = (self.band_center + self.band_height / 2.) * height;
+        let reach = top.max(height - bottom);
+        if self.sigma == 0. || reach <= 0. {
+            return (img.clone(), tags);
+        }
+
+        let levels = [
+            img.clone(),
+            imageops::blur(img, self.sigma / 2.),
+            imageops::blur(img, self.sigma),
+        ];
+        // How far each row is blurred, from `0` (sharp) to `2` (fully blurred)
+        let blend: Vec<_> = (0..img.height())
+            .map(|y| {
+                let y = y as f32 + 0.5;
+                let distance = (top - y).max(y - bottom).max(0.);
+                2. * smoothstep(0., reach, distance)
+            })
+            .collect();
+
+        let blurred = Image::from_fn(img.width(), img.height(), |x, y| {
+            let level = blend[y as usize];
+            let lower = (level.floor() as usize).min(1);
+            lerp_pixel(
+                levels[lower].get_pixel(x, y),
+                levels[lower + 1].get_pixel(x, y),
+                level - lower as f32,
+            )
+        });
+
+        (blurred, tags)
+    }
+
+    fn name(&self) -> Cow<str> {
+        format!(
+            "tiltshift_y{:.1}_h{:.1}",
+            self.band_center, self.band_height
+        )
+        .into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({
+            "band_center": self.band_center,
+            "band_height": self.band_height,
+            "sigma": self.sigma,
+        }))
+    }
+}
+
+/// A shape which [`OccludeBuilder`] can draw over an image.
+///
+/// [`OccludeBuilder`]: about:blank
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum OccluderShape {
+    /// A filled circle.
+    Circle,
+    /// A filled, axis-aligned ellipse.
+    Ellipse,
+    /// A filled, axis-aligned square.
+    Rectangle,
+    /// A filled, irregular polygon with between three and six vertices.
+    Polygon,
+}
+
+/// A builder that will create `samples` stages, each of which draws `count` randomly placed shapes
+/// (picked from `shapes`) over the image. Each shape's size is a fraction of the image's smaller
+/// dimension between `min_size` and `max_size`, and it's filled with a random color blended over the
+/// image with an opacity between `min_opacity` and `max_opacity`.
+pub struct OccludeBuilder {
+    /// The number of occluded variants to create.
+    pub samples: usize,
+    /// The number of shapes drawn over the image in each variant, at least one.
+    pub count: usize,
+    /// The minimum size of a shape, as a fraction of the image's smaller dimension.
+    pub min_size: f32,
+    /// The maximum size of a shape, as a fraction of the image's smaller dimension.
+    pub max_size: f32,
+    /// The shapes to pick from, if empty only rectangles are drawn.
+    pub shapes: Vec<OccluderShape>,
+    /// The minimum opacity of a shape, between `0` and `1`.
+    pub min_opacity: f32,
+    /// The maximum opacity of a shape, between `0` and `1`.
+    pub max_opacity: f32,
+}
+
+impl<R: Rng> StageBuilder<Rgba<u8>, R> for OccludeBuilder {
+    fn variations(&self) -> usize {
+        self.samples
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !tags.0.contains(&Label::Occluded)
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<Rgba<u8>> + Send + Sync>> {
+        (0..self.samples)
+            .map(|id| {
+                // Without any shapes the image would be tagged as occluded while left as it is
+                let occluders = (0..self.count.max(1))
+                    .map(|_| {
+                        let shape = *self.shapes.choose(rng).unwrap_or(&OccluderShape::Rectangle);
+                        let radii = Occluder::random_radii(shape, rng);
+
+                        Occluder {
+                            shape,
+                            center: (rng.gen(), rng.gen()),
+                            size: rng.gen_range(self.min_size..=self.max_size),
+                            color: rng.gen(),
+                            opacity: rng.gen_range(self.min_opacity..=self.max_opacity),
+                            radii,
+                        }
+                    })
+                    .collect();
+
+                Box::new(OccludeStage { id, occluders })
+                    as Box<dyn ImageStage<Rgba<u8>> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new(
+            format!("occlude_{}_{{index}}", self.count.max(1)),
+            [Label::Occluded],
+        )]
+    }
+}
+
+/// A single shape drawn by an `OccludeStage`, with its position and size relative
+/// to the dimensions of the image it's drawn on.
+pub struct Occluder {
+    /// The kind of shape to draw.
+    shape: OccluderShape,
+    /// The center of the shape, as fractions of the image's width and height.
+    center: (f32, f32),
+    /// The size of the shape, as a fraction of the image's smaller dimension.
+    size: f32,
+    /// The RGB color the shape is filled with.
+    color: [u8; 3],
+    /// How opaque the shape is, between `0` and `1`.
+    opacity: f32,
+    /// For polygons, the distance of each (evenly spaced) vertex from the center as a fraction
+    /// of half the shape's size, and for ellipses, the horizontal and vertical radii as fractions
+    /// of half the shape's size.
+    radii: Vec<f32>,
+}
+
+impl Occluder {
+    /// Samples the `radii` of a `shape`, which only polygons and ellipses have.
+    fn random_radii<R: Rng>(shape: OccluderShape, rng: &mut R) -> Vec<f32> {
+        match shape {
+            OccluderShape::Polygon => {
+                let vertices = rng.gen_range(3..=6);
+                (0..vertices).map(|_| rng.gen_range(0.5..=1.)).collect()
+            }
+            OccluderShape::Ellipse => vec![rng.gen_range(0.3..=1.), rng.gen_range(0.3..=1.)],
+            _ => vec![],
+        }
+    }
+
+    /// Draws the shape at full intensity on `mask`, clipping it to the mask's bounds.
+    fn draw(&self, mask: &mut GrayImage) {
+        let (width, height) = mask.dimensions();
+        let size = (self.size * width.min(height) as f32).max(1.);
+        let (cx, cy) = (self.center.0 * width as f32, self.center.1 * height as f32);
+
+        match self.shape {
+            OccluderShape::Circle => draw_filled_circle_mut(
+                mask,
+                (cx as i32, cy as i32),
+                (size / 2.) as i32,
+                Luma([255]),
+            ),
+            OccluderShape::Ellipse => {
+                let radius = |idx: usize| {
+                    let fraction = self.radii.get(idx).copied().unwrap_or(1.);
+                    ((fraction * size / 2.) as i32).max(1)
+                };
+                draw_filled_ellipse_mut(
+                    mask,
+                    (cx as i32, cy as i32),
+                    radius(0),
+                    radius(1),
+                    Luma([255]),
+                )
+            }
+            OccluderShape::Rectangle => draw_filled_rect_mut(
+                mask,
+                Rect::at((cx - size / 2.) as i32, (cy - size / 2.) as i32)
+                    .of_size(size as u32, size as u32),
+                Luma([255]),
+            ),
+            OccluderShape::Polygon => {
+                let mut points: Vec<_> = self
+                    .radii
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, radius)| {
+                        let angle =
+                            2. * std::f32::consts::PI * idx as f32 / self.radii.len() as f32;
+                        let radius = radius * size / 2.;
+                        Point::new(
+                            (cx + angle.cos() * radius) as i32,
+                            (cy + angle.sin() * radius) as i32,
+                        )
+                    })
+                    .collect();
+                points.dedup();
+                // `draw_polygon_mut` panics if the polygon is closed, which tiny shapes can
+                // degenerate into
+                if points.len() >= 3 && points.first() != points.last() {
+                    draw_polygon_mut(mask, &points, Luma([255]));
+                }
+            }
+        }
+    }
+}
+
+/// The actual stage which occludes the image, it will blend each of its `occluders` over the
+/// image in order. Outputs are named by the number of shapes, followed by the variant's `id` to
+/// tell them apart.
+pub struct OccludeStage {
+    /// The index of this variant, used to tell apart the outputs of the same builder.
+    id: usize,
+    /// The shapes to draw over the image.
+    occluders: Vec<Occluder>,
+}
+
+impl ImageStage<Rgba<u8>> for OccludeStage {
+    fn execute(&self, img: &Image<Rgba<u8>>) -> (Image<Rgba<u8>>, Tags) {
+        let mut img = img.clone();
+        for occluder in &self.occluders {
+            let mut mask = GrayImage::new(img.width(), img.height());
+            occluder.draw(&mut mask);
+
+            for (pixel, _) in img
+                .pixels_mut()
+                .zip(mask.pixels())
+                .filter(|(_, mask)| mask[0] > 0)
+            {
+                for (channel, color) in pixel.0.iter_mut().zip(occluder.color.iter()) {
+                    *channel = (*channel as f32 * (1. - occluder.opacity)
+                        + *color as f32 * occluder.opacity)
+                        .round() as u8;
+                }
+            }
+        }
+
+        (img, Tags(HashSet::from_iter([Label::Occluded])))
+    }
+
+    fn name(&self) -> Cow<str> {
+        format!("occlude_{}_{}", self.occluders.len(), self.id).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        let occluders: Vec<_> = self
+            .occluders
+            .iter()
+            .map(|occluder| {
+                json!({
+                    "shape": format!("{:?}", occluder.shape),
+                    "center": occluder.center,
+                    "size": occluder.size,
+                    "color": occluder.color,
+                    "opacity": occluder.opacity,
+                    "radii": occluder.radii,
+                })
+            })
+            .collect();
+        Some(json!({ "occluders": occluders }))
+    }
+}
+
+/// A builder that will create `samples` stages which tint the image with a rainbow, by shifting each
+/// pixel's hue proportionally to its position in the image. The strength is the fraction of the full
+/// hue wheel swept from one side of the image to the other (so `1.0` is a full rainbow), and is
+/// sampled uniformly from `strength_range`.
+pub struct RainbowBuilder {
+    /// The number of rainbow variants to create.
+    pub samples: usize,
+    /// The range the strength of the hue shift is sampled from.
+    pub strength_range: Range<f32>,
+    /// Whether the hue shifts outward from the center of the image, rather than from left to right.
+    pub radial: bool,
+}
+
+impl<R: Rng> StageBuilder<Rgba<u8>, R> for RainbowBuilder {
+    fn variations(&self) -> usize {
+        self.samples
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !tags.0.contains(&Label::Rainbow)
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<Rgba<u8>> + Send + Sync>> {
+        rng.sample_iter(Uniform::from(self.strength_range.clone()))
+            .take(self.samples)
+            .map(|strength| {
+                Box::new(RainbowStage {
+                    strength,
+                    radial: self.radial,
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new("rainbow_{strength}", [Label::Rainbow])]
+    }
+}
+
+/// The actual stage which tints the image, each pixel's hue is rotated by `strength` turns of the
+/// hue wheel multiplied by how far along the image (or how far from its center) the pixel is.
+pub struct RainbowStage {
+    /// The fraction of the hue wheel swept across the image.
+    strength: f32,
+    /// Whether the hue shifts radially from the center, rather than horizontally.
+    radial: bool,
+}
+
+impl ImageStage<Rgba<u8>> for RainbowStage {
+    fn execute(&self, img: &Image<Rgba<u8>>) -> (Image<Rgba<u8>>, Tags) {
+        let (width, height) = img.dimensions();
+        let (cx, cy) = (width as f32 / 2., height as f32 / 2.);
+        let max_distance = cx.hypot(cy).max(1.);
+
+        let mut img = img.clone();
+        for (x, y, pixel) in img.enumerate_pixels_mut() {
+            let position = if self.radial {
+                (x as f32 + 0.5 - cx).hypot(y as f32 + 0.5 - cy) / max_distance
+            } else {
+                x as f32 / width as f32
+            };
+
+            let [hue, saturation, value] = rgb_to_hsv([pixel[0], pixel[1], pixel[2]]);
+            let [r, g, b] = hsv_to_rgb([hue + 360. * self.strength * position, saturation, value]);
+            *pixel = Rgba([r, g, b, pixel[3]]);
+        }
+
+        (img, Tags(HashSet::from_iter([Label::Rainbow])))
+    }
+
+    fn name(&self) -> Cow<str> {
+        format!("rainbow_{:.2}", self.strength).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({ "strength": self.strength, "radial": self.radial }))
+    }
+}
+
+/// A debugging aid that yields a single stage drawing a reference grid of `cells` by `cells` over
+/// the image in the given `color` (`cells: 3` gives rule-of-thirds lines), which makes it easy to see
+/// how geometric stages warp the image when added before them.
+pub struct GridBuilder<P: Pixel> {
+    /// The number of rows and columns the grid divides the image into.
+    pub cells: u32,
+    /// The color of the grid lines.
+    pub color: P,
+}
+
+impl<P, R> StageBuilder<P, R> for GridBuilder<P>
+where
+    P: Pixel + Send + Sync + 'static,
+    R: Rng,
+{
+    fn variations(&self) -> usize {
+        1
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !tags.0.contains(&Label::Grid)
+    }
+
+    fn build_stage(&self, _: &mut R) -> Vec<Box<dyn ImageStage<P> + Send + Sync>> {
+        vec![Box::new(GridStage {
+            cells: self.cells,
+            color: self.color,
+        })]
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new("grid", [Label::Grid])]
+    }
+}
+
+/// The actual stage which draws a one pixel wide grid of `cells` by `cells` over the image.
+pub struct GridStage<P: Pixel> {
+    /// The number of rows and columns the grid divides the image into.
+    cells: u32,
+    /// The color of the grid lines.
+    color: P,
+}
+
+impl<P: Pixel + 'static> ImageStage<P> for GridStage<P> {
+    fn execute(&self, img: &Image<P>) -> (Image<P>, Tags) {
+        let (width, height) = img.dimensions();
+        let mut img = img.clone();
+
+        for line in 1..self.cells {
+            let x = (width as u64 * line as u64 / self.cells as u64) as u32;
+            let y = (height as u64 * line as u64 / self.cells as u64) as u32;
+            for row in 0..height {
+                img.put_pixel(x, row, self.color);
+            }
+            for column in 0..width {
+                img.put_pixel(column, y, self.color);
+            }
+        }
+
+        (img, Tags(HashSet::from_iter([Label::Grid])))
+    }
+
+    fn name(&self) -> Cow<str> {
+        "grid".into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({ "cells": self.cells }))
+    }
+}
+
+/// A builder that will create `samples` stages which blur a randomly placed elliptical or
+/// rectangular region of the image, simulating a selective focus. Each region's half-extents are a
+/// fraction of the image's width and height between `min_size` and `max_size`, and it's blurred with
+/// a gaussian blur whose standard deviation is between `min_sigma` and `max_sigma`. The blurred
+/// region is blended back into the sharp image over the outer `feather` fraction of the region,
+/// so there's no hard edge between them.
+pub struct RegionBlurBuilder {
+    /// The number of region blurred variants to create.
+    pub samples: usize,
+    /// The minimum half-extent of the region, as a fraction of the image's dimensions.
+    pub min_size: f32,
+    /// The maximum half-extent of the region, as a fraction of the image's dimensions.
+    pub max_size: f32,
+    /// The minimum standard deviation in the gaussian blur kernel.
+    pub min_sigma: f32,
+    /// The maximum standard deviation in the gaussian blur kernel.
+    pub max_sigma: f32,
+    /// The fraction of the region (from its edge inward) over which the blur fades out.
+    pub feather: f32,
+}
+
+impl<P, R> StageBuilder<P, R> for RegionBlurBuilder
+where
+    P: Pixel + 'static,
+    <P as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+    R: Rng,
+{
+    fn variations(&self) -> usize {
+        self.samples
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !(tags.0.contains(&Label::Blurred) || tags.0.contains(&Label::RegionBlurred))
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<P> + Send + Sync>> {
+        (0..self.samples)
+            .map(|id| {
+                Box::new(RegionBlurStage {
+                    id,
+                    center: (rng.gen(), rng.gen()),
+                    size: (
+                        rng.gen_range(self.min_size..=self.max_size),
+                        rng.gen_range(self.min_size..=self.max_size),
+                    ),
+                    elliptical: rng.gen(),
+                    sigma: rng.gen_range(self.min_sigma..=self.max_sigma),
+                    feather: self.feather,
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new(
+            "regionblur_{index}",
+            [Label::RegionBlurred],
+        )]
+    }
+}
+
+/// The actual stage which blurs a region of the image, pixels inside the region are taken from a
+/// copy of the image blurred with a gaussian blur whose standard deviation is `sigma`, fading into
+/// the sharp image toward the region's edge.
+pub struct RegionBlurStage {
+    /// The index of this variant, used to tell apart the outputs of the same builder.
+    id: usize,
+    /// The center of the region, as fractions of the image's width and height.
+    center: (f32, f32),
+    /// The half-extents of the region, as fractions of the image's width and height.
+    size: (f32, f32),
+    /// Whether the region is an ellipse, rather than a rectangle.
+    elliptical: bool,
+    /// The standard deviation of the gaussian blur kernel.
+    sigma: f32,
+    /// The fraction of the region over which the blur fades out.
+    feather: f32,
+}
+
+impl<P> ImageStage<P> for RegionBlurStage
+where
+    P: Pixel + 'static,
+    <P as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    fn execute(&self, img: &Image<P>) -> (Image<P>, Tags) {
+        let (width, height) = img.dimensions();
+        let (cx, cy) = (self.center.0 * width as f32, self.center.1 * height as f32);
+        let rx = (self.size.0 * width as f32).max(1.);
+        let ry = (self.size.1 * height as f32).max(1.);
+
+        let blurred = imageops::blur(img, self.sigma);
+        let mut img = img.clone();
+        for (x, y, pixel) in img.enumerate_pixels_mut() {
+            let (dx, dy) = ((x as f32 + 0.5 - cx) / rx, (y as f32 + 0.5 - cy) / ry);
+            // The normalized distance from the center, `1` being on the region's edge
+            let distance = if self.elliptical {
+                dx.hypot(dy)
+            } else {
+                dx.abs().max(dy.abs())
+            };
+            let weight = 1. - smoothstep(1. - self.feather, 1., distance);
+            if weight > 0. {
+                *pixel = lerp_pixel(pixel, blurred.get_pixel(x, y), weight);
+            }
+        }
+
+        (img, Tags(HashSet::from_iter([Label::RegionBlurred])))
+    }
+
+    fn name(&self) -> Cow<str> {
+        format!("regionblur_{}", self.id).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({
+            "center": self.center,
+            "size": self.size,
+            "elliptical": self.elliptical,
+            "sigma": self.sigma,
+            "feather": self.feather,
+        }))
+    }
+}
+
+/// How a [`ChromaticAberrationBuilder`] shifts the red and blue channels.
+///
+/// [`ChromaticAberrationBuilder`]: about:blank
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AberrationMode {
+    /// The red channel is shifted inward and the blue channel outward, growing with the distance
+    /// from the center of the image, as lenses fringe toward their edges.
+    Radial,
+    /// The red and blue channels are shifted uniformly in opposite directions, in a random
+    /// direction per stage, as with a misaligned sensor or cheap print.
+    Lateral,
+}
+
+impl Default for AberrationMode {
+    fn default() -> Self {
+        Self::Radial
+    }
+}
+
+/// A builder that will create `samples` stages simulating the chromatic aberration of a cheap lens,
+/// by shifting the red and blue channels in opposite directions according to `mode`. The shift is
+/// between `min_shift` and `max_shift` pixels (in the corners, for radial shifts).
+pub struct ChromaticAberrationBuilder {
+    /// The number of aberrated variants to create.
+    pub samples: usize,
+    /// The minimum shift of the red and blue channels, in pixels.
+    pub min_shift: f32,
+    /// The maximum shift of the red and blue channels, in pixels.
+    pub max_shift: f32,
+    /// How the channels are shifted.
+    pub mode: AberrationMode,
+}
+
+impl<R: Rng> StageBuilder<Rgba<u8>, R> for ChromaticAberrationBuilder {
+    fn variations(&self) -> usize {
+        self.samples
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !tags.0.contains(&Label::ChromaticAberration)
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<Rgba<u8>> + Send + Sync>> {
+        let shifts: Vec<f32> = rng
+            .sample_iter(Uniform::from(self.min_shift..self.max_shift))
+            .take(self.samples)
+            .collect();
+        shifts
+            .into_iter()
+            .map(|shift| {
+                let direction = match self.mode {
+                    AberrationMode::Radial => None,
+                    AberrationMode::Lateral => Some(rng.gen_range(0f32..360.)),
+                };
+                Box::new(ChromaticAberrationStage { shift, direction })
+                    as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        let name = match self.mode {
+            AberrationMode::Radial => "chroma_{shift}px",
+            AberrationMode::Lateral => "chroma_{shift}px_{direction}deg",
+        };
+        vec![StageDescriptor::new(name, [Label::ChromaticAberration])]
+    }
+}
+
+/// The actual stage which aberrates the image, the red and blue channels are resampled (with
+/// bilinear interpolation, clamping at the edges) from points offset in opposite directions. With a
+/// `direction` they're offset by `shift` pixels along it, otherwise they're scaled toward and away
+/// from the center respectively, so that they're `shift` pixels off in the corners. Alpha is left
+/// untouched.
+pub struct ChromaticAberrationStage {
+    /// How far the red and blue channels are shifted (in the image's corners if radial), in pixels.
+    shift: f32,
+    /// The direction the red channel is shifted in degrees, clockwise from the right, or `None` to
+    /// shift it radially.
+    direction: Option<f32>,
+}
+
+impl ImageStage<Rgba<u8>> for ChromaticAberrationStage {
+    fn execute(&self, img: &Image<Rgba<u8>>) -> (Image<Rgba<u8>>, Tags) {
+        let (width, height) = img.dimensions();
+        let (cx, cy) = (width as f32 / 2., height as f32 / 2.);
+        let scale = self.shift / cx.hypot(cy).max(1.);
+
+        let mut out = img.clone();
+        if let Some(direction) = self.direction {
+            let (sy, sx) = direction.to_radians().sin_cos();
+            let (sx, sy) = (sx * self.shift, sy * self.shift);
+            for (x, y, pixel) in out.enumerate_pixels_mut() {
+                let (x, y) = (x as f32, y as f32);
+                pixel[0] = sample_bilinear(img, x - sx, y - sy)[0];
+                pixel[2] = sample_bilinear(img, x + sx, y + sy)[2];
+            }
+            return (out, Tags(HashSet::from_iter([Label::ChromaticAberration])));
+        }
+
+        for (x, y, pixel) in out.enumerate_pixels_mut() {
+            let (dx, dy) = (x as f32 + 0.5 - cx, y as f32 + 0.5 - cy);
+            let red = sample_bilinear(
+                img,
+                cx + dx * (1. - scale) - 0.5,
+                cy + dy * (1. - scale) - 0.5,
+            );
+            let blue = sample_bilinear(
+                img,
+                cx + dx * (1. + scale) - 0.5,
+                cy + dy * (1. + scale) - 0.5,
+            );
+            pixel[0] = red[0];
+            pixel[2] = blue[2];
+        }
+
+        (out, Tags(HashSet::from_iter([Label::ChromaticAberration])))
+    }
+
+    fn name(&self) -> Cow<str> {
+        match self.direction {
+            Some(direction) => format!("chroma_{:.1}px_{:.0}deg", self.shift, direction).into(),
+            None => format!("chroma_{:.1}px", self.shift).into(),
+        }
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({ "shift": self.shift, "direction": self.direction }))
+    }
+}
+
+/// A builder that yields a single stage splitting the image into a grid of `rows` by `cols` tiles,
+/// each of which is treated as its own output by the rest of the pipeline. When the dimensions
+/// aren't divisible by the grid, the last row and column of tiles are slightly larger so no pixels
+/// are dropped.
+pub struct TileBuilder {
+    /// The number of rows of tiles.
+    pub rows: u32,
+    /// The number of columns of tiles.
+    pub cols: u32,
+}
+
+impl<P: Pixel + 'static, R: Rng> StageBuilder<P, R> for TileBuilder {
+    fn variations(&self) -> usize {
+        1
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !tags.0.contains(&Label::Tiled)
+    }
+
+    fn build_stage(&self, _: &mut R) -> Vec<Box<dyn ImageStage<P> + Send + Sync>> {
+        vec![Box::new(TileStage {
+            rows: self.rows.max(1),
+            cols: self.cols.max(1),
+        })]
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new(
+            format!("tile_{}x{}_{{index}}", self.rows.max(1), self.cols.max(1)),
+            [Label::Tiled],
+        )]
+    }
+}
+
+/// The actual stage which splits the image into `rows` by `cols` tiles, in row-major order.
+pub struct TileStage {
+    /// The number of rows of tiles.
+    rows: u32,
+    /// The number of columns of tiles.
+    cols: u32,
+}
+
+impl<P: Pixel + 'static> ImageStage<P> for TileStage {
+    fn execute(&self, img: &Image<P>) -> (Image<P>, Tags) {
+        self.execute_many(img).swap_remove(0)
+    }
+
+    fn execute_many(&self, img: &Image<P>) -> Vec<(Image<P>, Tags)> {
+        let (width, height) = img.dimensions();
+        let (tile_width, tile_height) = (width / self.cols, height / self.rows);
+        if tile_width == 0 || tile_height == 0 {
+            return vec![(img.clone(), Tags(HashSet::from_iter([Label::Tiled])))];
+        }
+
+        (0..self.rows)
+            .flat_map(|row| (0..self.cols).map(move |col| (row, col)))
+            .map(|(row, col)| {
+                let (x, y) = (col * tile_width, row * tile_height);
+                let w = if col == self.cols - 1 {
+                    width - x
+                } else {
+                    tile_width
+                };
+                let h = if row == self.rows - 1 {
+                    height - y
+                } else {
+                    tile_height
+                };
+                (
+                    imageops::crop_imm(img, x, y, w, h).to_image(),
+                    Tags(HashSet::from_iter([Label::Tiled])),
+                )
+            })
+            .collect()
+    }
+
+    fn name(&self) -> Cow<str> {
+        format!("tile_{}x{}", self.rows, self.cols).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({ "rows": self.rows, "cols": self.cols }))
+    }
+}
+
+/// A builder that yields a single stage posterizing the image to `levels` values per channel while
+/// applying an ordered (Bayer matrix) dither of `matrix_size` by `matrix_size` pixels, for a
+/// deliberately lo-fi look distinct from either effect alone. `levels` is clamped to at least `2`,
+/// and `matrix_size` is rounded up to a power of two between `2` and `16`.
+pub struct RetroBuilder {
+    /// The number of values each color channel is quantized to.
+    pub levels: u8,
+    /// The width and height of the dither matrix.
+    pub matrix_size: u32,
+}
+
+impl<R: Rng> StageBuilder<Rgba<u8>, R> for RetroBuilder {
+    fn variations(&self) -> usize {
+        1
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !tags.0.contains(&Label::Retro)
+    }
+
+    fn build_stage(&self, _: &mut R) -> Vec<Box<dyn ImageStage<Rgba<u8>> + Send + Sync>> {
+        vec![Box::new(RetroStage::new(self.levels, self.matrix_size))]
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new(
+            format!("retro_{}", self.levels.max(2)),
+            [Label::Retro],
+        )]
+    }
+}
+
+/// The actual stage which quantizes every color channel to `levels` values, offsetting each pixel
+/// by its threshold in the Bayer matrix before rounding. Alpha is left alone.
+pub struct RetroStage {
+    /// The number of values each color channel is quantized to.
+    levels: u8,
+    /// The Bayer matrix thresholds in row-major order, each between `-0.5` and `0.5`.
+    thresholds: Vec<f32>,
+    /// The width and height of the Bayer matrix.
+    size: u32,
+}
+
+impl RetroStage {
+    /// Creates the stage, building a Bayer matrix of `matrix_size` (rounded up to a power of two
+    /// between `2` and `16`).
+    fn new(levels: u8, matrix_size: u32) -> Self {
+        let size = matrix_size.clamp(2, 16).next_power_of_two();
+
+        // Each doubling tiles the previous matrix four times, offset as in the 2x2 base matrix
+        let mut matrix = vec![0u32];
+        let mut n = 1;
+        while n < size {
+            let mut next = vec![0; (4 * n * n) as usize];
+            for y in 0..2 * n {
+                for x in 0..2 * n {
+                    let base = matrix[((y % n) * n + x % n) as usize];
+                    let offset = [0, 2, 3, 1][((y / n) * 2 + x / n) as usize];
+                    next[(y * 2 * n + x) as usize] = 4 * base + offset;
+                }
+            }
+            matrix = next;
+            n *= 2;
+        }
+
+        let cells = (size * size) as f32;
+        Self {
+            levels: levels.max(2),
+            thresholds: matrix
+                .into_iter()
+                .map(|v| (v as f32 + 0.5) / cells - 0.5)
+                .collect(),
+            size,
+        }
+    }
+}
+
+impl ImageStage<Rgba<u8>> for RetroStage {
+    fn execute(&self, img: &Image<Rgba<u8>>) -> (Image<Rgba<u8>>, Tags) {
+        let step = 255. / (self.levels - 1) as f32;
+
+        let mut img = img.clone();
+        for (x, y, pixel) in img.enumerate_pixels_mut() {
+            let threshold = self.thresholds[((y % self.size) * self.size + x % self.size) as usize];
+            for c in pixel.0.iter_mut().take(3) {
+                let level = (*c as f32 / step + threshold)
+                    .round()
+                    .clamp(0., (self.levels - 1) as f32);
+                *c = (level * step).round() as u8;
+            }
+        }
+
+        (img, Tags(HashSet::from_iter([Label::Retro])))
+    }
+
+    fn name(&self) -> Cow<str> {
+        format!("retro_{}", self.levels).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({ "levels": self.levels, "matrix_size": self.size }))
+    }
+}
+
+/// A builder that will create `samples` stages jittering the hue, saturation and value of the image
+/// all at once, in a single round trip through HSV (the equivalent of torchvision's `ColorJitter`).
+/// The hue is shifted by a number of degrees sampled from `hue_range`, while the saturation and value
+/// are multiplied by factors sampled from `saturation_range` and `value_range`. The ranges are
+/// inclusive, so a component can be left untouched with a range such as `1.0..=1.0`. Symmetric
+/// ranges around no change can be set up with [`HsvJitterBuilder::new`].
+///
+/// [`HsvJitterBuilder::new`]: about:blank
+pub struct HsvJitterBuilder {
+    /// The number of jittered variants to create.
+    pub samples: usize,
+    /// The range of the hue shift, in degrees.
+    pub hue_range: RangeInclusive<f32>,
+    /// The range of the factor the saturation is multiplied by.
+    pub saturation_range: RangeInclusive<f32>,
+    /// The range of the factor the value is multiplied by.
+    pub value_range: RangeInclusive<f32>,
+}
+
+impl HsvJitterBuilder {
+    /// Creates a builder shifting the hue by up to `hue_deg` degrees either way, and scaling the
+    /// saturation and value by up to `sat_frac` and `val_frac` up or down (so a `sat_frac` of `0.2`
+    /// scales the saturation by a factor between `0.8` and `1.2`).
+    ///
+    /// # Panics
+    ///
+    /// If any of the bounds is negative.
+    pub fn new(samples: usize, hue_deg: f32, sat_frac: f32, val_frac: f32) -> Self {
+        assert!(
+            hue_deg >= 0. && sat_frac >= 0. && val_frac >= 0.,
+            "the HSV jitter bounds ({}, {}, {}) can't be negative",
+            hue_deg,
+            sat_frac,
+            val_frac
+        );
+
+        Self {
+            samples,
+            hue_range: -hue_deg..=hue_deg,
+            saturation_range: (1. - sat_frac).max(0.)..=1. + sat_frac,
+            value_range: (1. - val_frac).max(0.)..=1. + val_frac,
+        }
+    }
+}
+
+impl<R: Rng> StageBuilder<Rgba<u8>, R> for HsvJitterBuilder {
+    fn variations(&self) -> usize {
+        self.samples
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        // Color jitter shifts the same properties
+        ![
+            Label::HsvJittered,
+            Label::HueShifted,
+            Label::SaturationJittered,
+        ]
+        .iter()
+        .any(|label| tags.0.contains(label))
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<Rgba<u8>> + Send + Sync>> {
+        (0..self.samples)
+            .map(|_| {
+                Box::new(HsvJitterStage {
+                    hue: rng.gen_range(self.hue_range.clone()),
+                    saturation: rng.gen_range(self.saturation_range.clone()),
+                    value: rng.gen_range(self.value_range.clone()),
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new(
+            "hsv_{hash}",
+            [
+                Label::HsvJittered,
+                Label::HueShifted,
+                Label::SaturationJittered,
+            ],
+        )]
+    }
+}
+
+/// The actual stage which jitters the image's colors, shifting the hue of every pixel by `hue`
+/// degrees and scaling its saturation and value by `saturation` and `value`, leaving alpha alone.
+/// Besides the jitter itself, the image is tagged with whichever of its hue and saturation actually
+/// changed.
+pub struct HsvJitterStage {
+    /// The hue shift, in degrees.
+    hue: f32,
+    /// The factor the saturation is multiplied by.
+    saturation: f32,
+    /// The factor the value is multiplied by.
+    value: f32,
+}
+
+impl ImageStage<Rgba<u8>> for HsvJitterStage {
+    fn execute(&self, img: &Image<Rgba<u8>>) -> (Image<Rgba<u8>>, Tags) {
+        let mut img = img.clone();
+        for pixel in img.pixels_mut() {
+            let [h, s, v] = rgb_to_hsv([pixel[0], pixel[1], pixel[2]]);
+            let [r, g, b] = hsv_to_rgb([h + self.hue, s * self.saturation, v * self.value]);
+            *pixel = Rgba([r, g, b, pixel[3]]);
+        }
+
+        let mut tags = Tags(HashSet::from_iter([Label::HsvJittered]));
+        if self.hue != 0. {
+            tags.0.insert(Label::HueShifted);
+        }
+        if self.saturation != 1. {
+            tags.0.insert(Label::SaturationJittered);
+        }
+        (img, tags)
+    }
+
+    fn name(&self) -> Cow<str> {
+        let bytes: Vec<u8> = [self.hue, self.saturation, self.value]
+            .iter()
+            .flat_map(|p| p.to_le_bytes())
+            .collect();
+        format!("hsv_{:08x}", stable_hash(&bytes) as u32).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({
+            "hue": self.hue,
+            "saturation": self.saturation,
+            "value": self.value,
+        }))
+    }
+}
+
+/// A builder that yields one stage per entry in `qualities`, each of which round trips the image
+/// through JPEG compression at that exact quality (between `1` and `100`). Unlike sampled stages,
+/// this gives a reproducible ladder of compression levels for benchmarking robustness to
+/// compression artifacts.
+pub struct JpegLadderBuilder {
+    /// The JPEG qualities to compress the image with.
+    pub qualities: Vec<u8>,
+}
+
+impl<R: Rng> StageBuilder<Rgba<u8>, R> for JpegLadderBuilder {
+    fn variations(&self) -> usize {
+        self.qualities.len()
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !tags
+            .0
+            .iter()
+            .any(|label| matches!(label, Label::JpegQuality(_)))
+    }
+
+    fn build_stage(&self, _: &mut R) -> Vec<Box<dyn ImageStage<Rgba<u8>> + Send + Sync>> {
+        self.qualities
+            .iter()
+            .map(|&quality| {
+                Box::new(JpegStage {
+                    quality: quality.max(1).min(100),
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        self.qualities
+            .iter()
+            .map(|&quality| {
+                let quality = quality.max(1).min(100);
+                StageDescriptor::new(format!("jpegq{}", quality), [Label::JpegQuality(quality)])
+            })
+            .collect()
+    }
+}
+
+/// The actual stage which compresses the image, it will encode the image as a JPEG with the given
+/// `quality` and decode it again. JPEGs can't hold an alpha channel, so it's carried over from the
+/// original image untouched.
+pub struct JpegStage {
+    /// The quality to encode the JPEG with, between `1` and `100`.
+    quality: u8,
+}
+
+impl ImageStage<Rgba<u8>> for JpegStage {
+    fn execute(&self, img: &Image<Rgba<u8>>) -> (Image<Rgba<u8>>, Tags) {
+        let tags = Tags(HashSet::from_iter([Label::JpegQuality(self.quality)]));
+        let rgb = DynamicImage::ImageRgba8(img.clone()).to_rgb8();
+
+        let mut data = vec![];
+        let decoded = JpegEncoder::new_with_quality(&mut data, self.quality)
+            .encode(&rgb, rgb.width(), rgb.height(), ColorType::Rgb8)
+            .and_then(|_| image::load_from_memory_with_format(&data, ImageFormat::Jpeg));
+        let decoded = match decoded {
+            Ok(decoded) => decoded.to_rgb8(),
+            // Only happens for degenerate (e.g. empty) images, which have nothing to compress
+            Err(_) => return (img.clone(), tags),
+        };
+
+        let mut img = img.clone();
+        for (pixel, compressed) in img.pixels_mut().zip(decoded.pixels()) {
+            *pixel = Rgba([compressed[0], compressed[1], compressed[2], pixel[3]]);
+        }
+
+        (img, tags)
+    }
+
+    fn name(&self) -> Cow<str> {
+        format!("jpegq{}", self.quality).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({ "quality": self.quality }))
+    }
+}
+
+/// Brackets the exposure of an image, as a photographer would when shooting for HDR, yielding a
+/// variant for each of `stops`. Unlike the additive shift of [`LuminosityBuilder`], exposure is
+/// multiplied in linear light and re-encoded to sRGB, which is physically correct and rolls off
+/// highlights rather than clipping them flat. Stops of `0` would leave the image as it is, so
+/// they're ignored.
+///
+/// [`LuminosityBuilder`]: about:blank
+pub struct ExposureBracketBuilder {
+    /// The exposure shifts to apply, in stops (EV), e.g. `vec![-2, -1, 1, 2]`.
+    pub stops: Vec<i8>,
+}
+
+impl ExposureBracketBuilder {
+    /// The non-zero stops to apply.
+    fn shifts(&self) -> impl Iterator<Item = i8> + '_ {
+        self.stops.iter().copied().filter(|&ev| ev != 0)
+    }
+}
+
+impl Default for ExposureBracketBuilder {
+    fn default() -> Self {
+        Self {
+            stops: vec![-2, -1, 1, 2],
+        }
+    }
+}
+
+impl<R: Rng> StageBuilder<Rgba<u8>, R> for ExposureBracketBuilder {
+    fn variations(&self) -> usize {
+        self.shifts().count()
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !tags
+            .0
+            .iter()
+            .any(|label| matches!(label, Label::Exposure(_)))
+    }
+
+    fn build_stage(&self, _: &mut R) -> Vec<Box<dyn ImageStage<Rgba<u8>> + Send + Sync>> {
+        self.shifts()
+            .map(|ev| Box::new(ExposureStage { ev }) as Box<dyn ImageStage<_> + Send + Sync>)
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        self.shifts()
+            .map(|ev| StageDescriptor::new(format!("ev_{:+}", ev), [Label::Exposure(ev)]))
+            .collect()
+    }
+}
+
+/// The actual stage which shifts the exposure of the image by `ev` stops, i.e. multiplies each
+/// color channel by `2^ev` in linear light. Alpha is left untouched.
+pub struct ExposureStage {
+    /// The exposure shift in stops.
+    ev: i8,
+}
+
+impl ImageStage<Rgba<u8>> for ExposureStage {
+    fn execute(&self, img: &Image<Rgba<u8>>) -> (Image<Rgba<u8>>, Tags) {
+        let gain = 2f32.powi(self.ev as i32);
+        // Only 256 possible inputs per channel, so map them all once upfront
+        let mut lut = [0u8; 256];
+        for (c, out) in lut.iter_mut().enumerate() {
+            let linear = (srgb_to_linear(c as f32 / 255.0) * gain).min(1.0);
+            *out = (linear_to_srgb(linear) * 255.0).round() as u8;
+        }
+
+        let mut img = img.clone();
+        for pixel in img.pixels_mut() {
+            for c in pixel.0.iter_mut().take(3) {
+                *c = lut[*c as usize];
+            }
+        }
+
+        (img, Tags(HashSet::from_iter([Label::Exposure(self.ev)])))
+    }
+
+    fn name(&self) -> Cow<str> {
+        format!("ev_{:+}", self.ev).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({ "ev": self.ev }))
+    }
+}
+
+/// A background to composite an image over, see [`FlattenBuilder`].
+///
+/// [`FlattenBuilder`]: about:blank
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Background {
+    /// A fixed solid color.
+    Color(Rgb<u8>),
+    /// A solid color picked at random for every stage.
+    Random,
+    /// A light gray checkerboard, as image editors show behind transparent areas.
+    Checkerboard,
+}
+
+impl Background {
+    /// The side of a checkerboard square, in pixels.
+    const CHECKER_SIZE: u32 = 8;
+
+    /// A short name for the background in stage names, with white and black named as such. The
+    /// background mustn't be random.
+    fn name(&self) -> Cow<'static, str> {
+        match *self {
+            Background::Color(Rgb([255, 255, 255])) => "white".into(),
+            Background::Color(Rgb([0, 0, 0])) => "black".into(),
+            Background::Color(Rgb([r, g, b])) => format!("{:02x}{:02x}{:02x}", r, g, b).into(),
+            Background::Random => "random".into(),
+            Background::Checkerboard => "checker".into(),
+        }
+    }
+
+    /// The pixel of the background at `x` and `y`. The background mustn't be random.
+    fn pixel(&self, x: u32, y: u32) -> Rgba<u8> {
+        match *self {
+            Background::Color(color) => color.to_rgba(),
+            Background::Random => Rgba([0, 0, 0, 255]),
+            Background::Checkerboard => {
+                let light = (x / Self::CHECKER_SIZE + y / Self::CHECKER_SIZE) % 2 == 0;
+                let level = if light { 255 } else { 204 };
+                Rgba([level, level, level, 255])
+            }
+        }
+    }
+}
+
+/// A builder that will create a stage for each of `backgrounds`, compositing the image over that
+/// background so it's left fully opaque. Color stages touch the color of pixels regardless of
+/// their alpha, producing garbage in fully transparent areas, so this should come before them in
+/// pipelines of transparent images (as should formats without alpha, such as JPEG). Images already
+/// tagged as flattened are skipped, and images which turn out to be opaque yield no output, since
+/// there's nothing to flatten.
+pub struct FlattenBuilder {
+    /// The backgrounds to composite the image over, e.g. white and black.
+    pub backgrounds: Vec<Background>,
+}
+
+impl<R: Rng> StageBuilder<Rgba<u8>, R> for FlattenBuilder {
+    fn variations(&self) -> usize {
+        self.backgrounds.len()
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !tags.0.contains(&Label::Flattened)
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<Rgba<u8>> + Send + Sync>> {
+        self.backgrounds
+            .iter()
+            .map(|background| {
+                let background = match *background {
+                    Background::Random => Background::Color(Rgb(rng.gen())),
+                    background => background,
+                };
+                Box::new(FlattenStage { background }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        self.backgrounds
+            .iter()
+            .map(|background| match background {
+                Background::Random => "flat_{color}".to_owned(),
+                background => format!("flat_{}", background.name()),
+            })
+            .map(|name| StageDescriptor::new(name, [Label::Flattened]))
+            .collect()
+    }
+}
+
+/// The actual stage which composites the image over `background` (a solid color or checkerboard,
+/// never random), setting every pixel's alpha to fully opaque. It yields no output at all for fully
+/// opaque images.
+pub struct FlattenStage {
+    /// The background.
+    background: Background,
+}
+
+impl ImageStage<Rgba<u8>> for FlattenStage {
+    fn execute(&self, img: &Image<Rgba<u8>>) -> (Image<Rgba<u8>>, Tags) {
+        let mut img = img.clone();
+        for (x, y, pixel) in img.enumerate_pixels_mut() {
+            let alpha = pixel[3] as f32 / u8::MAX as f32;
+            *pixel = lerp_pixel(&self.background.pixel(x, y), pixel, alpha);
+            pixel[3] = u8::MAX;
+        }
+
+        (img, Tags(HashSet::from_iter([Label::Flattened])))
+    }
+
+    fn execute_many(&self, img: &Image<Rgba<u8>>) -> Vec<(Image<Rgba<u8>>, Tags)> {
+        if img.pixels().all(|p| p[3] == u8::MAX) {
+            vec![]
+        } else {
+            vec![self.execute(img)]
+        }
+    }
+
+    fn name(&self) -> Cow<str> {
+        format!("flat_{}", self.background.name()).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        match self.background {
+            Background::Color(color) => Some(json!({ "color": color.0 })),
+            background => Some(json!({ "background": background.name() })),
+        }
+    }
+}
+
+/// A builder that will create `samples` stages, each of which adds signal-dependent shot noise, as
+/// a camera sensor does in low light, with a scale between `min_scale` and `max_scale`. Unlike
+/// additive Gaussian noise, the noise follows Poisson statistics so brighter pixels get
+/// proportionally more of it. Besides being tagged as sensor noise, the outputs are tagged as noisy
+/// in general, so other noise isn't piled on top, and images which are already noisy are skipped.
+pub struct PoissonNoiseBuilder {
+    /// The number of noise variations to generate.
+    pub samples: usize,
+    /// The minimum scale, the number of 8-bit levels each captured photon is worth. Higher scales
+    /// mean fewer photons for the same brightness, and so noisier images.
+    pub min_scale: f32,
+    /// The maximum scale, see `min_scale`.
+    pub max_scale: f32,
+}
+
+impl<R: Rng> StageBuilder<Rgba<u8>, R> for PoissonNoiseBuilder {
+    fn variations(&self) -> usize {
+        self.samples
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !tags.0.contains(&Label::SensorNoise) && !tags.0.contains(&Label::Noisy)
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<Rgba<u8>> + Send + Sync>> {
+        (0..self.samples)
+            .map(|_| {
+                Box::new(PoissonNoiseStage {
+                    scale: rng
+                        .gen_range(self.min_scale..=self.max_scale)
+                        .max(f32::EPSILON),
+                    seed: rng.gen(),
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new(
+            "shot_{scale}",
+            [Label::SensorNoise, Label::Noisy],
+        )]
+    }
+}
+
+/// The actual stage which adds shot noise, treating each color channel's value divided by `scale`
+/// as the expected number of photons captured, and replacing it by a Poisson distributed count
+/// scaled back up by `scale`. The noise is drawn from an RNG seeded by `seed`, so it's the same
+/// every time. Alpha is left untouched.
+pub struct PoissonNoiseStage {
+    /// The number of 8-bit levels each photon is worth.
+    scale: f32,
+    /// The seed of the RNG the noise is drawn from.
+    seed: u64,
+}
+
+impl ImageStage<Rgba<u8>> for PoissonNoiseStage {
+    fn execute(&self, img: &Image<Rgba<u8>>) -> (Image<Rgba<u8>>, Tags) {
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        // There are only 256 possible means, so build their distributions once upfront
+        let distributions: Vec<_> = (0..=u8::MAX)
+            .map(|c| Poisson::new(c as f32 / self.scale).ok())
+            .collect();
+
+        let mut img = img.clone();
+        for pixel in img.pixels_mut() {
+            for c in pixel.0.iter_mut().take(3) {
+                // A mean of zero has no distribution, but always yields zero photons anyway
+                if let Some(poisson) = &distributions[*c as usize] {
+                    let photons: f32 = poisson.sample(&mut rng);
+                    *c = (photons * self.scale).round().min(u8::MAX as f32) as u8;
+                }
+            }
+        }
+
+        (
+            img,
+            Tags(HashSet::from_iter([Label::SensorNoise, Label::Noisy])),
+        )
+    }
+
+    fn name(&self) -> Cow<str> {
+        format!("shot_{:.2}", self.scale).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({ "scale": self.scale, "seed": self.seed }))
+    }
+}
+
+/// A builder that will create `samples` stages, each of which adds zero-mean gaussian noise to
+/// every color channel independently, with a standard deviation (in subpixel values, e.g. between
+/// `0` and `255` for 8-bit images) between `min_stddev` and `max_stddev`.
+pub struct GaussianNoiseBuilder {
+    /// The number of noise variations to generate.
+    pub samples: usize,
+    /// The minimum standard deviation of the noise.
+    pub min_stddev: f32,
+    /// The maximum standard deviation of the noise.
+    pub max_stddev: f32,
+}
+
+impl<P, R> StageBuilder<P, R> for GaussianNoiseBuilder
+where
+    P: Pixel + 'static,
+    P::Subpixel: ValueInto<f32> + Clamp<f32>,
+    R: Rng,
+{
+    fn variations(&self) -> usize {
+        self.samples
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !tags.0.contains(&Label::Noisy)
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<P> + Send + Sync>> {
+        (0..self.samples)
+            .map(|_| {
+                Box::new(GaussianNoiseStage {
+                    stddev: rng.gen_range(self.min_stddev..=self.max_stddev).max(0.),
+                    seed: rng.gen(),
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new("gnoise_{stddev}", [Label::Noisy])]
+    }
+}
+
+/// The actual stage which adds gaussian noise with a standard deviation of `stddev` to each color
+/// channel, clamping the result to the subpixel's range. The noise is drawn from an RNG seeded by
+/// `seed`, so it's the same every time. The alpha channel, if any, is left untouched.
+pub struct GaussianNoiseStage {
+    /// The standard deviation of the noise, in subpixel values.
+    stddev: f32,
+    /// The seed of the RNG the noise is drawn from.
+    seed: u64,
+}
+
+impl<P> ImageStage<P> for GaussianNoiseStage
+where
+    P: Pixel + 'static,
+    P::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    fn execute(&self, img: &Image<P>) -> (Image<P>, Tags) {
+        let tags = Tags(HashSet::from_iter([Label::Noisy]));
+        let normal = match Normal::new(0., self.stddev) {
+            Ok(normal) => normal,
+            // Only for a non-finite standard deviation, which there's no sensible noise for
+            Err(_) => return (img.clone(), tags),
+        };
+        let colors = color_channels::<P>();
+
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let mut img = img.clone();
+        for pixel in img.pixels_mut() {
+            for c in pixel.channels_mut().iter_mut().take(colors) {
+                let value: f32 = (*c).value_into().unwrap_or_default();
+                let noise: f32 = normal.sample(&mut rng);
+                *c = Clamp::clamp(value + noise);
+            }
+        }
+
+        (img, tags)
+    }
+
+    fn name(&self) -> Cow<str> {
+        format!("gnoise_{:.1}", self.stddev).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({ "stddev": self.stddev, "seed": self.seed }))
+    }
+}
+
+/// A builder that will create `samples` stages, each of which adds the noise of a camera at high ISO,
+/// with the luma noise's standard deviation (in levels) sampled from `luma_range` and the chroma
+/// noise's from `chroma_range`. Real sensors speckle much more in color than in brightness, which
+/// plain RGB noise (as from [`GaussianNoiseBuilder`]) can't reproduce.
+///
+/// [`GaussianNoiseBuilder`]: about:blank
+pub struct IsoNoiseBuilder {
+    /// The number of noisy variants to create.
+    pub samples: usize,
+    /// The range of the standard deviation of the luma noise, in levels.
+    pub luma_range: RangeInclusive<f32>,
+    /// The range of the standard deviation of the chroma noise, in levels.
+    pub chroma_range: RangeInclusive<f32>,
+}
+
+impl<R: Rng> StageBuilder<Rgba<u8>, R> for IsoNoiseBuilder {
+    fn variations(&self) -> usize {
+        self.samples
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !(tags.0.contains(&Label::Noisy) || tags.0.contains(&Label::SensorNoise))
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<Rgba<u8>> + Send + Sync>> {
+        (0..self.samples)
+            .map(|_| {
+                Box::new(IsoNoiseStage {
+                    luma: rng.gen_range(self.luma_range.clone()).max(0.),
+                    chroma: rng.gen_range(self.chroma_range.clone()).max(0.),
+                    seed: rng.gen(),
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new(
+            "iso_l{luma}_c{chroma}",
+            [Label::SensorNoise, Label::Noisy],
+        )]
+    }
+}
+
+/// The actual stage which splits every pixel into luma and two color differences (blue and red minus
+/// luma, as in YCbCr), adds gaussian noise with a standard deviation of `luma` to the former and
+/// `chroma` to the latter, and converts back, clamping to the 8-bit range. The noise is drawn from an
+/// RNG seeded by `seed`, so it's the same every time. Alpha is left alone.
+pub struct IsoNoiseStage {
+    /// The standard deviation of the luma noise, in levels.
+    luma: f32,
+    /// The standard deviation of the chroma noise, in levels.
+    chroma: f32,
+    /// The seed of the RNG the noise is drawn from.
+    seed: u64,
+}
+
+impl ImageStage<Rgba<u8>> for IsoNoiseStage {
+    fn execute(&self, img: &Image<Rgba<u8>>) -> (Image<Rgba<u8>>, Tags) {
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let mut img = img.clone();
+        for pixel in img.pixels_mut() {
+            let [r, g, b] = [pixel[0] as f32, pixel[1] as f32, pixel[2] as f32];
+            let y = 0.299 * r + 0.587 * g + 0.114 * b;
+            let mut noise = || rng.sample::<f32, _>(rand_distr::StandardNormal);
+            let (cb, cr) = (b - y, r - y);
+            let y = y + noise() * self.luma;
+            let cb = cb + noise() * self.chroma;
+            let cr = cr + noise() * self.chroma;
+
+            let (r, b) = (y + cr, y + cb);
+            let g = (y - 0.299 * r - 0.114 * b) / 0.587;
+            for (c, value) in [r, g, b].iter().enumerate() {
+                pixel[c] = value.round().max(0.).min(255.) as u8;
+            }
+        }
+
+        (
+            img,
+            Tags(HashSet::from_iter([Label::SensorNoise, Label::Noisy])),
+        )
+    }
+
+    fn name(&self) -> Cow<str> {
+        format!("iso_l{:.0}_c{:.0}", self.luma, self.chroma).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({
+            "luma": self.luma,
+            "chroma": self.chroma,
+            "seed": self.seed,
+        }))
+    }
+}
+
+/// A builder that will create `samples` stages, each of which scales the saturation of the image
+/// by a random factor between `min_factor` and `max_factor`. Factors below `1` desaturate the
+/// image (with `0` leaving it grayscale), while factors above `1` oversaturate it.
+pub struct SaturationBuilder {
+    /// The number of saturation variations to generate.
+    pub samples: usize,
+    /// The minimum factor to scale the saturation by.
+    pub min_factor: f32,
+    /// The maximum factor to scale the saturation by.
+    pub max_factor: f32,
+}
+
+impl<R: Rng> StageBuilder<Rgba<u8>, R> for SaturationBuilder {
+    fn variations(&self) -> usize {
+        self.samples
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !tags.0.contains(&Label::SaturationJittered)
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<Rgba<u8>> + Send + Sync>> {
+        (0..self.samples)
+            .map(|_| {
+                Box::new(SaturationStage {
+                    factor: rng.gen_range(self.min_factor..=self.max_factor).max(0.),
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new(
+            "sat_{factor}",
+            [Label::SaturationJittered],
+        )]
+    }
+}
+
+/// The actual stage which scales the saturation of every pixel by `factor` in HSV space, leaving
+/// their hue, value and alpha untouched.
+pub struct SaturationStage {
+    /// The factor to scale the saturation by.
+    factor: f32,
+}
+
+impl ImageStage<Rgba<u8>> for SaturationStage {
+    fn execute(&self, img: &Image<Rgba<u8>>) -> (Image<Rgba<u8>>, Tags) {
+        let mut img = img.clone();
+        for pixel in img.pixels_mut() {
+            let [r, g, b] = scale_saturation([pixel[0], pixel[1], pixel[2]], self.factor);
+            *pixel = Rgba([r, g, b, pixel[3]]);
+        }
+
+        (img, Tags(HashSet::from_iter([Label::SaturationJittered])))
+    }
+
+    fn name(&self) -> Cow<str> {
+        format!("sat_{:.2}", self.factor).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({ "factor": self.factor }))
+    }
+}
+
+/// A builder that will create `samples` stages, each of which sharpens the image with an unsharp
+/// mask, the counterpart of [`BlurBuilder`]. The mask is the difference between the image and a
+/// copy blurred with a `sigma` between `min_sigma` and `max_sigma`, which is added back onto the
+/// image scaled by an `amount` between `min_amount` and `max_amount`.
+///
+/// Sharpening an image that was blurred (by a stage) only amplifies junk, so blurred images are
+/// never sharpened, and this shares its conflict group with [`BlurBuilder`].
+///
+/// [`BlurBuilder`]: about:blank
+pub struct SharpenBuilder {
+    /// The number of variations to generate.
+    pub samples: usize,
+    /// The minimum standard deviation of the mask's gaussian blur.
+    pub min_sigma: f32,
+    /// The maximum standard deviation of the mask's gaussian blur.
+    pub max_sigma: f32,
+    /// The minimum amount of the mask added back onto the image.
+    pub min_amount: f32,
+    /// The maximum amount of the mask added back onto the image.
+    pub max_amount: f32,
+}
+
+impl<P, R> StageBuilder<P, R> for SharpenBuilder
+where
+    P: Pixel + 'static,
+    P::Subpixel: ValueInto<f32> + Clamp<f32>,
+    R: Rng,
+{
+    fn variations(&self) -> usize {
+        self.samples
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !(tags.0.contains(&Label::Sharpened) || tags.0.contains(&Label::Blurred))
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<P> + Send + Sync>> {
+        (0..self.samples)
+            .map(|_| {
+                Box::new(SharpenStage {
+                    sigma: rng.gen_range(self.min_sigma..=self.max_sigma),
+                    amount: rng.gen_range(self.min_amount..=self.max_amount),
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new(
+            "sharp_{sigma}_a{amount}",
+            [Label::Sharpened],
+        )]
+    }
+
+    fn conflict_group(&self) -> Option<Cow<str>> {
+        Some("sharpness".into())
+    }
+}
+
+/// The actual stage which sharpens the image with an unsharp mask, adding `amount` times the
+/// difference between the image and a copy blurred with a standard deviation of `sigma`.
+pub struct SharpenStage {
+    /// The standard deviation of the mask's gaussian blur.
+    sigma: f32,
+    /// The amount of the mask added back onto the image.
+    amount: f32,
+}
+
+impl<P> ImageStage<P> for SharpenStage
+where
+    P: Pixel + 'static,
+    P::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    fn execute(&self, img: &Image<P>) -> (Image<P>, Tags) {
+        let colors = color_channels::<P>();
+        let blurred = imageops::blur(img, self.sigma);
+        let mut img = img.clone();
+        for (pixel, blurred) in img.pixels_mut().zip(blurred.pixels()) {
+            let channels = pixel.channels_mut().iter_mut().zip(blurred.channels());
+            for (c, blurred) in channels.take(colors) {
+                let value: f32 = (*c).value_into().unwrap_or_default();
+                let blurred: f32 = (*blurred).value_into().unwrap_or_default();
+                *c = Clamp::clamp(value + (value - blurred) * self.amount);
+            }
+        }
+
+        (img, Tags(HashSet::from_iter([Label::Sharpened])))
+    }
+
+    fn name(&self) -> Cow<str> {
+        format!("sharp_{:.2}_a{:.2}", self.sigma, self.amount).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({ "sigma": self.sigma, "amount": self.amount }))
+    }
+}
+
+/// A builder that will create `samples` stages, each of which pixelates the image into a mosaic of
+/// square blocks between `min_block` and `max_block` pixels wide, by downscaling the image and
+/// scaling it back up with nearest-neighbour sampling. The output is always the same size as the
+/// input.
+pub struct PixelateBuilder {
+    /// The number of block sizes to generate.
+    pub samples: usize,
+    /// The minimum width of a block in pixels, blocks a single pixel wide would do nothing so at
+    /// least two is used.
+    pub min_block: u32,
+    /// The maximum width of a block in pixels.
+    pub max_block: u32,
+}
+
+impl<P: Pixel + 'static, R: Rng> StageBuilder<P, R> for PixelateBuilder {
+    fn variations(&self) -> usize {
+        self.samples
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !tags.0.contains(&Label::Pixelated)
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<P> + Send + Sync>> {
+        let (min, max) = (self.min_block.max(2), self.max_block.max(2));
+        (0..self.samples)
+            .map(|_| {
+                Box::new(PixelateStage {
+                    block: rng.gen_range(min..=max.max(min)),
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new("pixel_{block}", [Label::Pixelated])]
+    }
+}
+
+/// The actual stage which pixelates the image into blocks `block` pixels wide, clamped to the
+/// image's smaller dimension. Images too small to hold a block over a pixel wide are skipped.
+pub struct PixelateStage {
+    /// The width of a block in pixels.
+    block: u32,
+}
+
+impl PixelateStage {
+    /// The block width actually used for a `width` by `height` image, or `None` if it's too small
+    /// to pixelate.
+    fn block_for(&self, width: u32, height: u32) -> Option<u32> {
+        let block = self.block.min(width).min(height);
+        if block <= 1 {
+            None
+        } else {
+            Some(block)
+        }
+    }
+}
+
+impl<P: Pixel + 'static> ImageStage<P> for PixelateStage {
+    fn execute(&self, img: &Image<P>) -> (Image<P>, Tags) {
+        let (width, height) = img.dimensions();
+        let block = match self.block_for(width, height) {
+            Some(block) => block,
+            None => return (img.clone(), Tags::default()),
+        };
+
+        let (cols, rows) = ((width + block - 1) / block, (height + block - 1) / block);
+        let small = imageops::resize(img, cols, rows, FilterType::Triangle);
+        (
+            imageops::resize(&small, width, height, FilterType::Nearest),
+            Tags(HashSet::from_iter([Label::Pixelated])),
+        )
+    }
+
+    fn execute_many(&self, img: &Image<P>) -> Vec<(Image<P>, Tags)> {
+        match self.block_for(img.width(), img.height()) {
+            Some(_) => vec![self.execute(img)],
+            None => vec![],
+        }
+    }
+
+    fn name(&self) -> Cow<str> {
+        format!("pixel_{}", self.block).into()
+    }
+
+    fn name_for(&self, img: &Image<P>) -> Cow<str> {
+        let block = self.block_for(img.width(), img.height());
+        format!("pixel_{}", block.unwrap_or(self.block)).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({ "block": self.block }))
+    }
+}
+
+/// What the holes cut out by a [`CutoutBuilder`] are filled with.
+///
+/// [`CutoutBuilder`]: about:blank
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CutoutFill {
+    /// A solid color.
+    Color(Rgba<u8>),
+    /// Uniformly random, opaque noise.
+    Noise,
+}
+
+/// A builder that will create `samples` stages, each of which cuts between `min_holes` and
+/// `max_holes` randomly placed rectangular holes out of the image (also known as random erasing),
+/// filling them with `fill`. Each hole has the image's aspect ratio, and covers between
+/// `min_size_frac` and `max_size_frac` of its area.
+pub struct CutoutBuilder {
+    /// The number of variations to generate.
+    pub samples: usize,
+    /// The minimum number of holes cut out of the image, at least one.
+    pub min_holes: usize,
+    /// The maximum number of holes cut out of the image.
+    pub max_holes: usize,
+    /// The minimum fraction of the image's area a hole covers, between `0` and `1`.
+    pub min_size_frac: f32,
+    /// The maximum fraction of the image's area a hole covers, between `0` and `1`.
+    pub max_size_frac: f32,
+    /// What the holes are filled with.
+    pub fill: CutoutFill,
+}
+
+impl<R: Rng> StageBuilder<Rgba<u8>, R> for CutoutBuilder {
+    fn variations(&self) -> usize {
+        self.samples
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        // Occlusions would cover up the holes, and the other way around
+        !(tags.0.contains(&Label::Cutout) || tags.0.contains(&Label::Occluded))
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<Rgba<u8>> + Send + Sync>> {
+        (0..self.samples)
+            .map(|_| {
+                // Without any holes the image would be tagged as cut out while left as it is
+                let min_holes = self.min_holes.max(1);
+                let count = rng.gen_range(min_holes..=self.max_holes.max(min_holes));
+                let holes = (0..count)
+                    .map(|_| {
+                        let area = rng
+                            .gen_range(self.min_size_frac..=self.max_size_frac)
+                            .max(0.)
+                            .min(1.);
+                        (rng.gen(), rng.gen(), area)
+                    })
+                    .collect();
+                Box::new(CutoutStage {
+                    holes,
+                    fill: self.fill,
+                    seed: rng.gen(),
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new(
+            "cutout_{holes}x{size}",
+            [Label::Cutout],
+        )]
+    }
+}
+
+/// The actual stage which cuts holes out of the image, clipping them to its bounds. Images too small
+/// for any hole to cover a pixel are skipped.
+pub struct CutoutStage {
+    /// The center of each hole as fractions of the image's width and height, along with the
+    /// fraction of the image's area it covers.
+    holes: Vec<(f32, f32, f32)>,
+    /// What the holes are filled with.
+    fill: CutoutFill,
+    /// The seed of the RNG noise is drawn from, when filling holes with noise.
+    seed: u64,
+}
+
+impl CutoutStage {
+    /// The bounds of each hole in a `width` by `height` image covering at least a pixel, as the
+    /// left, top, right and bottom edges (exclusive of the latter two).
+    fn bounds(&self, width: u32, height: u32) -> Vec<(u32, u32, u32, u32)> {
+        self.holes
+            .iter()
+            .map(|&(cx, cy, area)| {
+                let scale = area.sqrt();
+                let (w, h) = (scale * width as f32, scale * height as f32);
+                let (x, y) = (cx * width as f32 - w / 2., cy * height as f32 - h / 2.);
+                let (x0, y0) = (x.max(0.) as u32, y.max(0.) as u32);
+                let x1 = ((x + w).max(0.) as u32).min(width);
+                let y1 = ((y + h).max(0.) as u32).min(height);
+                (x0, y0, x1, y1)
+            })
+            .filter(|&(x0, y0, x1, y1)| x0 < x1 && y0 < y1)
+            .collect()
+    }
+}
+
+impl ImageStage<Rgba<u8>> for CutoutStage {
+    fn execute(&self, img: &Image<Rgba<u8>>) -> (Image<Rgba<u8>>, Tags) {
+        let (width, height) = img.dimensions();
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let mut img = img.clone();
+        for (x0, y0, x1, y1) in self.bounds(width, height) {
+            for y in y0..y1 {
+                for x in x0..x1 {
+                    let pixel = match self.fill {
+                        CutoutFill::Color(color) => color,
+                        CutoutFill::Noise => {
+                            let [r, g, b]: [u8; 3] = rng.gen();
+                            Rgba([r, g, b, u8::MAX])
+                        }
+                    };
+                    img.put_pixel(x, y, pixel);
+                }
+            }
+        }
+
+        (img, Tags(HashSet::from_iter([Label::Cutout])))
+    }
+
+    fn execute_many(&self, img: &Image<Rgba<u8>>) -> Vec<(Image<Rgba<u8>>, Tags)> {
+        if self.bounds(img.width(), img.height()).is_empty() {
+            vec![]
+        } else {
+            vec![self.execute(img)]
+        }
+    }
+
+    fn name(&self) -> Cow<str> {
+        let mean =
+            self.holes.iter().map(|hole| hole.2).sum::<f32>() / self.holes.len().max(1) as f32;
+        format!("cutout_{}x{:.2}", self.holes.len(), mean).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({ "holes": self.holes, "seed": self.seed }))
+    }
+}
+
+/// A builder that will create `samples` stages, each of which smooths the image with a median filter
+/// whose radius is between `min_radius` and `max_radius`, mimicking the artifacts of aggressive
+/// denoising. Median filtering gets expensive with large radii, so the radius is capped at
+/// [`MedianFilterStage::MAX_RADIUS`], and to half the image's smaller dimension.
+///
+/// [`MedianFilterStage::MAX_RADIUS`]: about:blank
+pub struct MedianFilterBuilder {
+    /// The number of variations to generate.
+    pub samples: usize,
+    /// The minimum radius of the filter, in pixels.
+    pub min_radius: u32,
+    /// The maximum radius of the filter, in pixels.
+    pub max_radius: u32,
+}
+
+impl<P, R> StageBuilder<P, R> for MedianFilterBuilder
+where
+    P: Pixel<Subpixel = u8> + 'static,
+    R: Rng,
+{
+    fn variations(&self) -> usize {
+        self.samples
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !tags.0.contains(&Label::MedianFiltered)
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<P> + Send + Sync>> {
+        let max = self.max_radius.min(MedianFilterStage::MAX_RADIUS);
+        let min = self.min_radius.max(1).min(max);
+        (0..self.samples)
+            .map(|_| {
+                Box::new(MedianFilterStage {
+                    radius: rng.gen_range(min..=max.max(min)),
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new(
+            "median_{radius}",
+            [Label::MedianFiltered],
+        )]
+    }
+}
+
+/// The actual stage which smooths the image with a median filter of `radius` pixels, capped to half
+/// the image's smaller dimension (so images under two pixels wide or high are skipped).
+pub struct MedianFilterStage {
+    /// The radius of the filter, in pixels.
+    radius: u32,
+}
+
+impl MedianFilterStage {
+    /// The largest radius a median filter is built with.
+    pub const MAX_RADIUS: u32 = 16;
+
+    /// The radius actually used for a `width` by `height` image, or `None` if it's too small to
+    /// filter.
+    fn radius_for(&self, width: u32, height: u32) -> Option<u32> {
+        match self.radius.min(width.min(height) / 2) {
+            0 => None,
+            radius => Some(radius),
+        }
+    }
+}
+
+impl<P: Pixel<Subpixel = u8> + 'static> ImageStage<P> for MedianFilterStage {
+    fn execute(&self, img: &Image<P>) -> (Image<P>, Tags) {
+        match self.radius_for(img.width(), img.height()) {
+            Some(radius) => (
+                median_filter(img, radius, radius),
+                Tags(HashSet::from_iter([Label::MedianFiltered])),
+            ),
+            None => (img.clone(), Tags::default()),
+        }
+    }
+
+    fn execute_many(&self, img: &Image<P>) -> Vec<(Image<P>, Tags)> {
+        match self.radius_for(img.width(), img.height()) {
+            Some(_) => vec![self.execute(img)],
+            None => vec![],
+        }
+    }
+
+    fn name(&self) -> Cow<str> {
+        format!("median_{}", self.radius).into()
+    }
+
+    fn name_for(&self, img: &Image<P>) -> Cow<str> {
+        let radius = self.radius_for(img.width(), img.height());
+        format!("median_{}", radius.unwrap_or(self.radius)).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({ "radius": self.radius }))
+    }
+}
+
+/// How the edge magnitudes of a [`SobelEdgeStage`] are scaled into the 8-bit range.
+///
+/// [`SobelEdgeStage`]: about:blank
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EdgeNormalization {
+    /// Scaled so the strongest edge of each image is white, bringing out faint edges but making
+    /// the brightness incomparable between images.
+    Max,
+    /// Scaled by a fixed factor, so a full black to white step along one axis is white, and
+    /// anything stronger is clamped.
+    Fixed,
+}
+
+/// A builder that yields a single stage replacing the image by a map of its edges, the magnitude of
+/// its Sobel gradients, normalized according to `normalization`.
+pub struct SobelEdgeBuilder {
+    /// Whether to skip images that were blurred, whose edges are mostly empty.
+    pub skip_blurred: bool,
+    /// How the edge magnitudes are scaled into the 8-bit range.
+    pub normalization: EdgeNormalization,
+}
+
+impl<R: Rng> StageBuilder<Rgba<u8>, R> for SobelEdgeBuilder {
+    fn variations(&self) -> usize {
+        1
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !(tags.0.contains(&Label::SobelEdges)
+            || (self.skip_blurred && tags.0.contains(&Label::Blurred)))
+    }
+
+    fn build_stage(&self, _: &mut R) -> Vec<Box<dyn ImageStage<Rgba<u8>> + Send + Sync>> {
+        vec![Box::new(SobelEdgeStage {
+            normalization: self.normalization,
+        })]
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new("edges", [Label::SobelEdges])]
+    }
+}
+
+/// The actual stage which replaces the image by the magnitude of the Sobel gradients of its
+/// luminance, copied into each color channel. Alpha is left untouched.
+pub struct SobelEdgeStage {
+    /// How the edge magnitudes are scaled into the 8-bit range.
+    normalization: EdgeNormalization,
+}
+
+impl ImageStage<Rgba<u8>> for SobelEdgeStage {
+    fn execute(&self, img: &Image<Rgba<u8>>) -> (Image<Rgba<u8>>, Tags) {
+        let gray = DynamicImage::ImageRgba8(img.clone()).to_luma8();
+        let gradients = sobel_gradients(&gray);
+        let scale = match self.normalization {
+            EdgeNormalization::Max => {
+                let max = gradients.pixels().map(|px| px[0]).max().unwrap_or(0);
+                u8::MAX as f32 / max.max(1) as f32
+            }
+            // The response of each Sobel kernel to a full step is 4 * 255
+            EdgeNormalization::Fixed => 1. / 4.,
+        };
+
+        let mut img = img.clone();
+        for (pixel, gradient) in img.pixels_mut().zip(gradients.pixels()) {
+            let edge = (gradient[0] as f32 * scale).round().min(u8::MAX as f32) as u8;
+            *pixel = Rgba([edge, edge, edge, pixel[3]]);
+        }
+
+        (img, Tags(HashSet::from_iter([Label::SobelEdges])))
+    }
+
+    fn name(&self) -> Cow<str> {
+        "edges".into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({ "normalization": format!("{:?}", self.normalization) }))
+    }
+}
+
+/// A builder that will create `samples` stages, each of which shifts the white balance of the image
+/// as if it were lit by a blackbody of a random temperature between `min_kelvin` and `max_kelvin`,
+/// relative to daylight (6500K). Lower temperatures are warmer (redder) and higher ones cooler
+/// (bluer).
+pub struct ColorTemperatureBuilder {
+    /// The number of temperatures to generate.
+    samples: usize,
+    /// The minimum temperature, in kelvin.
+    min_kelvin: u32,
+    /// The maximum temperature, in kelvin.
+    max_kelvin: u32,
+}
+
+impl ColorTemperatureBuilder {
+    /// The range of temperatures the blackbody approximation is sane for, in kelvin.
+    pub const KELVIN_RANGE: RangeInclusive<u32> = 1000..=12000;
+
+    /// Creates a builder sampling `samples` temperatures between `min_kelvin` and `max_kelvin`.
+    ///
+    /// # Panics
+    ///
+    /// If either temperature is outside of [`KELVIN_RANGE`], or `min_kelvin` is above `max_kelvin`,
+    /// so misconfigurations are caught before producing garbage outputs.
+    ///
+    /// [`KELVIN_RANGE`]: about:blank
+    pub fn new(samples: usize, min_kelvin: u32, max_kelvin: u32) -> Self {
+        assert!(
+            Self::KELVIN_RANGE.contains(&min_kelvin) && Self::KELVIN_RANGE.contains(&max_kelvin),
+            "color temperatures must be between 1000K and 12000K, got {}K to {}K",
+            min_kelvin,
+            max_kelvin
+        );
+        assert!(
+            min_kelvin <= max_kelvin,
+            "the minimum color temperature {}K is above the maximum {}K",
+            min_kelvin,
+            max_kelvin
+        );
+
+        Self {
+            samples,
+            min_kelvin,
+            max_kelvin,
+        }
+    }
+}
+
+impl<R: Rng> StageBuilder<Rgba<u8>, R> for ColorTemperatureBuilder {
+    fn variations(&self) -> usize {
+        self.samples
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !tags.0.contains(&Label::WhiteBalanced)
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<Rgba<u8>> + Send + Sync>> {
+        (0..self.samples)
+            .map(|_| {
+                Box::new(ColorTemperatureStage {
+                    kelvin: rng.gen_range(self.min_kelvin..=self.max_kelvin),
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new("wb_{kelvin}k", [Label::WhiteBalanced])]
+    }
+}
+
+/// Approximates the color of a blackbody at `kelvin` degrees, as red, green and blue intensities
+/// between `0` and `1`, using Tanner Helland's fit of the CIE data.
+fn blackbody(kelvin: u32) -> [f32; 3] {
+    let t = kelvin as f32 / 100.;
+    let red = if t <= 66. {
+        255.
+    } else {
+        329.698_73 * (t - 60.).powf(-0.133_204_76)
+    };
+    let green = if t <= 66. {
+        99.470_8 * t.ln() - 161.119_57
+    } else {
+        288.122_16 * (t - 60.).powf(-0.075_514_85)
+    };
+    let blue = if t >= 66. {
+        255.
+    } else if t <= 19. {
+        0.
+    } else {
+        138.517_73 * (t - 10.).ln() - 305.044_8
+    };
+
+    [red, green, blue].map(|c| c.max(0.).min(255.) / 255.)
+}
+
+/// The actual stage which shifts the white balance of the image, scaling the red and blue channels
+/// by the color of a blackbody at `kelvin` degrees relative to one at 6500K. Alpha is left
+/// untouched.
+pub struct ColorTemperatureStage {
+    /// The temperature of the light, in kelvin.
+    kelvin: u32,
+}
+
+impl ImageStage<Rgba<u8>> for ColorTemperatureStage {
+    fn execute(&self, img: &Image<Rgba<u8>>) -> (Image<Rgba<u8>>, Tags) {
+        let [red, _, blue] = blackbody(self.kelvin);
+        let [white_red, _, white_blue] = blackbody(6500);
+        let (red_gain, blue_gain) = (red / white_red, blue / white_blue);
+        let scale = |c: u8, gain: f32| (c as f32 * gain).round().min(u8::MAX as f32) as u8;
+
+        let mut img = img.clone();
+        for pixel in img.pixels_mut() {
+            pixel[0] = scale(pixel[0], red_gain);
+            pixel[2] = scale(pixel[2], blue_gain);
+        }
+
+        (img, Tags(HashSet::from_iter([Label::WhiteBalanced])))
+    }
+
+    fn name(&self) -> Cow<str> {
+        format!("wb_{}k", self.kelvin).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({ "kelvin": self.kelvin }))
+    }
+}
+
+/// How the area of an image left uncovered by a geometric transformation is filled.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Fill<P: Pixel> {
+    /// A solid color.
+    Color(P),
+    /// The nearest pixel on the image's edge, stretching the edges outward.
+    Clamp,
+    /// The image repeated, wrapping around from the opposite edge.
+    Wrap,
+    /// The image mirrored at its edges (without repeating the edge pixels themselves).
+    Reflect,
+}
+
+/// Reflects the coordinate `x` back into `0..len` by mirroring it at the bounds, as often as needed.
+fn reflect(x: i64, len: i64) -> i64 {
+    let period = 2 * (len - 1);
+    if period == 0 {
+        return 0;
+    }
+    let x = x.rem_euclid(period);
+    if x < len {
+        x
+    } else {
+        period - x
+    }
+}
+
+impl<P: Pixel> Fill<P> {
+    /// The pixel of `img` at the (possibly out of bounds) coordinates `x` and `y` according to this
+    /// fill, `img` must not be empty.
+    fn pixel(&self, img: &Image<P>, x: i64, y: i64) -> P {
+        let (width, height) = (img.width() as i64, img.height() as i64);
+        if (0..width).contains(&x) && (0..height).contains(&y) {
+            return *img.get_pixel(x as u32, y as u32);
+        }
+
+        match *self {
+            Fill::Color(color) => color,
+            Fill::Clamp => *img.get_pixel(
+                x.max(0).min(width - 1) as u32,
+                y.max(0).min(height - 1) as u32,
+            ),
+            Fill::Wrap => *img.get_pixel(x.rem_euclid(width) as u32, y.rem_euclid(height) as u32),
+            Fill::Reflect => *img.get_pixel(reflect(x, width) as u32, reflect(y, height) as u32),
+        }
+    }
+
+    /// Samples `img` at the (possibly fractional and out of bounds) coordinates `x` and `y` with
+    /// bilinear interpolation according to this fill, `img` must not be empty.
+    fn sample(&self, img: &Image<P>, x: f32, y: f32) -> P
+    where
+        P: 'static,
+        P::Subpixel: ValueInto<f32> + Clamp<f32>,
+    {
+        let (width, height) = (img.width() as f32, img.height() as f32);
+        let inside = (0. ..=width - 1.).contains(&x) && (0. ..=height - 1.).contains(&y);
+        match *self {
+            Fill::Color(color) if !inside => color,
+            Fill::Wrap if !inside => {
+                sample_bilinear(img, x.rem_euclid(width), y.rem_euclid(height))
+            }
+            Fill::Reflect if !inside => {
+                let reflect = |x: f32, len: f32| {
+                    let period = 2. * (len - 1.);
+                    if period <= 0. {
+                        return 0.;
+                    }
+                    let x = x.rem_euclid(period);
+                    if x <= len - 1. {
+                        x
+                    } else {
+                        period - x
+                    }
+                };
+                sample_bilinear(img, reflect(x, width), reflect(y, height))
+            }
+            // Bilinear sampling clamps to the edges already
+            _ => sample_bilinear(img, x, y),
+        }
+    }
+}
+
+/// A builder that will create `samples` stages, each of which shifts the image by a random offset
+/// of up to `max_dx_frac` of its width horizontally and `max_dy_frac` of its height vertically, in
+/// either direction. The area left uncovered is filled according to `fill`, and the image keeps its
+/// dimensions.
+pub struct TranslationBuilder<P: Pixel> {
+    /// The number of offsets to generate.
+    pub samples: usize,
+    /// The largest horizontal offset, as a fraction of the image's width.
+    pub max_dx_frac: f32,
+    /// The largest vertical offset, as a fraction of the image's height.
+    pub max_dy_frac: f32,
+    /// How the area left uncovered is filled.
+    pub fill: Fill<P>,
+}
+
+impl<P, R> StageBuilder<P, R> for TranslationBuilder<P>
+where
+    P: Pixel + Send + Sync + 'static,
+    R: Rng,
+{
+    fn variations(&self) -> usize {
+        self.samples
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !tags.0.contains(&Label::Translated)
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<P> + Send + Sync>> {
+        let (max_dx, max_dy) = (
+            self.max_dx_frac.abs().min(1.),
+            self.max_dy_frac.abs().min(1.),
+        );
+        (0..self.samples)
+            .map(|_| {
+                Box::new(TranslationStage {
+                    offset: (
+                        rng.gen_range(-max_dx..=max_dx),
+                        rng.gen_range(-max_dy..=max_dy),
+                    ),
+                    fill: self.fill,
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new("shift_{dx}_{dy}", [Label::Translated])]
+    }
+}
+
+/// The actual stage which shifts the image by `offset`, filling the area left uncovered according
+/// to `fill`.
+pub struct TranslationStage<P: Pixel> {
+    /// The offset, as fractions of the image's width and height.
+    offset: (f32, f32),
+    /// How the area left uncovered is filled.
+    fill: Fill<P>,
+}
+
+impl<P: Pixel + Send + Sync + 'static> ImageStage<P> for TranslationStage<P> {
+    fn execute(&self, img: &Image<P>) -> (Image<P>, Tags) {
+        let tags = Tags(HashSet::from_iter([Label::Translated]));
+        let (width, height) = img.dimensions();
+        if width == 0 || height == 0 {
+            return (img.clone(), tags);
+        }
+
+        let dx = (self.offset.0 * width as f32).round() as i64;
+        let dy = (self.offset.1 * height as f32).round() as i64;
+        let shifted = Image::from_fn(width, height, |x, y| {
+            self.fill.pixel(img, x as i64 - dx, y as i64 - dy)
+        });
+
+        (shifted, tags)
+    }
+
+    /// Named after the offset in percent of the image's width and height, e.g. `"shift_+12_-30"`.
+    fn name(&self) -> Cow<str> {
+        format!(
+            "shift_{:+.0}_{:+.0}",
+            self.offset.0 * 100.,
+            self.offset.1 * 100.
+        )
+        .into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({ "offset": self.offset }))
+    }
+}
+
+/// Conjugates `projection` so it's applied about the center of an image `width` by `height` pixels
+/// in size, rather than about its top-left corner.
+fn about_center(projection: Projection, width: u32, height: u32) -> Projection {
+    let (cx, cy) = (width as f32 / 2., height as f32 / 2.);
+    Projection::translate(cx, cy) * projection * Projection::translate(-cx, -cy)
+}
+
+/// A builder that will create `samples` stages, each of which shears the image about its center
+/// both horizontally and vertically by random factors between `-max_shear` and `max_shear`, where
+/// a factor of `1` is a 45 degree slant. The image keeps its dimensions, and the area left uncovered
+/// is filled with `fill`.
+pub struct ShearBuilder<P: Pixel> {
+    /// The number of shears to generate.
+    samples: usize,
+    /// The largest shear factor in either direction.
+    max_shear: f32,
+    /// The color the area left uncovered is filled with.
+    fill: P,
+}
+
+impl<P: Pixel> ShearBuilder<P> {
+    /// Creates a builder generating `samples` shears of up to `max_shear`, filling the area left
+    /// uncovered with `fill` (see [`zero_pixel`] for transparent black of any pixel type).
+    ///
+    /// # Panics
+    ///
+    /// If `max_shear` isn't positive.
+    ///
+    /// [`zero_pixel`]: about:blank
+    pub fn new(samples: usize, max_shear: f32, fill: P) -> Self {
+        assert!(
+            max_shear > 0.,
+            "the maximum shear must be positive, got {}",
+            max_shear
+        );
+
+        Self {
+            samples,
+            max_shear,
+            fill,
+        }
+    }
+}
+
+impl<P, R> StageBuilder<P, R> for ShearBuilder<P>
+where
+    P: Pixel + Send + Sync + 'static,
+    P::Subpixel: Send + Sync + ValueInto<f32> + Clamp<f32>,
+    R: Rng,
+{
+    fn variations(&self) -> usize {
+        self.samples
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !tags.0.contains(&Label::Sheared)
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<P> + Send + Sync>> {
+        let range = -self.max_shear..=self.max_shear;
+        (0..self.samples)
+            .map(|_| {
+                Box::new(ShearStage {
+                    shear: (rng.gen_range(range.clone()), rng.gen_range(range.clone())),
+                    fill: self.fill,
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new("shear_x{x}_y{y}", [Label::Sheared])]
+    }
+}
+
+/// The actual stage which shears the image about its center by the horizontal and vertical factors
+/// in `shear`, filling the area left uncovered with `fill`. Shears which would flatten the image
+/// onto a line (where the factors multiply to `1`) leave it as is.
+pub struct ShearStage<P: Pixel> {
+    /// The horizontal and vertical shear factors.
+    shear: (f32, f32),
+    /// The color the area left uncovered is filled with.
+    fill: P,
+}
+
+impl<P> ImageStage<P> for ShearStage<P>
+where
+    P: Pixel + Send + Sync + 'static,
+    P::Subpixel: Send + Sync + ValueInto<f32> + Clamp<f32>,
+{
+    fn execute(&self, img: &Image<P>) -> (Image<P>, Tags) {
+        let tags = Tags(HashSet::from_iter([Label::Sheared]));
+        let (sx, sy) = self.shear;
+        let projection = match Projection::from_matrix([1., sx, 0., sy, 1., 0., 0., 0., 1.]) {
+            Some(projection) => projection,
+            None => return (img.clone(), tags),
+        };
+        let projection = about_center(projection, img.width(), img.height());
+
+        (
+            geometric_transformations::warp(img, &projection, Interpolation::Bilinear, self.fill),
+            tags,
+        )
+    }
+
+    fn name(&self) -> Cow<str> {
+        format!("shear_x{:.2}_y{:.2}", self.shear.0, self.shear.1).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({ "shear": self.shear }))
+    }
+}
+
+/// A builder that will create `samples` stages, each of which applies a random affine transformation
+/// about the image's center, composing a rotation of up to `max_rotation_deg` degrees, shears of up
+/// to `max_shear`, a scale within `scale_range`, and a translation of up to `max_translate_frac` of
+/// the image's dimensions. Applying them as a single warp keeps the number of combinations down
+/// compared to stacking the separate builders, and resamples the image only once. The area left
+/// uncovered is filled with `fill`.
+pub struct RandomAffineBuilder<P: Pixel> {
+    /// The number of transformations to generate.
+    pub samples: usize,
+    /// The largest rotation in either direction, in degrees.
+    pub max_rotation_deg: f32,
+    /// The largest horizontal and vertical shear factor in either direction.
+    pub max_shear: f32,
+    /// The largest translation in either direction, as a fraction of the image's dimensions.
+    pub max_translate_frac: f32,
+    /// The range of factors to scale the image by.
+    pub scale_range: RangeInclusive<f32>,
+    /// The color the area left uncovered is filled with.
+    pub fill: P,
+}
+
+impl<P, R> StageBuilder<P, R> for RandomAffineBuilder<P>
+where
+    P: Pixel + Send + Sync + 'static,
+    P::Subpixel: Send + Sync + ValueInto<f32> + Clamp<f32>,
+    R: Rng,
+{
+    fn variations(&self) -> usize {
+        self.samples
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !(tags.0.contains(&Label::OffAxis)
+            || tags.0.contains(&Label::Sheared)
+            || tags.0.contains(&Label::Translated))
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<P> + Send + Sync>> {
+        let rotation = self.max_rotation_deg.abs();
+        let shear = self.max_shear.abs();
+        let translate = self.max_translate_frac.abs();
+        (0..self.samples)
+            .map(|_| {
+                Box::new(AffineStage {
+                    rotation: rng.gen_range(-rotation..=rotation),
+                    shear: (rng.gen_range(-shear..=shear), rng.gen_range(-shear..=shear)),
+                    scale: rng.gen_range(self.scale_range.clone()),
+                    translation: (
+                        rng.gen_range(-translate..=translate),
+                        rng.gen_range(-translate..=translate),
+                    ),
+                    fill: self.fill,
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new(
+            "affine_{hash}",
+            [Label::OffAxis, Label::Sheared, Label::Translated],
+        )]
+    }
+}
+
+/// The actual stage which applies an affine transformation to the image, scaling it by `scale`,
+/// shearing it by `shear` and rotating it by `rotation` about its center (in that order), then
+/// translating it by `translation`. It's resampled with bicubic interpolation, and the area left
+/// uncovered is filled with `fill`. Degenerate transformations which would flatten the image leave
+/// it as is.
+pub struct AffineStage<P: Pixel> {
+    /// The rotation, clockwise in degrees.
+    rotation: f32,
+    /// The horizontal and vertical shear factors.
+    shear: (f32, f32),
+    /// The factor to scale the image by.
+    scale: f32,
+    /// The translation, as fractions of the image's width and height.
+    translation: (f32, f32),
+    /// The color the area left uncovered is filled with.
+    fill: P,
+}
+
+impl<P> ImageStage<P> for AffineStage<P>
+where
+    P: Pixel + Send + Sync + 'static,
+    P::Subpixel: Send + Sync + ValueInto<f32> + Clamp<f32>,
+{
+    fn execute(&self, img: &Image<P>) -> (Image<P>, Tags) {
+        let tags = Tags(HashSet::from_iter([
+            Label::OffAxis,
+            Label::Sheared,
+            Label::Translated,
+        ]));
+        let (width, height) = img.dimensions();
+        let (sx, sy) = self.shear;
+        let shear = match Projection::from_matrix([1., sx, 0., sy, 1., 0., 0., 0., 1.]) {
+            Some(shear) => shear,
+            None => return (img.clone(), tags),
+        };
+        if self.scale.abs() <= f32::EPSILON {
+            return (img.clone(), tags);
+        }
+
+        let linear = Projection::rotate(self.rotation.to_radians())
+            * shear
+            * Projection::scale(self.scale, self.scale);
+        let projection = Projection::translate(
+            self.translation.0 * width as f32,
+            self.translation.1 * height as f32,
+        ) * about_center(linear, width, height);
+
+        (
+            geometric_transformations::warp(img, &projection, Interpolation::Bicubic, self.fill),
+            tags,
+        )
+    }
+
+    /// Named after a hash of the parameters, which are too many to fit in a filename, see
+    /// `params` for the parameters themselves.
+    fn name(&self) -> Cow<str> {
+        let params = [
+            self.rotation,
+            self.shear.0,
+            self.shear.1,
+            self.scale,
+            self.translation.0,
+            self.translation.1,
+        ];
+        let bytes: Vec<u8> = params.iter().flat_map(|p| p.to_le_bytes()).collect();
+        format!("affine_{:08x}", stable_hash(&bytes) as u32).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({
+            "rotation": self.rotation,
+            "shear": self.shear,
+            "scale": self.scale,
+            "translation": self.translation,
+        }))
+    }
+}
+
+/// A builder that will create `samples` stages, each of which elastically deforms the image (as
+/// described by Simard et al.) by displacing every pixel along a smooth random field. The field is
+/// random noise blurred with a standard deviation within `sigma_range`, which controls how smooth
+/// the deformation is, and scaled by a factor within `alpha_range`, which controls its strength.
+pub struct ElasticDeformBuilder {
+    /// The number of deformations to generate.
+    pub samples: usize,
+    /// The range of factors the displacement field is scaled by, in pixels.
+    pub alpha_range: RangeInclusive<f32>,
+    /// The range of standard deviations the displacement field is blurred with, in pixels.
+    pub sigma_range: RangeInclusive<f32>,
+}
+
+impl<P, R> StageBuilder<P, R> for ElasticDeformBuilder
+where
+    P: Pixel + 'static,
+    P::Subpixel: ValueInto<f32> + Clamp<f32>,
+    R: Rng,
+{
+    fn variations(&self) -> usize {
+        self.samples
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !tags.0.contains(&Label::Elastic)
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<P> + Send + Sync>> {
+        (0..self.samples)
+            .map(|_| {
+                Box::new(ElasticDeformStage {
+                    alpha: rng.gen_range(self.alpha_range.clone()),
+                    sigma: rng.gen_range(self.sigma_range.clone()),
+                    seed: rng.gen(),
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new(
+            "elastic_a{alpha}_s{sigma}",
+            [Label::Elastic],
+        )]
+    }
+}
+
+/// The actual stage which elastically deforms the image, every pixel is resampled (with bilinear
+/// interpolation, clamping at the edges) from a point displaced by a field of uniform noise drawn
+/// from an RNG seeded by `seed`, blurred with a standard deviation of `sigma` and scaled by `alpha`.
+pub struct ElasticDeformStage {
+    /// The factor the displacement field is scaled by, in pixels.
+    alpha: f32,
+    /// The standard deviation the displacement field is blurred with, in pixels.
+    sigma: f32,
+    /// The seed of the RNG the displacement field is drawn from.
+    seed: u64,
+}
+
+impl<P> ImageStage<P> for ElasticDeformStage
+where
+    P: Pixel + 'static,
+    P::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    fn execute(&self, img: &Image<P>) -> (Image<P>, Tags) {
+        let tags = Tags(HashSet::from_iter([Label::Elastic]));
+        let (width, height) = img.dimensions();
+        if width == 0 || height == 0 {
+            return (img.clone(), tags);
+        }
+
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let mut field = || {
+            let noise: Image<Luma<f32>> =
+                Image::from_fn(width, height, |_, _| Luma([rng.gen_range(-1f32..=1.)]));
+            imageops::blur(&noise, self.sigma)
+        };
+        let (dx, dy) = (field(), field());
+
+        let deformed = Image::from_fn(width, height, |x, y| {
+            sample_bilinear(
+                img,
+                x as f32 + dx.get_pixel(x, y)[0] * self.alpha,
+                y as f32 + dy.get_pixel(x, y)[0] * self.alpha,
+            )
+        });
+
+        (deformed, tags)
+    }
+
+    fn name(&self) -> Cow<str> {
+        format!("elastic_a{:.0}_s{:.0}", self.alpha, self.sigma).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({ "alpha": self.alpha, "sigma": self.sigma, "seed": self.seed }))
+    }
+}
+
+/// A builder that will create `samples` stages, each of which distorts the image radially about its
+/// center as a cheap lens would, with a coefficient `k` between `min_k` and `max_k`. Positive
+/// coefficients yield barrel distortion (bulging outward) and negative ones pincushion distortion
+/// (pinching inward). A coefficient of zero does nothing, so it's never used, and the area pulled
+/// from outside of the image is filled according to `fill`.
+pub struct LensDistortionBuilder<P: Pixel> {
+    /// The number of distortions to generate.
+    pub samples: usize,
+    /// The minimum distortion coefficient.
+    pub min_k: f32,
+    /// The maximum distortion coefficient.
+    pub max_k: f32,
+    /// How the area pulled from outside of the image is filled.
+    pub fill: Fill<P>,
+}
+
+impl<P, R> StageBuilder<P, R> for LensDistortionBuilder<P>
+where
+    P: Pixel + Send + Sync + 'static,
+    P::Subpixel: ValueInto<f32> + Clamp<f32>,
+    R: Rng,
+{
+    fn variations(&self) -> usize {
+        self.samples
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        // Nothing but no-ops could be generated without a non-zero coefficient
+        let no_op = self.min_k == 0. && self.max_k == 0.;
+        !(no_op || tags.0.contains(&Label::LensDistorted) || tags.0.contains(&Label::Fisheye))
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<P> + Send + Sync>> {
+        (0..self.samples)
+            .map(|_| {
+                let mut k = 0.;
+                while k == 0. && (self.min_k != 0. || self.max_k != 0.) {
+                    k = rng.gen_range(self.min_k..=self.max_k);
+                }
+                Box::new(LensDistortionStage { k, fill: self.fill })
+                    as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new("lens_{k}", [Label::LensDistorted])]
+    }
+}
+
+/// The actual stage which distorts the image radially, each pixel is resampled from the point whose
+/// distance from the center is scaled by `1 + k * r^2`, where `r` is the pixel's distance from the
+/// center normalized by half the image's diagonal (so non-square images are distorted evenly).
+pub struct LensDistortionStage<P: Pixel> {
+    /// The distortion coefficient.
+    k: f32,
+    /// How the area pulled from outside of the image is filled.
+    fill: Fill<P>,
+}
+
+impl<P> ImageStage<P> for LensDistortionStage<P>
+where
+    P: Pixel + Send + Sync + 'static,
+    P::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    fn execute(&self, img: &Image<P>) -> (Image<P>, Tags) {
+        let tags = Tags(HashSet::from_iter([Label::LensDistorted]));
+        let (width, height) = img.dimensions();
+        if width == 0 || height == 0 {
+            return (img.clone(), tags);
+        }
+
+        let (cx, cy) = ((width - 1) as f32 / 2., (height - 1) as f32 / 2.);
+        let norm = cx.hypot(cy).max(1.);
+        let distorted = Image::from_fn(width, height, |x, y| {
+            let (dx, dy) = ((x as f32 - cx) / norm, (y as f32 - cy) / norm);
+            let scale = 1. + self.k * (dx * dx + dy * dy);
+            self.fill
+                .sample(img, cx + dx * scale * norm, cy + dy * scale * norm)
+        });
+
+        (distorted, tags)
+    }
+
+    fn name(&self) -> Cow<str> {
+        format!("lens_{:+.2}", self.k).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({ "k": self.k }))
+    }
+}
+
+/// A builder that will create `samples` stages, each of which projects the image through an
+/// equidistant fisheye lens centered on the image, with a strength between `min_strength` and
+/// `max_strength`. The strength is the fraction of a 180 degree field of view the lens covers, so
+/// strengths close to `1` are extremely distorted. The area outside of the lens' circle is filled
+/// according to `fill`.
+///
+/// This is far more aggressive than [`LensDistortionBuilder`], so the two are never combined.
+///
+/// [`LensDistortionBuilder`]: about:blank
+pub struct FisheyeBuilder<P: Pixel> {
+    /// The number of projections to generate.
+    pub samples: usize,
+    /// The minimum strength, between `0` and `1`.
+    pub min_strength: f32,
+    /// The maximum strength, between `0` and `1`.
+    pub max_strength: f32,
+    /// How the area outside of the lens' circle is filled.
+    pub fill: Fill<P>,
+}
+
+impl<P, R> StageBuilder<P, R> for FisheyeBuilder<P>
+where
+    P: Pixel + Send + Sync + 'static,
+    P::Subpixel: ValueInto<f32> + Clamp<f32>,
+    R: Rng,
+{
+    fn variations(&self) -> usize {
+        self.samples
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !(tags.0.contains(&Label::Fisheye) || tags.0.contains(&Label::LensDistorted))
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<P> + Send + Sync>> {
+        (0..self.samples)
+            .map(|_| {
+                Box::new(FisheyeStage {
+                    strength: rng.gen_range(self.min_strength..=self.max_strength),
+                    fill: self.fill,
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new("fisheye_{strength}", [Label::Fisheye])]
+    }
+}
+
+/// The actual stage which projects the image through an equidistant fisheye lens, whose circle
+/// spans the image's smaller dimension. Each pixel at a distance `r` from the center (normalized
+/// to `1` at the circle's edge) is resampled from the point of the image, seen as a rectilinear
+/// projection, at an angle of `r` times half of the lens' field of view. The area outside of the
+/// circle is filled according to `fill`.
+pub struct FisheyeStage<P: Pixel> {
+    /// The fraction of a 180 degree field of view the lens covers.
+    strength: f32,
+    /// How the area outside of the lens' circle is filled.
+    fill: Fill<P>,
+}
+
+impl<P> ImageStage<P> for FisheyeStage<P>
+where
+    P: Pixel + Send + Sync + 'static,
+    P::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    fn execute(&self, img: &Image<P>) -> (Image<P>, Tags) {
+        let tags = Tags(HashSet::from_iter([Label::Fisheye]));
+        let (width, height) = img.dimensions();
+        if width == 0 || height == 0 {
+            return (img.clone(), tags);
+        }
+
+        // Just short of a full 180 degrees, where the rectilinear projection goes to infinity
+        let half_fov = self.strength.max(f32::EPSILON).min(0.99) * std::f32::consts::FRAC_PI_2;
+        let (cx, cy) = ((width - 1) as f32 / 2., (height - 1) as f32 / 2.);
+        let radius = cx.min(cy).max(1.);
+        let projected = Image::from_fn(width, height, |x, y| {
+            let (dx, dy) = ((x as f32 - cx) / radius, (y as f32 - cy) / radius);
+            let r = dx.hypot(dy);
+            if let (true, Fill::Color(color)) = (r > 1., self.fill) {
+                return color;
+            }
+
+            let scale = if r > 0. {
+                (r * half_fov)
+                    .min(std::f32::consts::FRAC_PI_2 * 0.999)
+                    .tan()
+                    / half_fov.tan()
+                    / r
+            } else {
+                0.
+            };
+            self.fill
+                .sample(img, cx + dx * scale * radius, cy + dy * scale * radius)
+        });
+
+        (projected, tags)
+    }
+
+    fn name(&self) -> Cow<str> {
+        format!("fisheye_{:.2}", self.strength).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({ "strength": self.strength }))
+    }
+}
+
+/// A builder that will create `samples` stages, each of which zooms the image about its center by a
+/// factor between `min_factor` and `max_factor`, keeping its dimensions. Factors above `1` zoom in,
+/// cropping into the center, while factors below `1` zoom out, leaving a border filled according to
+/// `fill`. A factor of exactly `1` does nothing, so it's never used.
+pub struct ZoomBuilder<P: Pixel> {
+    /// The number of zooms to generate.
+    pub samples: usize,
+    /// The minimum zoom factor.
+    pub min_factor: f32,
+    /// The maximum zoom factor.
+    pub max_factor: f32,
+    /// How the border left when zooming out is filled.
+    pub fill: Fill<P>,
+}
+
+impl<P, R> StageBuilder<P, R> for ZoomBuilder<P>
+where
+    P: Pixel + Send + Sync + 'static,
+    P::Subpixel: ValueInto<f32> + Clamp<f32>,
+    R: Rng,
+{
+    fn variations(&self) -> usize {
+        self.samples
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        // Nothing but no-ops could be generated without a factor other than one
+        let no_op = self.min_factor == 1. && self.max_factor == 1.;
+        !(no_op || tags.0.contains(&Label::Zoomed))
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<P> + Send + Sync>> {
+        let (min, max) = (self.min_factor.max(0.01), self.max_factor.max(0.01));
+        (0..self.samples)
+            .map(|_| {
+                let mut factor = 1.;
+                while factor == 1. && (min != 1. || max != 1.) {
+                    factor = rng.gen_range(min..=max);
+                }
+                Box::new(ZoomStage {
+                    factor,
+                    fill: self.fill,
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new("zoom_{factor}", [Label::Zoomed])]
+    }
+}
+
+/// The actual stage which zooms the image about its center by `factor`, resampling it with bilinear
+/// interpolation and filling the border left when zooming out according to `fill`.
+pub struct ZoomStage<P: Pixel> {
+    /// The zoom factor, above `1` to zoom in and below to zoom out.
+    factor: f32,
+    /// How the border left when zooming out is filled.
+    fill: Fill<P>,
+}
+
+impl<P> ImageStage<P> for ZoomStage<P>
+where
+    P: Pixel + Send + Sync + 'static,
+    P::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    fn execute(&self, img: &Image<P>) -> (Image<P>, Tags) {
+        let tags = Tags(HashSet::from_iter([Label::Zoomed]));
+        let (width, height) = img.dimensions();
+        if width == 0 || height == 0 {
+            return (img.clone(), tags);
+        }
+
+        let (cx, cy) = ((width - 1) as f32 / 2., (height - 1) as f32 / 2.);
+        let zoomed = Image::from_fn(width, height, |x, y| {
+            self.fill.sample(
+                img,
+                cx + (x as f32 - cx) / self.factor,
+                cy + (y as f32 - cy) / self.factor,
+            )
+        });
+
+        (zoomed, tags)
+    }
+
+    fn name(&self) -> Cow<str> {
+        format!("zoom_{:.2}", self.factor).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({ "factor": self.factor }))
+    }
+}
+
+/// Where on the image a watermark is placed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WatermarkPosition {
+    /// The top-left corner.
+    TopLeft,
+    /// The top-right corner.
+    TopRight,
+    /// The bottom-left corner.
+    BottomLeft,
+    /// The bottom-right corner.
+    BottomRight,
+    /// The center of the image.
+    Center,
+}
+
+impl WatermarkPosition {
+    /// The short code used for this position in stage names.
+    pub fn code(self) -> &'static str {
+        match self {
+            WatermarkPosition::TopLeft => "tl",
+            WatermarkPosition::TopRight => "tr",
+            WatermarkPosition::BottomLeft => "bl",
+            WatermarkPosition::BottomRight => "br",
+            WatermarkPosition::Center => "c",
+        }
+    }
+
+    /// The top-left corner of an `overlay` sized watermark placed at this position on an image
+    /// `bottom` in size, inset from its edges by `margin` pixels.
+    fn origin(self, bottom: (u32, u32), overlay: (u32, u32), margin: u32) -> (u32, u32) {
+        let far = |outer: u32, inner: u32| outer.saturating_sub(inner + margin);
+        let near = |outer: u32, inner: u32| margin.min(outer.saturating_sub(inner));
+        let (w, h) = (bottom, overlay);
+        match self {
+            WatermarkPosition::TopLeft => (near(w.0, h.0), near(w.1, h.1)),
+            WatermarkPosition::TopRight => (far(w.0, h.0), near(w.1, h.1)),
+            WatermarkPosition::BottomLeft => (near(w.0, h.0), far(w.1, h.1)),
+            WatermarkPosition::BottomRight => (far(w.0, h.0), far(w.1, h.1)),
+            WatermarkPosition::Center => (w.0.saturating_sub(h.0) / 2, w.1.saturating_sub(h.1) / 2),
+        }
+    }
+}
+
+/// A builder that will create `samples` stages, each of which composites a watermark onto the image
+/// at one of `positions`, scaled by a random factor from `scale_range` and with a random opacity
+/// from `opacity_range`. The watermark is loaded once when the builder is created, and shrunk to
+/// fit any image it would otherwise overflow.
+pub struct WatermarkBuilder {
+    /// The number of watermarks to generate.
+    samples: usize,
+    /// The watermark, shared between every stage.
+    overlay: Arc<Image<Rgba<u8>>>,
+    /// The positions to choose between.
+    positions: Vec<WatermarkPosition>,
+    /// The range of opacities to choose between, from `0` to `1`.
+    opacity_range: RangeInclusive<f32>,
+    /// The range of factors to scale the watermark by.
+    scale_range: RangeInclusive<f32>,
+}
+
+impl WatermarkBuilder {
+    /// Creates a builder compositing the image at `overlay_path` onto `samples` variations, loading
+    /// it up front so a missing or corrupt watermark is an error here rather than while processing.
+    /// Likewise, an `opacity_range` outside `0` to `1` or a `scale_range` which isn't positive (or
+    /// either range being reversed) is an error.
+    ///
+    /// # Panics
+    ///
+    /// If `positions` is empty while `samples` isn't zero.
+    pub fn new<Q: AsRef<Path>>(
+        samples: usize,
+        overlay_path: Q,
+        positions: Vec<WatermarkPosition>,
+        opacity_range: RangeInclusive<f32>,
+        scale_range: RangeInclusive<f32>,
+    ) -> ImageResult<Self> {
+        assert!(
+            samples == 0 || !positions.is_empty(),
+            "a watermark needs at least one position to be placed at"
+        );
+        let invalid = |message: String| {
+            ImageError::Parameter(ParameterError::from_kind(ParameterErrorKind::Generic(
+                message,
+            )))
+        };
+        let (&min_opacity, &max_opacity) = (opacity_range.start(), opacity_range.end());
+        if !(0. <= min_opacity && min_opacity <= max_opacity && max_opacity <= 1.) {
+            return Err(invalid(format!(
+                "the watermark opacity range {}..={} isn't within 0 to 1",
+                min_opacity, max_opacity
+            )));
+        }
+        let (&min_scale, &max_scale) = (scale_range.start(), scale_range.end());
+        if !(0. < min_scale && min_scale <= max_scale) {
+            return Err(invalid(format!(
+                "the watermark scale range {}..={} isn't positive",
+                min_scale, max_scale
+            )));
+        }
+        let overlay = image::open(overlay_path)?.to_rgba8();
+
+        Ok(Self {
+            samples,
+            overlay: Arc::new(overlay),
+            positions,
+            opacity_range,
+            scale_range,
+        })
+    }
+}
+
+impl<R: Rng> StageBuilder<Rgba<u8>, R> for WatermarkBuilder {
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !tags.0.contains(&Label::Watermarked)
+    }
+
+    fn variations(&self) -> usize {
+        self.samples
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<Rgba<u8>> + Send + Sync>> {
+        (0..self.samples)
+            .map(|_| {
+                Box::new(WatermarkStage {
+                    overlay: Arc::clone(&self.overlay),
+                    position: *self.positions.choose(rng).unwrap(),
+                    opacity: rng.gen_range(self.opacity_range.clone()),
+                    scale: rng.gen_range(self.scale_range.clone()),
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new(
+            "wm_{position}_{opacity}",
+            [Label::Watermarked],
+        )]
+    }
+}
+
+/// The actual stage that composites `overlay`, scaled by `scale` and faded to `opacity`, onto the
+/// image at `position`. Watermarks which would overflow the image are shrunk to fit it, keeping
+/// their aspect ratio, while images too small to fit a pixel of the watermark are skipped.
+pub struct WatermarkStage {
+    /// The watermark.
+    overlay: Arc<Image<Rgba<u8>>>,
+    /// Where to place the watermark.
+    position: WatermarkPosition,
+    /// The opacity of the watermark, from `0` to `1`.
+    opacity: f32,
+    /// The factor to scale the watermark by.
+    scale: f32,
+}
+
+impl WatermarkStage {
+    /// The dimensions of the scaled watermark on a `width` by `height` image, or `None` if it
+    /// would vanish.
+    fn scaled_dimensions(&self, width: u32, height: u32) -> Option<(u32, u32)> {
+        let (ow, oh) = self.overlay.dimensions();
+        // Never let the watermark spill over the image's edges
+        let fit = (width as f32 / ow as f32).min(height as f32 / oh as f32);
+        let scale = self.scale.min(fit);
+        let (sw, sh) = ((ow as f32 * scale) as u32, (oh as f32 * scale) as u32);
+        if sw == 0 || sh == 0 {
+            None
+        } else {
+            Some((sw, sh))
+        }
+    }
+}
+
+impl ImageStage<Rgba<u8>> for WatermarkStage {
+    fn execute(&self, img: &Image<Rgba<u8>>) -> (Image<Rgba<u8>>, Tags) {
+        let (width, height) = img.dimensions();
+        let (sw, sh) = match self.scaled_dimensions(width, height) {
+            Some(dimensions) => dimensions,
+            None => return (img.clone(), Tags::default()),
+        };
+
+        let mut overlay = imageops::resize(&*self.overlay, sw, sh, FilterType::Triangle);
+        for pixel in overlay.pixels_mut() {
+            pixel[3] = (pixel[3] as f32 * self.opacity).round() as u8;
+        }
+        let margin = width.min(height) / 50;
+        let (x, y) = self.position.origin((width, height), (sw, sh), margin);
+
+        let mut out = img.clone();
+        imageops::overlay(&mut out, &overlay, x, y);
+
+        (out, Tags(HashSet::from_iter([Label::Watermarked])))
+    }
+
+    fn execute_many(&self, img: &Image<Rgba<u8>>) -> Vec<(Image<Rgba<u8>>, Tags)> {
+        match self.scaled_dimensions(img.width(), img.height()) {
+            Some(_) => vec![self.execute(img)],
+            None => vec![],
+        }
+    }
+
+    fn name(&self) -> Cow<str> {
+        format!("wm_{}_{:.1}", self.position.code(), self.opacity).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({
+            "position": self.position.code(),
+            "opacity": self.opacity,
+            "scale": self.scale,
+        }))
+    }
+}
+
+/// Measures the width and height in pixels of `text` drawn in `font` at `scale`.
+fn text_extent(font: &Font, scale: Scale, text: &str) -> (u32, u32) {
+    let metrics = font.v_metrics(scale);
+    let width = font
+        .layout(text, scale, point(0., 0.))
+        .filter_map(|glyph| glyph.pixel_bounding_box())
+        .map(|bounds| bounds.max.x)
+        .max()
+        .unwrap_or(0);
+    (
+        width.max(0) as u32,
+        (metrics.ascent - metrics.descent).ceil() as u32,
+    )
+}
+
+/// A builder that will create `samples` stages, each of which draws a random string from `texts` in
+/// `color` onto the image, at a random position and a random size from `size_range` (the height of
+/// the text in pixels). The font is loaded once when the builder is created.
+pub struct TextOverlayBuilder<P: Pixel> {
+    /// The number of overlays to generate.
+    samples: usize,
+    /// The strings to choose between.
+    texts: Vec<String>,
+    /// The font to draw with, shared between every stage.
+    font: Arc<Font<'static>>,
+    /// The range of text heights to choose between, in pixels.
+    size_range: RangeInclusive<f32>,
+    /// The color of the text.
+    color: P,
+}
+
+impl<P: Pixel> TextOverlayBuilder<P> {
+    /// Creates a builder drawing `texts` onto `samples` variations with the TrueType or OpenType
+    /// font at `font_path`, loading it up front so a missing or corrupt font is an error here
+    /// rather than while processing.
+    ///
+    /// # Panics
+    ///
+    /// If `texts` is empty while `samples` isn't zero.
+    pub fn new<Q: AsRef<Path>>(
+        samples: usize,
+        texts: Vec<String>,
+        font_path: Q,
+        size_range: RangeInclusive<f32>,
+        color: P,
+    ) -> io::Result<Self> {
+        assert!(
+            samples == 0 || !texts.is_empty(),
+            "a text overlay needs at least one string to draw"
+        );
+        let font = Font::try_from_vec(fs::read(font_path)?).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a TrueType or OpenType font",
+            )
+        })?;
+
+        Ok(Self {
+            samples,
+            texts,
+            font: Arc::new(font),
+            size_range,
+            color,
+        })
+    }
+}
+
+impl<P, R> StageBuilder<P, R> for TextOverlayBuilder<P>
+where
+    P: Pixel + Send + Sync + 'static,
+    P::Subpixel: ValueInto<f32> + Clamp<f32>,
+    R: Rng,
+{
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !tags.0.contains(&Label::TextOverlay)
+    }
+
+    fn variations(&self) -> usize {
+        self.samples
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<P> + Send + Sync>> {
+        let size = self.size_range.start().max(1.)..=self.size_range.end().max(1.);
+        (0..self.samples)
+            .map(|_| {
+                let index = rng.gen_range(0..self.texts.len());
+                Box::new(TextOverlayStage {
+                    index,
+                    text: self.texts[index].clone(),
+                    font: Arc::clone(&self.font),
+                    size: rng.gen_range(size.clone()),
+                    position: (rng.gen(), rng.gen()),
+                    color: self.color,
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new(
+            "text_{index}_{size}px",
+            [Label::TextOverlay],
+        )]
+    }
+}
+
+/// The actual stage that draws `text` in `font` at `size` pixels high. Its `position` is relative to
+/// the space left around the text, so it always lies within the image, and text too large for the
+/// image is shrunk to fit it rather than being clipped.
+pub struct TextOverlayStage<P: Pixel> {
+    /// The index of the text in the builder's strings, used in names instead of the text itself.
+    index: usize,
+    /// The text to draw.
+    text: String,
+    /// The font to draw with.
+    font: Arc<Font<'static>>,
+    /// The height of the text, in pixels.
+    size: f32,
+    /// The position of the text, from `0` to `1` along either axis of the free space.
+    position: (f32, f32),
+    /// The color of the text.
+    color: P,
+}
+
+impl<P> ImageStage<P> for TextOverlayStage<P>
+where
+    P: Pixel + Send + Sync + 'static,
+    P::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    fn execute(&self, img: &Image<P>) -> (Image<P>, Tags) {
+        let (width, height) = img.dimensions();
+        let mut scale = Scale::uniform(self.size);
+        let (text_width, text_height) = text_extent(&self.font, scale, &self.text);
+        if text_width == 0 || text_height == 0 || width == 0 || height == 0 {
+            return (img.clone(), Tags::default());
+        }
+
+        let fit = (width as f32 / text_width as f32).min(height as f32 / text_height as f32);
+        if fit < 1. {
+            scale = Scale::uniform(self.size * fit);
+        }
+        let (text_width, text_height) = text_extent(&self.font, scale, &self.text);
+        let x = (width.saturating_sub(text_width) as f32 * self.position.0) as u32;
+        let y = (height.saturating_sub(text_height) as f32 * self.position.1) as u32;
+
+        let mut out = img.clone();
+        draw_text_mut(&mut out, self.color, x, y, scale, &self.font, &self.text);
+
+        (out, Tags(HashSet::from_iter([Label::TextOverlay])))
+    }
+
+    fn name(&self) -> Cow<str> {
+        format!("text_{}_{:.0}px", self.index, self.size).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({
+            "index": self.index,
+            "text": self.text,
+            "size": self.size,
+            "position": self.position,
+        }))
+    }
+}
+
+/// A builder that will create `samples` stages, each of which frames the image with a solid border
+/// in a random one of `colors` (each paired with the name used for it in stage names), between
+/// `min_thickness_frac` and `max_thickness_frac` of the image's shorter side thick.
+///
+/// By default the border is drawn over the edges of the image, but with `grow` set the canvas is
+/// enlarged to fit it instead, so **images come out larger than they went in** by twice the
+/// border's thickness along either axis. Bear that in mind when combining this with stages which
+/// expect particular dimensions.
+pub struct BorderBuilder<P: Pixel> {
+    /// The number of borders to generate.
+    pub samples: usize,
+    /// The minimum thickness, relative to the image's shorter side.
+    pub min_thickness_frac: f32,
+    /// The maximum thickness, relative to the image's shorter side.
+    pub max_thickness_frac: f32,
+    /// The colors to choose between, each with a name for stage names.
+    pub colors: Vec<(String, P)>,
+    /// Whether to grow the canvas to fit the border rather than drawing over the image.
+    pub grow: bool,
+}
+
+impl<P: Pixel + Send + Sync + 'static, R: Rng> StageBuilder<P, R> for BorderBuilder<P> {
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !(self.colors.is_empty() || tags.0.contains(&Label::Bordered))
+    }
+
+    fn variations(&self) -> usize {
+        self.samples
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<P> + Send + Sync>> {
+        let min = self.min_thickness_frac.max(0.);
+        let max = self.max_thickness_frac.max(min);
+        (0..self.samples)
+            .map(|_| {
+                let (color_name, color) = self.colors.choose(rng).unwrap().clone();
+                Box::new(BorderStage {
+                    thickness_frac: rng.gen_range(min..=max),
+                    color,
+                    color_name,
+                    grow: self.grow,
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        let name = if self.grow {
+            "border_{thickness}px_{color}_grow"
+        } else {
+            "border_{thickness}px_{color}"
+        };
+        vec![StageDescriptor::new(name, [Label::Bordered])]
+    }
+}
+
+/// The actual stage that frames the image with a `color` border `thickness_frac` of its shorter side
+/// thick (but always at least a pixel), either over its edges or around them when `grow` is set.
+/// Outputs are named by the thickness in pixels on that image, e.g. `border_8px_black`.
+pub struct BorderStage<P: Pixel> {
+    /// The thickness, relative to the image's shorter side.
+    thickness_frac: f32,
+    /// The color of the border.
+    color: P,
+    /// The name of the color, for the stage's name.
+    color_name: String,
+    /// Whether to grow the canvas to fit the border.
+    grow: bool,
+}
+
+impl<P: Pixel> BorderStage<P> {
+    /// The thickness of the border around `img`, in pixels.
+    fn thickness(&self, img: &Image<P>) -> u32 {
+        ((img.width().min(img.height()) as f32 * self.thickness_frac).round() as u32).max(1)
+    }
+}
+
+impl<P: Pixel + 'static> ImageStage<P> for BorderStage<P> {
+    fn execute(&self, img: &Image<P>) -> (Image<P>, Tags) {
+        let (width, height) = img.dimensions();
+        let thickness = self.thickness(img);
+
+        let out = if self.grow {
+            let (grown_width, grown_height) = (width + 2 * thickness, height + 2 * thickness);
+            let mut out = Image::from_pixel(grown_width, grown_height, self.color);
+            out.copy_from(img, thickness, thickness).unwrap();
+            out
+        } else {
+            Image::from_fn(width, height, |x, y| {
+                let inside = (thickness..width.saturating_sub(thickness)).contains(&x)
+                    && (thickness..height.saturating_sub(thickness)).contains(&y);
+                if inside {
+                    *img.get_pixel(x, y)
+                } else {
+                    self.color
+                }
+            })
+        };
+
+        (out, Tags(HashSet::from_iter([Label::Bordered])))
+    }
+
+    fn name(&self) -> Cow<str> {
+        let suffix = if self.grow { "_grow" } else { "" };
+        format!(
+            "border_{:.1}pct_{}{}",
+            self.thickness_frac * 100.,
+            self.color_name,
+            suffix
+        )
+        .into()
+    }
+
+    fn name_for(&self, img: &Image<P>) -> Cow<str> {
+        let suffix = if self.grow { "_grow" } else { "" };
+        format!(
+            "border_{}px_{}{}",
+            self.thickness(img),
+            self.color_name,
+            suffix
+        )
+        .into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({
+            "thickness_frac": self.thickness_frac,
+            "color": self.color_name,
+            "grow": self.grow,
+        }))
+    }
+}
+
+/// A builder that will create `samples` stages, each of which jitters the brightness, contrast,
+/// saturation and hue of the image at once, in a random order, like torchvision's `ColorJitter`.
+/// The brightness, contrast and saturation are scaled by factors from `1 - x` to `1 + x` for their
+/// respective fields (floored at `0`), while the hue is shifted by between `-hue` and `hue` turns
+/// of the color wheel (at most half a turn). A field of `0` leaves that property untouched.
+///
+/// Since it adjusts the same properties, this won't run on images which have already been through
+/// [`LuminosityBuilder`], [`SaturationBuilder`] or [`HsvJitterBuilder`], or vice versa.
+///
+/// [`LuminosityBuilder`]: about:blank
+/// [`SaturationBuilder`]: about:blank
+/// [`HsvJitterBuilder`]: about:blank
+pub struct ColorJitterBuilder {
+    /// The number of jittered variants to create.
+    pub samples: usize,
+    /// How far the brightness factor may stray from `1`.
+    pub brightness: f32,
+    /// How far the contrast factor may stray from `1`.
+    pub contrast: f32,
+    /// How far the saturation factor may stray from `1`.
+    pub saturation: f32,
+    /// How far the hue may be shifted, in turns (up to `0.5`).
+    pub hue: f32,
+}
+
+impl<R: Rng> StageBuilder<Rgba<u8>, R> for ColorJitterBuilder {
+    fn variations(&self) -> usize {
+        self.samples
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        ![
+            Label::Bright,
+            Label::Dark,
+            Label::Contrast,
+            Label::SaturationJittered,
+            Label::HueShifted,
+            Label::HsvJittered,
+        ]
+        .iter()
+        .any(|label| tags.0.contains(label))
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<Rgba<u8>> + Send + Sync>> {
+        let factor = |spread: f32, rng: &mut R| {
+            let spread = spread.max(0.);
+            rng.gen_range((1. - spread).max(0.)..=1. + spread)
+        };
+        let hue = self.hue.max(0.).min(0.5);
+
+        (0..self.samples)
+            .map(|_| {
+                let mut order = [
+                    ColorOp::Brightness,
+                    ColorOp::Contrast,
+                    ColorOp::Saturation,
+                    ColorOp::Hue,
+                ];
+                order.shuffle(rng);
+                Box::new(ColorJitterStage {
+                    brightness: factor(self.brightness, rng),
+                    contrast: factor(self.contrast, rng),
+                    saturation: factor(self.saturation, rng),
+                    hue: rng.gen_range(-hue..=hue),
+                    order,
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        let mut labels = vec![];
+        if self.brightness > 0. {
+            labels.extend([Label::Bright, Label::Dark].iter().cloned());
+        }
+        if self.contrast > 0. {
+            labels.push(Label::Contrast);
+        }
+        if self.saturation > 0. {
+            labels.push(Label::SaturationJittered);
+        }
+        if self.hue > 0. {
+            labels.push(Label::HueShifted);
+        }
+        vec![StageDescriptor::new("cj_{hash}", labels)]
+    }
+}
+
+/// One of the adjustments made by a [`ColorJitterStage`].
+///
+/// [`ColorJitterStage`]: about:blank
+#[derive(Clone, Copy, Debug)]
+enum ColorOp {
+    /// Scaling the brightness.
+    Brightness,
+    /// Scaling the contrast about the mean luma.
+    Contrast,
+    /// Scaling the saturation.
+    Saturation,
+    /// Shifting the hue.
+    Hue,
+}
+
+/// The actual stage which scales the image's brightness, contrast and saturation by `brightness`,
+/// `contrast` and `saturation`, and shifts its hue by `hue` turns, in the given `order`. Contrast is
+/// scaled about the image's mean luma, as in torchvision. Alpha is left alone.
+pub struct ColorJitterStage {
+    /// The factor to scale the brightness by.
+    brightness: f32,
+    /// The factor to scale the contrast by.
+    contrast: f32,
+    /// The factor to scale the saturation by.
+    saturation: f32,
+    /// The hue shift, in turns.
+    hue: f32,
+    /// The order to make the adjustments in.
+    order: [ColorOp; 4],
+}
+
+impl ImageStage<Rgba<u8>> for ColorJitterStage {
+    fn execute(&self, img: &Image<Rgba<u8>>) -> (Image<Rgba<u8>>, Tags) {
+        let mut img = img.clone();
+        let luma = |p: &Rgba<u8>| 0.299 * p[0] as f32 + 0.587 * p[1] as f32 + 0.114 * p[2] as f32;
+
+        for op in &self.order {
+            match op {
+                ColorOp::Brightness => {
+                    for pixel in img.pixels_mut() {
+                        for c in 0..3 {
+                            pixel[c] = (pixel[c] as f32 * self.brightness).round().min(255.) as u8;
+                        }
+                    }
+                }
+                ColorOp::Contrast => {
+                    let count = (img.width() as f32 * img.height() as f32).max(1.);
+                    let mean = img.pixels().map(luma).sum::<f32>() / count;
+                    for pixel in img.pixels_mut() {
+                        for c in 0..3 {
+                            let v = mean + (pixel[c] as f32 - mean) * self.contrast;
+                            pixel[c] = v.round().max(0.).min(255.) as u8;
+                        }
+                    }
+                }
+                ColorOp::Saturation => {
+                    for pixel in img.pixels_mut() {
+                        let [r, g, b] =
+                            scale_saturation([pixel[0], pixel[1], pixel[2]], self.saturation);
+                        *pixel = Rgba([r, g, b, pixel[3]]);
+                    }
+                }
+                ColorOp::Hue => {
+                    for pixel in img.pixels_mut() {
+                        let [h, s, v] = rgb_to_hsv([pixel[0], pixel[1], pixel[2]]);
+                        let [r, g, b] = hsv_to_rgb([h + self.hue * 360., s, v]);
+                        *pixel = Rgba([r, g, b, pixel[3]]);
+                    }
+                }
+            }
+        }
+
+        let mut tags = HashSet::new();
+        if self.brightness > 1. {
+            tags.insert(Label::Bright);
+        } else if self.brightness < 1. {
+            tags.insert(Label::Dark);
+        }
+        if self.contrast != 1. {
+            tags.insert(Label::Contrast);
+        }
+        if self.saturation != 1. {
+            tags.insert(Label::SaturationJittered);
+        }
+        if self.hue != 0. {
+            tags.insert(Label::HueShifted);
+        }
+
+        (img, Tags(tags))
+    }
+
+    fn name(&self) -> Cow<str> {
+        let mut bytes: Vec<u8> = [self.brightness, self.contrast, self.saturation, self.hue]
+            .iter()
+            .flat_map(|p| p.to_le_bytes())
+            .collect();
+        bytes.extend(self.order.iter().map(|&op| op as u8));
+        format!("cj_{:08x}", stable_hash(&bytes) as u32).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({
+            "brightness": self.brightness,
+            "contrast": self.contrast,
+            "saturation": self.saturation,
+            "hue": self.hue,
+            "order": self.order.iter().map(|op| format!("{:?}", op)).collect::<Vec<_>>(),
+        }))
+    }
+}
+
+/// A builder that will create `samples` stages, each of which reduces the image to a palette of
+/// between `min_colors` and `max_colors` colors.
+pub struct PaletteQuantizeBuilder {
+    /// The number of quantized variants to create.
+    pub samples: usize,
+    /// The minimum size of the palette.
+    pub min_colors: usize,
+    /// The maximum size of the palette.
+    pub max_colors: usize,
+}
+
+impl<R: Rng> StageBuilder<Rgba<u8>, R> for PaletteQuantizeBuilder {
+    fn variations(&self) -> usize {
+        self.samples
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !tags.0.contains(&Label::Quantized)
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<Rgba<u8>> + Send + Sync>> {
+        let min = self.min_colors.max(1);
+        let max = self.max_colors.max(min);
+        (0..self.samples)
+            .map(|_| {
+                Box::new(PaletteQuantizeStage {
+                    colors: rng.gen_range(min..=max),
+                    seed: rng.gen(),
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new("palette_{colors}", [Label::Quantized])]
+    }
+}
+
+/// The actual stage which quantizes the image to a palette of `colors` colors, found by k-means
+/// clustering a subsample of its pixels, then maps every pixel to the nearest color in the palette.
+/// The subsample and initial clusters are drawn from an RNG seeded by `seed`, so the palette is the
+/// same every time. Alpha is left alone.
+pub struct PaletteQuantizeStage {
+    /// The size of the palette.
+    colors: usize,
+    /// The seed of the RNG the subsample and initial clusters are drawn from.
+    seed: u64,
+}
+
+impl PaletteQuantizeStage {
+    /// The most pixels clustered to find the palette.
+    const MAX_SUBSAMPLE: usize = 4096;
+    /// The number of k-means iterations.
+    const ITERATIONS: usize = 12;
+
+    /// The index of the color in `palette` nearest to `color`.
+    fn nearest(palette: &[[f32; 3]], color: [f32; 3]) -> usize {
+        let distance = |p: &[f32; 3]| {
+            (0..3)
+                .map(|c| (p[c] - color[c]) * (p[c] - color[c]))
+                .sum::<f32>()
+        };
+        (0..palette.len())
+            .min_by(|&a, &b| {
+                distance(&palette[a])
+                    .partial_cmp(&distance(&palette[b]))
+                    .unwrap()
+            })
+            .unwrap()
+    }
+
+    /// Clusters `points` into at most `self.colors` colors with k-means.
+    fn palette(&self, points: &[[f32; 3]], rng: &mut StdRng) -> Vec<[f32; 3]> {
+        let mut palette: Vec<[f32; 3]> = points
+            .choose_multiple(rng, self.colors.min(points.len()))
+            .cloned()
+            .collect();
+
+        for _ in 0..Self::ITERATIONS {
+            let mut sums = vec![([0f32; 3], 0usize); palette.len()];
+            for &point in points {
+                let (sum, count) = &mut sums[Self::nearest(&palette, point)];
+                (0..3).for_each(|c| sum[c] += point[c]);
+                *count += 1;
+            }
+            // Clusters which lost all their points keep their last color
+            for (color, (sum, count)) in palette.iter_mut().zip(sums) {
+                if count > 0 {
+                    *color = [
+                        sum[0] / count as f32,
+                        sum[1] / count as f32,
+                        sum[2] / count as f32,
+                    ];
+                }
+            }
+        }
+
+        palette
+    }
+}
+
+impl ImageStage<Rgba<u8>> for PaletteQuantizeStage {
+    fn execute(&self, img: &Image<Rgba<u8>>) -> (Image<Rgba<u8>>, Tags) {
+        let tags = Tags(HashSet::from_iter([Label::Quantized]));
+        let rgb = |p: &Rgba<u8>| [p[0] as f32, p[1] as f32, p[2] as f32];
+        let pixels: Vec<_> = img.pixels().map(rgb).collect();
+        if pixels.is_empty() {
+            return (img.clone(), tags);
+        }
+
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let subsample: Vec<_> = if pixels.len() > Self::MAX_SUBSAMPLE {
+            pixels
+                .choose_multiple(&mut rng, Self::MAX_SUBSAMPLE)
+                .cloned()
+                .collect()
+        } else {
+            pixels
+        };
+        let palette = self.palette(&subsample, &mut rng);
+
+        let mut img = img.clone();
+        for pixel in img.pixels_mut() {
+            let [r, g, b] = palette[Self::nearest(&palette, rgb(pixel))];
+            *pixel = Rgba([r.round() as u8, g.round() as u8, b.round() as u8, pixel[3]]);
+        }
+
+        (img, tags)
+    }
+
+    fn name(&self) -> Cow<str> {
+        format!("palette_{}", self.colors).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({ "colors": self.colors, "seed": self.seed }))
+    }
+}
+
+/// A builder that will create `samples` stages, each of which reduces every color channel of the
+/// image to a random bit depth between `min_bits` and `max_bits` by masking off its low bits,
+/// banding smooth gradients. By default values are truncated to the bottom of their band, but they
+/// can instead be rounded to its center with [`with_centered`].
+///
+/// [`with_centered`]: about:blank
+pub struct BitCrushBuilder {
+    /// The number of crushed variants to create.
+    samples: usize,
+    /// The minimum bit depth.
+    min_bits: u8,
+    /// The maximum bit depth.
+    max_bits: u8,
+    /// Whether to move values to the center of their band.
+    centered: bool,
+}
+
+impl BitCrushBuilder {
+    /// Creates a builder sampling `samples` bit depths between `min_bits` and `max_bits`.
+    ///
+    /// # Panics
+    ///
+    /// If `min_bits` is below `1`, `max_bits` is above `7` or `min_bits` is above `max_bits`.
+    pub fn new(samples: usize, min_bits: u8, max_bits: u8) -> Self {
+        assert!(
+            min_bits >= 1 && max_bits <= 7,
+            "bit depths must be between 1 and 7, got {} to {}",
+            min_bits,
+            max_bits
+        );
+        assert!(
+            min_bits <= max_bits,
+            "the minimum bit depth {} is above the maximum {}",
+            min_bits,
+            max_bits
+        );
+
+        Self {
+            samples,
+            min_bits,
+            max_bits,
+            centered: false,
+        }
+    }
+
+    /// Whether to move values to the center of their band rather than its bottom, which keeps the
+    /// image's overall brightness.
+    pub fn with_centered(mut self, centered: bool) -> Self {
+        self.centered = centered;
+        self
+    }
+}
+
+impl<P, R> StageBuilder<P, R> for BitCrushBuilder
+where
+    P: Pixel<Subpixel = u8> + 'static,
+    R: Rng,
+{
+    fn variations(&self) -> usize {
+        self.samples
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !tags.0.contains(&Label::Banded)
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<P> + Send + Sync>> {
+        (0..self.samples)
+            .map(|_| {
+                Box::new(BitCrushStage {
+                    bits: rng.gen_range(self.min_bits..=self.max_bits),
+                    centered: self.centered,
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new("bits_{bits}", [Label::Banded])]
+    }
+}
+
+/// The actual stage which keeps only the top `bits` bits of every color channel, moving values to
+/// the center of their band if `centered` is set. Alpha is left alone.
+pub struct BitCrushStage {
+    /// The bit depth, between `1` and `7`.
+    bits: u8,
+    /// Whether to move values to the center of their band.
+    centered: bool,
+}
+
+impl<P: Pixel<Subpixel = u8> + 'static> ImageStage<P> for BitCrushStage {
+    fn execute(&self, img: &Image<P>) -> (Image<P>, Tags) {
+        let mask = !(0xFF >> self.bits);
+        let offset = if self.centered { 0x80 >> self.bits } else { 0 };
+        let colors = color_channels::<P>();
+
+        let mut img = img.clone();
+        for pixel in img.pixels_mut() {
+            for c in pixel.channels_mut().iter_mut().take(colors) {
+                *c = (*c & mask) | offset;
+            }
+        }
+
+        (img, Tags(HashSet::from_iter([Label::Banded])))
+    }
+
+    fn name(&self) -> Cow<str> {
+        format!("bits_{}", self.bits).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({ "bits": self.bits, "centered": self.centered }))
+    }
+}
+
+/// A builder that will create `samples` stages, each of which overlays the image with monochrome film
+/// grain with a random strength between `min_strength` and `max_strength`. At a strength of `1` the
+/// grain has a standard deviation of a tenth of the channel range in the midtones, fading towards
+/// the shadows and highlights. `grain_size` is the standard deviation of the blur applied to the
+/// noise, so larger values give coarser grain, while `0` leaves it a pixel fine.
+pub struct FilmGrainBuilder {
+    /// The number of grainy variants to create.
+    pub samples: usize,
+    /// The minimum strength of the grain.
+    pub min_strength: f32,
+    /// The maximum strength of the grain.
+    pub max_strength: f32,
+    /// The size of the grain, in pixels.
+    pub grain_size: f32,
+}
+
+impl<R: Rng> StageBuilder<Rgba<u8>, R> for FilmGrainBuilder {
+    fn variations(&self) -> usize {
+        self.samples
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !tags.0.contains(&Label::Grain)
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<Rgba<u8>> + Send + Sync>> {
+        let min = self.min_strength.max(0.);
+        let max = self.max_strength.max(min);
+        (0..self.samples)
+            .map(|_| {
+                Box::new(FilmGrainStage {
+                    strength: rng.gen_range(min..=max),
+                    grain_size: self.grain_size.max(0.),
+                    seed: rng.gen(),
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new("grain_{strength}", [Label::Grain])]
+    }
+}
+
+/// The actual stage which overlays the image with grain of `strength`, drawn from an RNG seeded by
+/// `seed` so it's the same every time and blurred by `grain_size`. The same grain is added to the
+/// red, green and blue channels, scaled by how close each pixel's luma is to the midtones. Alpha is
+/// left alone.
+pub struct FilmGrainStage {
+    /// The strength of the grain.
+    strength: f32,
+    /// The standard deviation of the blur applied to the noise.
+    grain_size: f32,
+    /// The seed of the RNG the noise is drawn from.
+    seed: u64,
+}
+
+impl ImageStage<Rgba<u8>> for FilmGrainStage {
+    fn execute(&self, img: &Image<Rgba<u8>>) -> (Image<Rgba<u8>>, Tags) {
+        let tags = Tags(HashSet::from_iter([Label::Grain]));
+        let (width, height) = img.dimensions();
+        if width == 0 || height == 0 {
+            return (img.clone(), tags);
+        }
+
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let mut noise: Image<Luma<f32>> = Image::from_fn(width, height, |_, _| {
+            Luma([rng.sample(rand_distr::StandardNormal)])
+        });
+        if self.grain_size > 0. {
+            noise = imageops::blur(&noise, self.grain_size);
+        }
+        // Blurring shrinks the noise's spread, so bring it back to a unit standard deviation
+        let count = (width as f32) * (height as f32);
+        let mean = noise.pixels().map(|n| n[0]).sum::<f32>() / count;
+        let variance = noise.pixels().map(|n| (n[0] - mean).powi(2)).sum::<f32>() / count;
+        let scale = if variance > 0. {
+            self.strength * 25.5 / variance.sqrt()
+        } else {
+            0.
+        };
+
+        let mut img = img.clone();
+        for (pixel, n) in img.pixels_mut().zip(noise.pixels()) {
+            let luma =
+                (0.299 * pixel[0] as f32 + 0.587 * pixel[1] as f32 + 0.114 * pixel[2] as f32)
+                    / 255.;
+            let grain = (n[0] - mean) * scale * 4. * luma * (1. - luma);
+            for c in 0..3 {
+                pixel[c] = (pixel[c] as f32 + grain).round().max(0.).min(255.) as u8;
+            }
+        }
+
+        (img, tags)
+    }
+
+    fn name(&self) -> Cow<str> {
+        format!("grain_{:.2}", self.strength).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({
+            "strength": self.strength,
+            "grain_size": self.grain_size,
+            "seed": self.seed,
+        }))
+    }
+}
+
+/// A builder that will create `samples` stages, each of which scales the alpha channel of the image by
+/// a random factor between `min_scale` and `max_scale`. Fully opaque images have no transparency to
+/// jitter (or rather, scaling it would only fade the whole image uniformly), so they're skipped.
+pub struct AlphaJitterBuilder {
+    /// The number of jittered variants to create.
+    pub samples: usize,
+    /// The minimum factor to scale alpha by.
+    pub min_scale: f32,
+    /// The maximum factor to scale alpha by.
+    pub max_scale: f32,
+}
+
+impl<R: Rng> StageBuilder<Rgba<u8>, R> for AlphaJitterBuilder {
+    fn variations(&self) -> usize {
+        self.samples
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        // Whether the image is opaque can only be told from its pixels, which the stage checks
+        !(tags.0.contains(&Label::AlphaJittered) || tags.0.contains(&Label::Flattened))
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<Rgba<u8>> + Send + Sync>> {
+        let min = self.min_scale.max(0.);
+        let max = self.max_scale.max(min);
+        (0..self.samples)
+            .map(|_| {
+                Box::new(AlphaJitterStage {
+                    scale: rng.gen_range(min..=max),
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new(
+            "alpha_{scale}",
+            [Label::AlphaJittered],
+        )]
+    }
+}
+
+/// The actual stage which scales the alpha of every pixel that isn't fully transparent by `scale`,
+/// clamping it to the channel's range. It yields no output at all for fully opaque images.
+pub struct AlphaJitterStage {
+    /// The factor to scale alpha by.
+    scale: f32,
+}
+
+impl ImageStage<Rgba<u8>> for AlphaJitterStage {
+    fn execute(&self, img: &Image<Rgba<u8>>) -> (Image<Rgba<u8>>, Tags) {
+        let mut img = img.clone();
+        for pixel in img.pixels_mut().filter(|p| p[3] > 0) {
+            pixel[3] = (pixel[3] as f32 * self.scale).round().max(0.).min(255.) as u8;
+        }
+
+        (img, Tags(HashSet::from_iter([Label::AlphaJittered])))
+    }
+
+    fn execute_many(&self, img: &Image<Rgba<u8>>) -> Vec<(Image<Rgba<u8>>, Tags)> {
+        if img.pixels().all(|p| p[3] == u8::MAX) {
+            vec![]
+        } else {
+            vec![self.execute(img)]
+        }
+    }
+
+    fn name(&self) -> Cow<str> {
+        format!("alpha_{:.2}", self.scale).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({ "scale": self.scale }))
+    }
+}
+
+/// A builder that will create `samples` stages, each of which splits the image into an NxN grid of
+/// patches, with N sampled from `grid_range` (at least `2`), and shuffles them.
+pub struct PatchShuffleBuilder {
+    /// The number of shuffled variants to create.
+    pub samples: usize,
+    /// The range of grid sizes to choose between.
+    pub grid_range: RangeInclusive<u32>,
+}
+
+impl<P: Pixel + 'static, R: Rng> StageBuilder<P, R> for PatchShuffleBuilder {
+    fn variations(&self) -> usize {
+        self.samples
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !tags.0.contains(&Label::PatchShuffled)
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<P> + Send + Sync>> {
+        let min = (*self.grid_range.start()).max(2);
+        let max = (*self.grid_range.end()).max(min);
+        (0..self.samples)
+            .map(|_| {
+                Box::new(PatchShuffleStage {
+                    grid: rng.gen_range(min..=max),
+                    seed: rng.gen(),
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new(
+            "pshuf_{grid}x{grid}",
+            [Label::PatchShuffled],
+        )]
+    }
+}
+
+/// The actual stage which splits the image into a `grid` by `grid` grid of patches and shuffles
+/// them with permutations drawn from an RNG seeded by `seed`, so it's the same every time. When the
+/// image's dimensions aren't divisible by `grid` the last row and column of patches are slightly
+/// larger, so every patch keeps its size: the rows and columns are shuffled as a whole, then patches
+/// are shuffled among those of the same size. Images with fewer pixels than `grid` along either side
+/// use as fine a grid as they can, and are skipped if that's less than 2 by 2.
+pub struct PatchShuffleStage {
+    /// The number of patches along either side.
+    grid: u32,
+    /// The seed of the RNG the permutation is drawn from.
+    seed: u64,
+}
+
+impl PatchShuffleStage {
+    /// The grid actually used for a `width` by `height` image, or `None` if it's too small to
+    /// split.
+    fn grid_for(&self, width: u32, height: u32) -> Option<u32> {
+        let grid = self.grid.min(width).min(height);
+        if grid < 2 {
+            None
+        } else {
+            Some(grid)
+        }
+    }
+}
+
+impl<P: Pixel + 'static> ImageStage<P> for PatchShuffleStage {
+    fn execute(&self, img: &Image<P>) -> (Image<P>, Tags) {
+        let (width, height) = img.dimensions();
+        let grid = match self.grid_for(width, height) {
+            Some(grid) => grid,
+            None => return (img.clone(), Tags::default()),
+        };
+
+        // The start and length of each column or row of patches
+        let spans = |len: u32| -> Vec<(u32, u32)> {
+            (0..grid)
+                .map(|i| {
+                    let start = i * (len / grid);
+                    let end = if i == grid - 1 {
+                        len
+                    } else {
+                        start + len / grid
+                    };
+                    (start, end - start)
+                })
+                .collect()
+        };
+        let (cols, rows) = (spans(width), spans(height));
+
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let mut col_order: Vec<usize> = (0..cols.len()).collect();
+        col_order.shuffle(&mut rng);
+        let mut row_order: Vec<usize> = (0..rows.len()).collect();
+        row_order.shuffle(&mut rng);
+        // Where each column or row starts once laid out in its new order
+        let offsets = |spans: &[(u32, u32)], order: &[usize]| -> Vec<u32> {
+            order
+                .iter()
+                .scan(0, |offset, &i| {
+                    let start = *offset;
+                    *offset += spans[i].1;
+                    Some(start)
+                })
+                .collect()
+        };
+        let (xs, ys) = (offsets(&cols, &col_order), offsets(&rows, &row_order));
+
+        // Each slot's position in the output, and the (column, row) of the patch moved into it
+        let mut slots: Vec<_> = (0..rows.len())
+            .flat_map(|row| (0..cols.len()).map(move |col| (col, row)))
+            .map(|(col, row)| ((xs[col], ys[row]), (col_order[col], row_order[row])))
+            .collect();
+        let mut groups: Vec<((u32, u32), Vec<usize>)> = vec![];
+        for (i, &(_, (col, row))) in slots.iter().enumerate() {
+            let size = (cols[col].1, rows[row].1);
+            match groups
+                .iter_mut()
+                .find(|(group_size, _)| *group_size == size)
+            {
+                Some((_, slots)) => slots.push(i),
+                None => groups.push((size, vec![i])),
+            }
+        }
+        for (_, group) in groups {
+            let mut sources: Vec<_> = group.iter().map(|&i| slots[i].1).collect();
+            sources.shuffle(&mut rng);
+            for (i, source) in group.into_iter().zip(sources) {
+                slots[i].1 = source;
+            }
+        }
+
+        let mut out = img.clone();
+        for ((x, y), (col, row)) in slots {
+            let ((fx, w), (fy, h)) = (cols[col], rows[row]);
+            out.copy_from(&imageops::crop_imm(img, fx, fy, w, h).to_image(), x, y)
+                .unwrap();
+        }
+
+        (out, Tags(HashSet::from_iter([Label::PatchShuffled])))
+    }
+
+    fn execute_many(&self, img: &Image<P>) -> Vec<(Image<P>, Tags)> {
+        match self.grid_for(img.width(), img.height()) {
+            Some(_) => vec![self.execute(img)],
+            None => vec![],
+        }
+    }
+
+    fn name(&self) -> Cow<str> {
+        format!("pshuf_{}x{}", self.grid, self.grid).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({ "grid": self.grid, "seed": self.seed }))
+    }
+}
+
+/// A builder that will create `samples` stages, each of which blends the image with another randomly
+/// chosen input image (mixup), weighting the partner by a random alpha from `alpha_range`. Partners
+/// are drawn from an executor's [`InputPool`], so nothing is generated until it's executing.
+///
+/// [`InputPool`]: about:blank
+pub struct MixupBuilder {
+    /// The number of blended variants to create.
+    pub samples: usize,
+    /// The range of weights to give the partner image, from `0` to `1`.
+    pub alpha_range: RangeInclusive<f32>,
+    /// The input images to choose partners from.
+    pub pool: InputPool,
+}
+
+impl<R: Rng> StageBuilder<Rgba<u8>, R> for MixupBuilder {
+    fn variations(&self) -> usize {
+        self.samples
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !(self.pool.is_empty() || tags.0.contains(&Label::Mixed))
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<Rgba<u8>> + Send + Sync>> {
+        let min = self.alpha_range.start().max(0.);
+        let max = self.alpha_range.end().min(1.).max(min);
+        let len = self.pool.len().max(1);
+        (0..self.samples)
+            .map(|_| {
+                // An empty path never loads, so a missing partner yields no output
+                let partner = self.pool.get(rng.gen_range(0..len)).unwrap_or_default();
+                Box::new(MixupStage {
+                    partner,
+                    alpha: rng.gen_range(min..=max),
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new(
+            "mix_{partner}_{alpha}",
+            [Label::Mixed],
+        )]
+    }
+}
+
+/// The actual stage which blends the image with the image at `partner`, resized to match it, giving
+/// the partner a weight of `alpha`. Partners which are missing or fail to load yield no output at
+/// all rather than aborting the pipeline.
+pub struct MixupStage {
+    /// The path of the image to blend with.
+    partner: PathBuf,
+    /// The weight of the partner image, from `0` to `1`.
+    alpha: f32,
+}
+
+impl MixupStage {
+    /// The file stem of the partner image.
+    fn partner_stem(&self) -> Cow<str> {
+        self.partner
+            .file_stem()
+            .map_or_else(|| "".into(), |stem| stem.to_string_lossy())
+    }
+}
+
+impl ImageStage<Rgba<u8>> for MixupStage {
+    fn execute(&self, img: &Image<Rgba<u8>>) -> (Image<Rgba<u8>>, Tags) {
+        self.execute_many(img)
+            .pop()
+            .unwrap_or_else(|| (img.clone(), Tags::default()))
+    }
+
+    fn execute_many(&self, img: &Image<Rgba<u8>>) -> Vec<(Image<Rgba<u8>>, Tags)> {
+        let partner = match image::open(&self.partner) {
+            Ok(partner) => partner.to_rgba8(),
+            Err(_) => return vec![],
+        };
+        let (width, height) = img.dimensions();
+        let partner = imageops::resize(&partner, width, height, FilterType::Triangle);
+
+        let mut img = img.clone();
+        for (pixel, other) in img.pixels_mut().zip(partner.pixels()) {
+            for (c, o) in pixel.0.iter_mut().zip(&other.0) {
+                let mixed = *c as f32 * (1. - self.alpha) + *o as f32 * self.alpha;
+                *c = mixed.round() as u8;
+            }
+        }
+
+        vec![(img, Tags(HashSet::from_iter([Label::Mixed])))]
+    }
+
+    fn name(&self) -> Cow<str> {
+        format!("mix_{}_{:.1}", self.partner_stem(), self.alpha).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({
+            "partner": self.partner.to_string_lossy(),
+            "alpha": self.alpha,
+        }))
+    }
+}
+
+/// A builder that will create `samples` stages, each of which composites the image with three other
+/// randomly chosen input images into a 2x2 mosaic (as popularized by YOLOv4) at the image's original
+/// resolution. The point the four tiles meet at is jittered from the image's center by up to
+/// `center_jitter` of its width and height in either direction. Partners are drawn from an
+/// executor's [`InputPool`], so nothing is generated until it's executing.
+///
+/// [`InputPool`]: about:blank
+pub struct MosaicBuilder {
+    /// The number of mosaics to create.
+    pub samples: usize,
+    /// How far the center of the mosaic may stray from the image's center, relative to its size.
+    pub center_jitter: f32,
+    /// The input images to choose partners from.
+    pub pool: InputPool,
+}
+
+impl<R: Rng> StageBuilder<Rgba<u8>, R> for MosaicBuilder {
+    fn variations(&self) -> usize {
+        self.samples
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !(self.pool.is_empty() || tags.0.contains(&Label::Mosaic))
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<Rgba<u8>> + Send + Sync>> {
+        let jitter = self.center_jitter.max(0.).min(0.5);
+        let len = self.pool.len().max(1);
+        (0..self.samples)
+            .map(|_| {
+                let mut partner = || self.pool.get(rng.gen_range(0..len)).unwrap_or_default();
+                let partners = [partner(), partner(), partner()];
+                Box::new(MosaicStage {
+                    partners,
+                    center: (
+                        0.5 + rng.gen_range(-jitter..=jitter),
+                        0.5 + rng.gen_range(-jitter..=jitter),
+                    ),
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new("mosaic_{hash}", [Label::Mosaic])]
+    }
+}
+
+/// The actual stage which tiles the image into the top-left of a 2x2 mosaic with the images at
+/// `partners` filling the top-right, bottom-left and bottom-right, meeting at `center` (relative to
+/// the image's size). Each tile is scaled to cover its cell and cropped to it from the center.
+/// Partners which fail to load are replaced with the image itself.
+pub struct MosaicStage {
+    /// The paths of the images in the top-right, bottom-left and bottom-right cells.
+    partners: [PathBuf; 3],
+    /// The point the cells meet at, relative to the image's width and height.
+    center: (f32, f32),
+}
+
+/// Scales `img` to cover a `width` by `height` area, keeping its aspect ratio, and crops the excess
+/// evenly from either side.
+fn cover(img: &Image<Rgba<u8>>, width: u32, height: u32) -> Image<Rgba<u8>> {
+    let scale = (width as f32 / img.width() as f32).max(height as f32 / img.height() as f32);
+    let scaled_width = ((img.width() as f32 * scale).ceil() as u32).max(width);
+    let scaled_height = ((img.height() as f32 * scale).ceil() as u32).max(height);
+    let scaled = imageops::resize(img, scaled_width, scaled_height, FilterType::Triangle);
+    let (x, y) = ((scaled_width - width) / 2, (scaled_height - height) / 2);
+    imageops::crop_imm(&scaled, x, y, width, height).to_image()
+}
+
+impl ImageStage<Rgba<u8>> for MosaicStage {
+    fn execute(&self, img: &Image<Rgba<u8>>) -> (Image<Rgba<u8>>, Tags) {
+        let tags = Tags(HashSet::from_iter([Label::Mosaic]));
+        let (width, height) = img.dimensions();
+        if width < 2 || height < 2 {
+            return (img.clone(), tags);
+        }
+
+        let cx = ((width as f32 * self.center.0) as u32)
+            .max(1)
+            .min(width - 1);
+        let cy = ((height as f32 * self.center.1) as u32)
+            .max(1)
+            .min(height - 1);
+        let cells = [
+            (0, 0, cx, cy),
+            (cx, 0, width - cx, cy),
+            (0, cy, cx, height - cy),
+            (cx, cy, width - cx, height - cy),
+        ];
+        let partners = self.partners.iter().map(|path| {
+            image::open(path)
+                .ok()
+                .map(|partner| partner.to_rgba8())
+                .filter(|partner| partner.width() > 0 && partner.height() > 0)
+        });
+
+        let mut out = Image::new(width, height);
+        let tiles = std::iter::once(None).chain(partners);
+        for (&(x, y, w, h), tile) in cells.iter().zip(tiles) {
+            let tile = cover(tile.as_ref().unwrap_or(img), w, h);
+            out.copy_from(&tile, x, y).unwrap();
+        }
+
+        (out, tags)
+    }
+
+    fn name(&self) -> Cow<str> {
+        let mut bytes: Vec<u8> = self
+            .partners
+            .iter()
+            .flat_map(|path| path.to_string_lossy().into_owned().into_bytes())
+            .collect();
+        bytes.extend(&self.center.0.to_le_bytes());
+        bytes.extend(&self.center.1.to_le_bytes());
+        format!("mosaic_{:08x}", stable_hash(&bytes) as u32).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        let partners: Vec<_> = self.partners.iter().map(|p| p.to_string_lossy()).collect();
+        Some(json!({ "partners": partners, "center": self.center }))
+    }
+}
+
+/// What the shapes drawn by a [`ShapeOcclusionBuilder`] are filled with.
+///
+/// [`ShapeOcclusionBuilder`]: about:blank
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ShapeFill {
+    /// A random solid color for each shape.
+    RandomColor,
+    /// Uniformly random, opaque noise.
+    Noise,
+}
+
+/// A builder that will create `samples` stages, each of which covers the image with a random number
+/// of opaque shapes from `count_range`, picked from `shapes` and filled according to `fill`. Each
+/// shape's size is a fraction of the image's smaller dimension from `size_range`. Unlike
+/// [`OccludeBuilder`], the number of shapes varies and they completely hide what's beneath them.
+///
+/// Like [`CutoutBuilder`], this removes parts of the image, so the two aren't stacked unless
+/// `allow_stacking` is set.
+///
+/// [`OccludeBuilder`]: about:blank
+/// [`CutoutBuilder`]: about:blank
+pub struct ShapeOcclusionBuilder {
+    /// The number of occluded variants to create.
+    pub samples: usize,
+    /// The shapes to pick from, if empty only rectangles are drawn.
+    pub shapes: Vec<OccluderShape>,
+    /// The range of the number of shapes drawn, at least one.
+    pub count_range: RangeInclusive<usize>,
+    /// The range of the size of a shape, as a fraction of the image's smaller dimension.
+    pub size_range: RangeInclusive<f32>,
+    /// What the shapes are filled with.
+    pub fill: ShapeFill,
+    /// Whether to occlude images which have already been occluded or had holes cut out of them.
+    pub allow_stacking: bool,
+}
+
+impl<R: Rng> StageBuilder<Rgba<u8>, R> for ShapeOcclusionBuilder {
+    fn variations(&self) -> usize {
+        self.samples
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        self.allow_stacking
+            || !(tags.0.contains(&Label::Occluded) || tags.0.contains(&Label::Cutout))
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<Rgba<u8>> + Send + Sync>> {
+        // Without any shapes the image would be tagged as occluded while left as it is
+        let min_count = (*self.count_range.start()).max(1);
+        let max_count = (*self.count_range.end()).max(min_count);
+        let min_size = self.size_range.start().max(0.);
+        let max_size = self.size_range.end().max(min_size);
+        (0..self.samples)
+            .map(|id| {
+                let count = rng.gen_range(min_count..=max_count);
+                let occluders = (0..count)
+                    .map(|_| {
+                        let shape = *self.shapes.choose(rng).unwrap_or(&OccluderShape::Rectangle);
+                        let radii = Occluder::random_radii(shape, rng);
+
+                        Occluder {
+                            shape,
+                            center: (rng.gen(), rng.gen()),
+                            size: rng.gen_range(min_size..=max_size),
+                            color: rng.gen(),
+                            opacity: 1.,
+                            radii,
+                        }
+                    })
+                    .collect();
+
+                Box::new(ShapeOcclusionStage {
+                    id,
+                    occluders,
+                    fill: self.fill,
+                    seed: rng.gen(),
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new(
+            "occl_{count}shapes_{index}",
+            [Label::Occluded],
+        )]
+    }
+}
+
+/// The actual stage which covers the image with each of its `occluders` in order, clipped to the
+/// image's bounds. Shapes are either filled with their own color or with noise drawn from an RNG
+/// seeded by `seed`, so it's the same every time.
+pub struct ShapeOcclusionStage {
+    /// The index of this variant, used to tell apart variants drawing as many shapes.
+    id: usize,
+    /// The shapes to draw over the image.
+    occluders: Vec<Occluder>,
+    /// What the shapes are filled with.
+    fill: ShapeFill,
+    /// The seed of the RNG noise is drawn from, when filling shapes with noise.
+    seed: u64,
+}
+
+impl ImageStage<Rgba<u8>> for ShapeOcclusionStage {
+    fn execute(&self, img: &Image<Rgba<u8>>) -> (Image<Rgba<u8>>, Tags) {
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let mut img = img.clone();
+        for occluder in &self.occluders {
+            let mut mask = GrayImage::new(img.width(), img.height());
+            occluder.draw(&mut mask);
+
+            for (pixel, _) in img
+                .pixels_mut()
+                .zip(mask.pixels())
+                .filter(|(_, mask)| mask[0] > 0)
+            {
+                let [r, g, b] = match self.fill {
+                    ShapeFill::RandomColor => occluder.color,
+                    ShapeFill::Noise => rng.gen(),
+                };
+                *pixel = Rgba([r, g, b, u8::MAX]);
+            }
+        }
+
+        (img, Tags(HashSet::from_iter([Label::Occluded])))
+    }
+
+    fn name(&self) -> Cow<str> {
+        format!("occl_{}shapes_{}", self.occluders.len(), self.id).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        let occluders: Vec<_> = self
+            .occluders
+            .iter()
+            .map(|occluder| {
+                json!({
+                    "shape": format!("{:?}", occluder.shape),
+                    "center": occluder.center,
+                    "size": occluder.size,
+                    "color": occluder.color,
+                    "radii": occluder.radii,
+                })
+            })
+            .collect();
+        Some(json!({
+            "occluders": occluders,
+            "fill": format!("{:?}", self.fill),
+            "seed": self.seed,
+        }))
+    }
+}
+
+/// A builder that will create `samples` stages, each of which darkens every Nth row of the image, with
+/// a random period N between `min_period` and `max_period`, by a random amount from `darken_range`
+/// (negative amounts brighten the rows instead). With `interlace` set, alternate rows are also
+/// offset horizontally by one or two pixels, like a badly deinterlaced video frame.
+pub struct ScanlineBuilder {
+    /// The number of variants to create.
+    pub samples: usize,
+    /// The minimum number of rows between scanlines.
+    pub min_period: u32,
+    /// The maximum number of rows between scanlines.
+    pub max_period: u32,
+    /// The range of the fraction scanlines are darkened by.
+    pub darken_range: RangeInclusive<f32>,
+    /// Whether to offset alternate rows horizontally.
+    pub interlace: bool,
+}
+
+impl<P, R> StageBuilder<P, R> for ScanlineBuilder
+where
+    P: Pixel + 'static,
+    P::Subpixel: ValueInto<f32> + Clamp<f32>,
+    R: Rng,
+{
+    fn variations(&self) -> usize {
+        self.samples
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !tags.0.contains(&Label::Scanlines)
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<P> + Send + Sync>> {
+        let min = self.min_period.max(2);
+        let max = self.max_period.max(min);
+        let darken = self.darken_range.start().max(-1.)..=self.darken_range.end().min(1.);
+        (0..self.samples)
+            .map(|_| {
+                Box::new(ScanlineStage {
+                    period: rng.gen_range(min..=max),
+                    darken: rng.gen_range(darken.clone()),
+                    offset: if self.interlace {
+                        rng.gen_range(1..=2)
+                    } else {
+                        0
+                    },
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        let name = if self.interlace {
+            "scan_p{period}_d{darken}_i{offset}"
+        } else {
+            "scan_p{period}_d{darken}"
+        };
+        vec![StageDescriptor::new(name, [Label::Scanlines])]
+    }
+}
+
+/// The actual stage which scales the color channels of every `period`th row by `1 - darken`, and
+/// shifts every other row right by `offset` pixels (repeating the row's first pixel to fill the
+/// gap). Images no taller than the period would only ever have their first row touched, so they
+/// yield no output at all.
+pub struct ScanlineStage {
+    /// The number of rows between scanlines.
+    period: u32,
+    /// The fraction scanlines are darkened by, negative to brighten them.
+    darken: f32,
+    /// The number of pixels alternate rows are offset by.
+    offset: u32,
+}
+
+impl<P> ImageStage<P> for ScanlineStage
+where
+    P: Pixel + 'static,
+    P::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    fn execute(&self, img: &Image<P>) -> (Image<P>, Tags) {
+        let colors = color_channels::<P>();
+        let factor = 1. - self.darken;
+
+        let out = Image::from_fn(img.width(), img.height(), |x, y| {
+            let shift = if y % 2 == 1 { self.offset } else { 0 };
+            let mut pixel = *img.get_pixel(x.saturating_sub(shift), y);
+            if y % self.period == 0 {
+                for c in pixel.channels_mut().iter_mut().take(colors) {
+                    let value: f32 = (*c).value_into().unwrap_or_default();
+                    *c = P::Subpixel::clamp(value * factor);
+                }
+            }
+            pixel
+        });
+
+        (out, Tags(HashSet::from_iter([Label::Scanlines])))
+    }
+
+    fn execute_many(&self, img: &Image<P>) -> Vec<(Image<P>, Tags)> {
+        if img.height() <= self.period {
+            vec![]
+        } else {
+            vec![self.execute(img)]
+        }
+    }
+
+    fn name(&self) -> Cow<str> {
+        if self.offset > 0 {
+            format!("scan_p{}_d{:.1}_i{}", self.period, self.darken, self.offset).into()
+        } else {
+            format!("scan_p{}_d{:.1}", self.period, self.darken).into()
+        }
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({
+            "period": self.period,
+            "darken": self.darken,
+            "offset": self.offset,
+        }))
+    }
+}
+
+/// A builder that will create `samples` stages, each of which glitches the image by shifting between
+/// `min_slices` and `max_slices` random horizontal slices of it sideways, by up to `max_shift_frac`
+/// of its width. With `channel_corrupt` set, one color channel of each slice is shifted further than
+/// the others, for the classic datamosh look.
+pub struct GlitchBuilder {
+    /// The number of glitched variants to create.
+    pub samples: usize,
+    /// The minimum number of slices shifted, at least one.
+    pub min_slices: usize,
+    /// The maximum number of slices shifted.
+    pub max_slices: usize,
+    /// The furthest a slice is shifted, relative to the image's width.
+    pub max_shift_frac: f32,
+    /// Whether to shift a color channel of each slice on its own.
+    pub channel_corrupt: bool,
+}
+
+impl<R: Rng> StageBuilder<Rgba<u8>, R> for GlitchBuilder {
+    fn variations(&self) -> usize {
+        self.samples
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !tags.0.contains(&Label::Glitched)
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<Rgba<u8>> + Send + Sync>> {
+        // Without any slices the image would be tagged as glitched while left as it is
+        let min = self.min_slices.max(1);
+        let max = self.max_slices.max(min);
+        (0..self.samples)
+            .map(|_| {
+                Box::new(GlitchStage {
+                    slices: rng.gen_range(min..=max),
+                    max_shift_frac: self.max_shift_frac.max(0.).min(1.),
+                    channel_corrupt: self.channel_corrupt,
+                    seed: rng.gen(),
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new(
+            "glitch_{slices}slices_{hash}",
+            [Label::Glitched],
+        )]
+    }
+}
+
+/// The actual stage which shifts `slices` horizontal slices of the image sideways, wrapping around
+/// its edges, by up to `max_shift_frac` of its width. Each slice is up to a tenth of the image's
+/// height, clipped to its bottom. The slices and shifts are drawn from an RNG seeded by `seed`, so
+/// they're the same every time. Images too narrow for a slice to shift by a pixel are skipped.
+pub struct GlitchStage {
+    /// The number of slices to shift.
+    slices: usize,
+    /// The furthest a slice is shifted, relative to the image's width.
+    max_shift_frac: f32,
+    /// Whether to shift a color channel of each slice on its own.
+    channel_corrupt: bool,
+    /// The seed of the RNG the slices are drawn from.
+    seed: u64,
+}
+
+impl GlitchStage {
+    /// The furthest a slice of a `width` by `height` image is shifted in pixels, or `None` if it
+    /// can't be shifted at all.
+    fn max_shift(&self, width: u32, height: u32) -> Option<i64> {
+        let max_shift = (width as f32 * self.max_shift_frac) as i64;
+        if max_shift == 0 || height == 0 {
+            None
+        } else {
+            Some(max_shift)
+        }
+    }
+}
+
+impl ImageStage<Rgba<u8>> for GlitchStage {
+    fn execute(&self, img: &Image<Rgba<u8>>) -> (Image<Rgba<u8>>, Tags) {
+        let tags = Tags(HashSet::from_iter([Label::Glitched]));
+        let (width, height) = img.dimensions();
+        let max_shift = match self.max_shift(width, height) {
+            Some(max_shift) => max_shift,
+            None => return (img.clone(), Tags::default()),
+        };
+
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let max_height = (height / 10).max(1);
+        let mut out = img.clone();
+        for _ in 0..self.slices {
+            let top = rng.gen_range(0..height);
+            let bottom = (top + rng.gen_range(1..=max_height)).min(height);
+            let shift = rng.gen_range(-max_shift..=max_shift);
+            let corrupt = if self.channel_corrupt {
+                Some((rng.gen_range(0..3), rng.gen_range(-max_shift..=max_shift)))
+            } else {
+                None
+            };
+
+            for y in top..bottom {
+                // Shift from the already glitched rows, so overlapping slices compound
+                let row: Vec<_> = (0..width).map(|x| *out.get_pixel(x, y)).collect();
+                let wrap =
+                    |x: u32, shift: i64| row[(x as i64 - shift).rem_euclid(width as i64) as usize];
+                for x in 0..width {
+                    let mut pixel = wrap(x, shift);
+                    if let Some((channel, extra)) = corrupt {
+                        pixel[channel] = wrap(x, shift + extra)[channel];
+                    }
+                    out.put_pixel(x, y, pixel);
+                }
+            }
+        }
+
+        (out, tags)
+    }
+
+    fn execute_many(&self, img: &Image<Rgba<u8>>) -> Vec<(Image<Rgba<u8>>, Tags)> {
+        match self.max_shift(img.width(), img.height()) {
+            Some(_) => vec![self.execute(img)],
+            None => vec![],
+        }
+    }
+
+    fn name(&self) -> Cow<str> {
+        let hash = stable_hash(&self.seed.to_le_bytes()) as u32;
+        format!("glitch_{}slices_{:08x}", self.slices, hash).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({
+            "slices": self.slices,
+            "max_shift_frac": self.max_shift_frac,
+            "channel_corrupt": self.channel_corrupt,
+            "seed": self.seed,
+        }))
+    }
+}
+
+/// A builder that will create `samples` stages, each of which lays a `grid` by `grid` grid of cells
+/// over the image, moves each of its nodes by a random offset of up to `max_magnitude` pixels along
+/// either axis, and warps the image along with the grid (as in albumentations' `GridDistortion`).
+pub struct GridDistortionBuilder {
+    /// The number of distorted variants to create.
+    samples: usize,
+    /// The number of cells along either side of the grid.
+    grid: u32,
+    /// The furthest a node is moved along either axis, in pixels.
+    max_magnitude: f32,
+}
+
+impl GridDistortionBuilder {
+    /// Creates a builder distorting `samples` variants through a `grid` by `grid` grid with nodes
+    /// moved by up to `max_magnitude` pixels.
+    ///
+    /// # Panics
+    ///
+    /// If `grid` is below `2`, since a single cell can't be distorted.
+    pub fn new(samples: usize, grid: u32, max_magnitude: f32) -> Self {
+        assert!(
+            grid >= 2,
+            "a distortion grid needs at least 2x2 cells, got {}",
+            grid
+        );
+
+        Self {
+            samples,
+            grid,
+            max_magnitude: max_magnitude.max(0.),
+        }
+    }
+}
+
+impl<P, R> StageBuilder<P, R> for GridDistortionBuilder
+where
+    P: Pixel + 'static,
+    P::Subpixel: ValueInto<f32> + Clamp<f32>,
+    R: Rng,
+{
+    fn variations(&self) -> usize {
+        self.samples
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !tags.0.contains(&Label::GridDistorted)
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<P> + Send + Sync>> {
+        (0..self.samples)
+            .map(|id| {
+                Box::new(GridDistortionStage {
+                    id,
+                    grid: self.grid,
+                    max_magnitude: self.max_magnitude,
+                    seed: rng.gen(),
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        let name = format!(
+            "griddist_{}x{}_m{:.0}_{{index}}",
+            self.grid, self.grid, self.max_magnitude
+        );
+        vec![StageDescriptor::new(name, [Label::GridDistorted])]
+    }
+}
+
+/// The actual stage which moves each node of a `grid` by `grid` grid over the image by up to
+/// `max_magnitude` pixels, with offsets drawn from an RNG seeded by `seed` so they're the same every
+/// time. Each pixel samples the image (with bilinear interpolation, clamped to its edges) at an
+/// offset interpolated bilinearly from the offsets of the corners of its cell.
+pub struct GridDistortionStage {
+    /// The index of this variant, since variants of the same builder differ only in their seed.
+    id: usize,
+    /// The number of cells along either side of the grid.
+    grid: u32,
+    /// The furthest a node is moved along either axis, in pixels.
+    max_magnitude: f32,
+    /// The seed of the RNG the offsets are drawn from.
+    seed: u64,
+}
+
+impl<P> ImageStage<P> for GridDistortionStage
+where
+    P: Pixel + 'static,
+    P::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    fn execute(&self, img: &Image<P>) -> (Image<P>, Tags) {
+        let tags = Tags(HashSet::from_iter([Label::GridDistorted]));
+        let (width, height) = img.dimensions();
+        if width == 0 || height == 0 {
+            return (img.clone(), tags);
+        }
+
+        let nodes = self.grid as usize + 1;
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let m = self.max_magnitude;
+        let offsets: Vec<(f32, f32)> = (0..nodes * nodes)
+            .map(|_| (rng.gen_range(-m..=m), rng.gen_range(-m..=m)))
+            .collect();
+        let (cell_width, cell_height) = (
+            width as f32 / self.grid as f32,
+            height as f32 / self.grid as f32,
+        );
+
+        let distorted = Image::from_fn(width, height, |x, y| {
+            let (gx, gy) = (x as f32 / cell_width, y as f32 / cell_height);
+            let (col, row) = ((gx as usize).min(nodes - 2), (gy as usize).min(nodes - 2));
+            let (fx, fy) = (gx - col as f32, gy - row as f32);
+            let node = |c: usize, r: usize| offsets[r * nodes + c];
+            let lerp = |a: (f32, f32), b: (f32, f32), t: f32| {
+                (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t)
+            };
+            let top = lerp(node(col, row), node(col + 1, row), fx);
+            let bottom = lerp(node(col, row + 1), node(col + 1, row + 1), fx);
+            let (dx, dy) = lerp(top, bottom, fy);
+            sample_bilinear(img, x as f32 + dx, y as f32 + dy)
+        });
+
+        (distorted, tags)
+    }
+
+    fn name(&self) -> Cow<str> {
+        format!(
+            "griddist_{}x{}_m{:.0}_{}",
+            self.grid, self.grid, self.max_magnitude, self.id
+        )
+        .into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({
+            "grid": self.grid,
+            "max_magnitude": self.max_magnitude,
+            "seed": self.seed,
+        }))
+    }
+}
+
+/// A builder that will create `samples` stages, each of which twists the image into a whirlpool about
+/// its center, rotating pixels by up to a random number of radians between `min_strength` and
+/// `max_strength` at the center, decaying to nothing at `radius_frac` of the way to the image's
+/// edges. Pixels beyond that are left untouched.
+pub struct SwirlBuilder {
+    /// The number of swirled variants to create.
+    pub samples: usize,
+    /// The minimum rotation at the center, in radians.
+    pub min_strength: f32,
+    /// The maximum rotation at the center, in radians.
+    pub max_strength: f32,
+    /// The extent of the swirl, relative to half the image's width and height.
+    pub radius_frac: f32,
+}
+
+impl<P, R> StageBuilder<P, R> for SwirlBuilder
+where
+    P: Pixel + 'static,
+    P::Subpixel: ValueInto<f32> + Clamp<f32>,
+    R: Rng,
+{
+    fn variations(&self) -> usize {
+        self.samples
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !tags.0.contains(&Label::Swirled)
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<P> + Send + Sync>> {
+        let max = self.max_strength.max(self.min_strength);
+        (0..self.samples)
+            .map(|_| {
+                Box::new(SwirlStage {
+                    strength: rng.gen_range(self.min_strength..=max),
+                    radius_frac: self.radius_frac.max(0.),
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new("swirl_{strength}", [Label::Swirled])]
+    }
+}
+
+/// The actual stage which rotates each pixel within `radius_frac` of the image's center by
+/// `strength` radians scaled by the square of how far it is from the swirl's edge, sampling the
+/// image with bilinear interpolation. The falloff is elliptical, following the image's aspect
+/// ratio, so the swirl isn't squashed on non-square images.
+pub struct SwirlStage {
+    /// The rotation at the center, in radians.
+    strength: f32,
+    /// The extent of the swirl, relative to half the image's width and height.
+    radius_frac: f32,
+}
+
+impl<P> ImageStage<P> for SwirlStage
+where
+    P: Pixel + 'static,
+    P::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    fn execute(&self, img: &Image<P>) -> (Image<P>, Tags) {
+        let tags = Tags(HashSet::from_iter([Label::Swirled]));
+        let (width, height) = img.dimensions();
+        let (cx, cy) = (width as f32 / 2., height as f32 / 2.);
+        let (rx, ry) = (cx * self.radius_frac, cy * self.radius_frac);
+        if rx <= 0. || ry <= 0. {
+            return (img.clone(), tags);
+        }
+
+        let swirled = Image::from_fn(width, height, |x, y| {
+            // Work in coordinates where the swirl's boundary is the unit circle
+            let (u, v) = ((x as f32 + 0.5 - cx) / rx, (y as f32 + 0.5 - cy) / ry);
+            let r = (u * u + v * v).sqrt();
+            if r >= 1. {
+                return *img.get_pixel(x, y);
+            }
+
+            let (sin, cos) = (self.strength * (1. - r).powi(2)).sin_cos();
+            let (su, sv) = (u * cos - v * sin, u * sin + v * cos);
+            sample_bilinear(img, cx + su * rx - 0.5, cy + sv * ry - 0.5)
+        });
+
+        (swirled, tags)
+    }
+
+    fn name(&self) -> Cow<str> {
+        format!("swirl_{:.1}", self.strength).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({ "strength": self.strength, "radius_frac": self.radius_frac }))
+    }
+}
+
+/// The axis a [`WaveBuilder`] displaces pixels along.
+///
+/// [`WaveBuilder`]: about:blank
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum WaveAxis {
+    /// Rows are displaced horizontally, by a wave running down the image.
+    Horizontal,
+    /// Columns are displaced vertically, by a wave running across the image.
+    Vertical,
+}
+
+/// A builder that will create `samples` stages, each of which ripples the image like the surface of
+/// water, displacing its rows or columns by a sine wave with a random amplitude from `amp_range`,
+/// wavelength from `wavelength_range` (both in pixels) and phase. The waves run along `axis`, or a
+/// random axis for each stage if it's `None`.
+pub struct WaveBuilder {
+    /// The number of waved variants to create.
+    pub samples: usize,
+    /// The range of the wave's amplitude, in pixels.
+    pub amp_range: RangeInclusive<f32>,
+    /// The range of the wave's wavelength, in pixels.
+    pub wavelength_range: RangeInclusive<f32>,
+    /// The axis pixels are displaced along, or `None` to pick one at random.
+    pub axis: Option<WaveAxis>,
+}
+
+impl<P, R> StageBuilder<P, R> for WaveBuilder
+where
+    P: Pixel + 'static,
+    P::Subpixel: ValueInto<f32> + Clamp<f32>,
+    R: Rng,
+{
+    fn variations(&self) -> usize {
+        self.samples
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !tags.0.contains(&Label::Waved)
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<P> + Send + Sync>> {
+        let min_wavelength = self.wavelength_range.start().max(1.);
+        let max_wavelength = self.wavelength_range.end().max(min_wavelength);
+        (0..self.samples)
+            .map(|_| {
+                let axis = self.axis.unwrap_or_else(|| {
+                    *[WaveAxis::Horizontal, WaveAxis::Vertical]
+                        .choose(rng)
+                        .unwrap()
+                });
+                Box::new(WaveStage {
+                    amplitude: rng.gen_range(self.amp_range.clone()),
+                    wavelength: rng.gen_range(min_wavelength..=max_wavelength),
+                    phase: rng.gen_range(0.0..std::f32::consts::TAU),
+                    axis,
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new(
+            "wave_a{amplitude}_w{wavelength}",
+            [Label::Waved],
+        )]
+    }
+}
+
+/// The actual stage which displaces every row (for horizontal waves) or column (for vertical ones)
+/// of the image by `amplitude` times the sine of its position, with a period of `wavelength` pixels
+/// offset by `phase` radians. Pixels are sampled with bilinear interpolation, clamped to the image's
+/// edges.
+pub struct WaveStage {
+    /// The amplitude of the wave, in pixels.
+    amplitude: f32,
+    /// The wavelength of the wave, in pixels.
+    wavelength: f32,
+    /// The phase of the wave, in radians.
+    phase: f32,
+    /// The axis pixels are displaced along.
+    axis: WaveAxis,
+}
+
+impl<P> ImageStage<P> for WaveStage
+where
+    P: Pixel + 'static,
+    P::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    fn execute(&self, img: &Image<P>) -> (Image<P>, Tags) {
+        let tags = Tags(HashSet::from_iter([Label::Waved]));
+        let (width, height) = img.dimensions();
+        if width == 0 || height == 0 {
+            return (img.clone(), tags);
+        }
+
+        let k = std::f32::consts::TAU / self.wavelength;
+        let waved = Image::from_fn(width, height, |x, y| {
+            let (x, y) = (x as f32, y as f32);
+            match self.axis {
+                WaveAxis::Horizontal => {
+                    let shift = self.amplitude * (k * y + self.phase).sin();
+                    sample_bilinear(img, x + shift, y)
+                }
+                WaveAxis::Vertical => {
+                    let shift = self.amplitude * (k * x + self.phase).sin();
+                    sample_bilinear(img, x, y + shift)
+                }
+            }
+        });
+
+        (waved, tags)
+    }
+
+    fn name(&self) -> Cow<str> {
+        format!("wave_a{:.0}_w{:.0}", self.amplitude, self.wavelength).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({
+            "amplitude": self.amplitude,
+            "wavelength": self.wavelength,
+            "phase": self.phase,
+            "axis": format!("{:?}", self.axis),
+        }))
+    }
+}
+
+/// A builder that will create `samples` stages, each of which binarizes the image at a random luma
+/// threshold between `min_threshold` and `max_threshold`. With `otsu` set the threshold is instead
+/// computed from each image with Otsu's method, and since that's deterministic only a single stage is
+/// created whatever `samples` is.
+pub struct ThresholdBuilder {
+    /// The number of binarized variants to create.
+    pub samples: usize,
+    /// The minimum threshold.
+    pub min_threshold: u8,
+    /// The maximum threshold.
+    pub max_threshold: u8,
+    /// Whether to compute the threshold with Otsu's method.
+    pub otsu: bool,
+}
+
+impl<R: Rng> StageBuilder<Rgba<u8>, R> for ThresholdBuilder {
+    fn variations(&self) -> usize {
+        if self.otsu {
+            self.samples.min(1)
+        } else {
+            self.samples
+        }
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !tags.0.contains(&Label::Binarized)
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<Rgba<u8>> + Send + Sync>> {
+        let max = self.max_threshold.max(self.min_threshold);
+        (0..StageBuilder::<Rgba<u8>, R>::variations(self))
+            .map(|_| {
+                let threshold = if self.otsu {
+                    None
+                } else {
+                    Some(rng.gen_range(self.min_threshold..=max))
+                };
+                Box::new(ThresholdStage { threshold }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        let name = if self.otsu {
+            "thresh_otsu"
+        } else {
+            "thresh_{threshold}"
+        };
+        vec![StageDescriptor::new(name, [Label::Binarized])]
+    }
+}
+
+/// The actual stage which turns pixels whose luma is above `threshold` white and the rest black,
+/// leaving alpha alone. Without a threshold one is computed with Otsu's method.
+pub struct ThresholdStage {
+    /// The luma threshold, or `None` to compute it with Otsu's method.
+    threshold: Option<u8>,
+}
+
+impl ImageStage<Rgba<u8>> for ThresholdStage {
+    fn execute(&self, img: &Image<Rgba<u8>>) -> (Image<Rgba<u8>>, Tags) {
+        let luma = DynamicImage::ImageRgba8(img.clone()).to_luma8();
+        let threshold = self.threshold.unwrap_or_else(|| otsu_level(&luma));
+
+        let mut img = img.clone();
+        for (pixel, luma) in img.pixels_mut().zip(luma.pixels()) {
+            let value = if luma[0] > threshold { u8::MAX } else { 0 };
+            *pixel = Rgba([value, value, value, pixel[3]]);
+        }
+
+        (img, Tags(HashSet::from_iter([Label::Binarized])))
+    }
+
+    fn name(&self) -> Cow<str> {
+        match self.threshold {
+            Some(threshold) => format!("thresh_{}", threshold).into(),
+            None => "thresh_otsu".into(),
+        }
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({ "threshold": self.threshold }))
+    }
+}
+
+/// A builder that will create `samples` stages, each of which gives the image a cartoonish look by
+/// smoothing it while preserving edges, then outlining those edges in black. The smoothing and the
+/// darkness of the outlines are random, between `0` and `1`, within `smoothing_range` and
+/// `edge_range` respectively.
+///
+/// This is far slower than most stages, so images larger than `max_dimension` along either side are
+/// processed at a smaller scale and scaled back up.
+pub struct CartoonBuilder {
+    /// The number of cartoon variants to create.
+    pub samples: usize,
+    /// The range of the amount of smoothing.
+    pub smoothing_range: RangeInclusive<f32>,
+    /// The range of the darkness of the outlines.
+    pub edge_range: RangeInclusive<f32>,
+    /// The largest width or height processed at full resolution.
+    pub max_dimension: u32,
+}
+
+impl<R: Rng> StageBuilder<Rgba<u8>, R> for CartoonBuilder {
+    fn variations(&self) -> usize {
+        self.samples
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !tags.0.contains(&Label::Cartoon)
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<Rgba<u8>> + Send + Sync>> {
+        let clamp = |range: &RangeInclusive<f32>| {
+            let min = range.start().max(0.).min(1.);
+            min..=range.end().max(min).min(1.)
+        };
+        let (smoothing, edges) = (clamp(&self.smoothing_range), clamp(&self.edge_range));
+        (0..self.samples)
+            .map(|_| {
+                Box::new(CartoonStage {
+                    smoothing: rng.gen_range(smoothing.clone()),
+                    edge_strength: rng.gen_range(edges.clone()),
+                    max_dimension: self.max_dimension.max(1),
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new(
+            "cartoon_{smoothing}",
+            [Label::Cartoon],
+        )]
+    }
+}
+
+/// The actual stage which smooths the image with a bilateral filter whose window and color
+/// tolerance grow with `smoothing`, then darkens it along its Sobel edges by up to `edge_strength`.
+/// Images larger than `max_dimension` are processed at a scale fitting within it. Alpha is left
+/// alone.
+pub struct CartoonStage {
+    /// The amount of smoothing, between `0` and `1`.
+    smoothing: f32,
+    /// How dark the outlines are, between `0` and `1`.
+    edge_strength: f32,
+    /// The largest width or height processed at full resolution.
+    max_dimension: u32,
+}
+
+impl CartoonStage {
+    /// Smooths `img` with a bilateral filter, averaging each pixel with those within `radius` of it
+    /// weighted both by their distance and by how different their color is.
+    fn bilateral(img: &Image<Rgba<u8>>, radius: i64, sigma_color: f32) -> Image<Rgba<u8>> {
+        let (width, height) = img.dimensions();
+        let sigma_space = radius as f32 / 2.;
+        let spatial: Vec<_> = (-radius..=radius)
+            .flat_map(|dy| (-radius..=radius).map(move |dx| (dx, dy)))
+            .map(|(dx, dy)| {
+                let distance = (dx * dx + dy * dy) as f32;
+                (dx, dy, (-distance / (2. * sigma_space * sigma_space)).exp())
+            })
+            .collect();
+
+        Image::from_fn(width, height, |x, y| {
+            let center = img.get_pixel(x, y);
+            let (mut sums, mut total) = ([0f32; 3], 0.);
+            for &(dx, dy, weight) in &spatial {
+                let sx = (x as i64 + dx).max(0).min(width as i64 - 1) as u32;
+                let sy = (y as i64 + dy).max(0).min(height as i64 - 1) as u32;
+                let other = img.get_pixel(sx, sy);
+                let difference: f32 = (0..3)
+                    .map(|c| (other[c] as f32 - center[c] as f32).powi(2))
+                    .sum();
+                let weight = weight * (-difference / (2. * sigma_color * sigma_color)).exp();
+                (0..3).for_each(|c| sums[c] += other[c] as f32 * weight);
+                total += weight;
+            }
+            let channel = |c: usize| (sums[c] / total).round() as u8;
+            Rgba([channel(0), channel(1), channel(2), center[3]])
+        })
+    }
+}
+
+impl ImageStage<Rgba<u8>> for CartoonStage {
+    fn execute(&self, img: &Image<Rgba<u8>>) -> (Image<Rgba<u8>>, Tags) {
+        let tags = Tags(HashSet::from_iter([Label::Cartoon]));
+        let (width, height) = img.dimensions();
+        if width == 0 || height == 0 {
+            return (img.clone(), tags);
+        }
+
+        let scale = (self.max_dimension as f32 / width.max(height) as f32).min(1.);
+        let working = if scale < 1. {
+            let (w, h) = (
+                ((width as f32 * scale) as u32).max(1),
+                ((height as f32 * scale) as u32).max(1),
+            );
+            imageops::resize(img, w, h, FilterType::Triangle)
+        } else {
+            img.clone()
+        };
+
+        let radius = 2 + (self.smoothing * 4.).round() as i64;
+        let mut cartoon = Self::bilateral(&working, radius, 10. + 50. * self.smoothing);
+        let gray = DynamicImage::ImageRgba8(cartoon.clone()).to_luma8();
+        let gradients = sobel_gradients(&gray);
+        for (pixel, gradient) in cartoon.pixels_mut().zip(gradients.pixels()) {
+            // Half the response of a Sobel kernel to a full step already counts as a full edge
+            let edge = (gradient[0] as f32 / (2. * 255.)).min(1.);
+            let factor = 1. - self.edge_strength * edge;
+            for c in 0..3 {
+                pixel[c] = (pixel[c] as f32 * factor).round() as u8;
+            }
+        }
+
+        if scale < 1. {
+            cartoon = imageops::resize(&cartoon, width, height, FilterType::Triangle);
+            for (pixel, original) in cartoon.pixels_mut().zip(img.pixels()) {
+                pixel[3] = original[3];
+            }
+        }
+
+        (cartoon, tags)
+    }
+
+    fn name(&self) -> Cow<str> {
+        format!("cartoon_{:.1}", self.smoothing).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({
+            "smoothing": self.smoothing,
+            "edge_strength": self.edge_strength,
+            "max_dimension": self.max_dimension,
+        }))
+    }
+}
+
+/// A builder that will create `samples` stages, each of which lifts or crushes the shadows and
+/// highlights of the image independently along a smooth tone curve, by random amounts (in 8-bit
+/// levels, positive to brighten) from `shadow_range` and `highlight_range` respectively. Unlike
+/// [`LuminosityBuilder`], pure black and white are left where they are.
+///
+/// [`LuminosityBuilder`]: about:blank
+pub struct ShadowHighlightBuilder {
+    /// The number of adjusted variants to create.
+    pub samples: usize,
+    /// The range of the shift of the shadows, in levels.
+    pub shadow_range: RangeInclusive<i16>,
+    /// The range of the shift of the highlights, in levels.
+    pub highlight_range: RangeInclusive<i16>,
+}
+
+impl<P, R> StageBuilder<P, R> for ShadowHighlightBuilder
+where
+    P: Pixel<Subpixel = u8> + 'static,
+    R: Rng,
+{
+    fn variations(&self) -> usize {
+        self.samples
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !tags.0.contains(&Label::ToneCurve)
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<P> + Send + Sync>> {
+        (0..self.samples)
+            .map(|_| {
+                let shadows = rng.gen_range(self.shadow_range.clone());
+                let highlights = rng.gen_range(self.highlight_range.clone());
+                // Bumps peaking at a third and two thirds of the way to white, and zero at both ends
+                let mut lut = [0; 256];
+                for (level, out) in lut.iter_mut().enumerate() {
+                    let t = level as f32 / 255.;
+                    let shadow_weight = 27. / 4. * t * (1. - t).powi(2);
+                    let highlight_weight = 27. / 4. * t * t * (1. - t);
+                    let value = level as f32
+                        + shadows as f32 * shadow_weight
+                        + highlights as f32 * highlight_weight;
+                    *out = value.round().max(0.).min(255.) as u8;
+                }
+
+                Box::new(ShadowHighlightStage {
+                    shadows,
+                    highlights,
+                    lut,
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new(
+            "tone_s{shadows}_h{highlights}",
+            [Label::ToneCurve],
+        )]
+    }
+}
+
+/// The actual stage which maps every color channel through the tone curve `lut`, which shifts the
+/// shadows by up to `shadows` levels and the highlights by up to `highlights`. Alpha is left alone.
+pub struct ShadowHighlightStage {
+    /// The peak shift of the shadows, in levels.
+    shadows: i16,
+    /// The peak shift of the highlights, in levels.
+    highlights: i16,
+    /// The value each level is mapped to.
+    lut: [u8; 256],
+}
+
+impl<P: Pixel<Subpixel = u8> + 'static> ImageStage<P> for ShadowHighlightStage {
+    fn execute(&self, img: &Image<P>) -> (Image<P>, Tags) {
+        let colors = color_channels::<P>();
+        let mut img = img.clone();
+        for pixel in img.pixels_mut() {
+            for c in pixel.channels_mut().iter_mut().take(colors) {
+                *c = self.lut[*c as usize];
+            }
+        }
+
+        (img, Tags(HashSet::from_iter([Label::ToneCurve])))
+    }
+
+    fn name(&self) -> Cow<str> {
+        format!("tone_s{:+}_h{:+}", self.shadows, self.highlights).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({ "shadows": self.shadows, "highlights": self.highlights }))
+    }
+}
+
+/// A builder that will create `samples` stages, each of which shifts the red channel left and the blue
+/// channel right by the same random whole number of pixels up to `max_offset`, like the ghosting of
+/// an anaglyph seen without 3D glasses. Unlike lateral [`ChromaticAberrationBuilder`] shifts this is
+/// an exact translation. With `random_direction` set, each stage may swap the two directions.
+///
+/// [`ChromaticAberrationBuilder`]: about:blank
+pub struct AnaglyphOffsetBuilder {
+    /// The number of offset variants to create.
+    pub samples: usize,
+    /// The maximum offset of the red and blue channels, in pixels.
+    pub max_offset: u32,
+    /// Whether the red channel may be shifted right and the blue left instead.
+    pub random_direction: bool,
+}
+
+impl<R: Rng> StageBuilder<Rgba<u8>, R> for AnaglyphOffsetBuilder {
+    fn variations(&self) -> usize {
+        if self.max_offset == 0 {
+            0
+        } else {
+            self.samples
+        }
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !tags.0.contains(&Label::ChannelOffset)
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<Rgba<u8>> + Send + Sync>> {
+        // An offset of 0 would leave the image as it is
+        if self.max_offset == 0 {
+            return vec![];
+        }
+
+        (0..self.samples)
+            .map(|_| {
+                Box::new(AnaglyphOffsetStage {
+                    offset: rng.gen_range(1..=self.max_offset),
+                    swapped: self.random_direction && rng.gen(),
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        let mut descriptors = vec![StageDescriptor::new(
+            "anaglyph_{offset}px",
+            [Label::ChannelOffset],
+        )];
+        if self.random_direction {
+            descriptors.push(StageDescriptor::new(
+                "anaglyph_{offset}px_rev",
+                [Label::ChannelOffset],
+            ));
+        }
+        descriptors
+    }
+}
+
+/// The actual stage which moves the red channel `offset` pixels left and the blue channel `offset`
+/// pixels right (the other way around if `swapped`), repeating the edge columns where they're
+/// pulled in from outside the image. Green and alpha are left alone.
+pub struct AnaglyphOffsetStage {
+    /// How far the red and blue channels are shifted, in pixels.
+    offset: u32,
+    /// Whether red is shifted right and blue left.
+    swapped: bool,
+}
+
+impl ImageStage<Rgba<u8>> for AnaglyphOffsetStage {
+    fn execute(&self, img: &Image<Rgba<u8>>) -> (Image<Rgba<u8>>, Tags) {
+        let last = img.width().saturating_sub(1);
+        let (red, blue) = if self.swapped {
+            (-(self.offset as i64), self.offset as i64)
+        } else {
+            (self.offset as i64, -(self.offset as i64))
+        };
+        // Where each channel is read from, relative to the pixel being written
+        let source = |x: u32, delta: i64| (x as i64 + delta).max(0).min(last as i64) as u32;
+
+        let mut out = img.clone();
+        for (x, y, pixel) in out.enumerate_pixels_mut() {
+            pixel[0] = img.get_pixel(source(x, red), y)[0];
+            pixel[2] = img.get_pixel(source(x, blue), y)[2];
+        }
+
+        (out, Tags(HashSet::from_iter([Label::ChannelOffset])))
+    }
+
+    fn name(&self) -> Cow<str> {
+        if self.swapped {
+            format!("anaglyph_{}px_rev", self.offset).into()
+        } else {
+            format!("anaglyph_{}px", self.offset).into()
+        }
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({ "offset": self.offset, "swapped": self.swapped }))
+    }
+}
+
+/// A builder that will create `samples` stages, each of which draws semi-transparent streaks of rain
+/// over the image. Every stage picks a random density (the number of streaks per 100 pixels of the
+/// image) from `density_range`, length (in pixels) from `length_range` and slant (in degrees from
+/// vertical, positive leaning right) from `angle_range`. With `motion_blur` set, the image behind the
+/// rain is also slightly smeared along the streaks.
+pub struct RainBuilder {
+    /// The number of rainy variants to create.
+    pub samples: usize,
+    /// The range of the number of streaks per 100 pixels.
+    pub density_range: RangeInclusive<f32>,
+    /// The range of the length of the streaks, in pixels.
+    pub length_range: RangeInclusive<u32>,
+    /// The range of the slant of the streaks, in degrees from vertical.
+    pub angle_range: RangeInclusive<f32>,
+    /// Whether to blur the image along the streaks.
+    pub motion_blur: bool,
+}
+
+impl<R: Rng> StageBuilder<Rgba<u8>, R> for RainBuilder {
+    fn variations(&self) -> usize {
+        self.samples
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !tags.0.contains(&Label::Rain)
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<Rgba<u8>> + Send + Sync>> {
+        let density = self.density_range.start().max(0.)..=self.density_range.end().max(0.);
+        let length = (*self.length_range.start()).max(1)..=(*self.length_range.end()).max(1);
+        (0..self.samples)
+            .map(|id| {
+                Box::new(RainStage {
+                    density: rng.gen_range(density.clone()),
+                    length: rng.gen_range(length.clone()),
+                    angle: rng.gen_range(self.angle_range.clone()),
+                    motion_blur: self.motion_blur,
+                    seed: rng.gen(),
+                    id,
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new(
+            "rain_{density}_{length}px_{id}",
+            [Label::Rain],
+        )]
+    }
+}
+
+/// The actual stage which rains on the image. The streaks are all drawn (at positions and opacities
+/// from an RNG seeded by `seed`) into a single mask, which is then used to blend the image toward a
+/// pale blue-gray, so drawing doesn't allocate per streak. Alpha is left alone.
+pub struct RainStage {
+    /// The number of streaks per 100 pixels.
+    density: f32,
+    /// The length of the streaks, in pixels.
+    length: u32,
+    /// The slant of the streaks, in degrees from vertical.
+    angle: f32,
+    /// Whether to blur the image along the streaks.
+    motion_blur: bool,
+    /// The seed of the RNG the streaks are placed with.
+    seed: u64,
+    /// The index of the stage in its builder, as stages may only differ by their seed.
+    id: usize,
+}
+
+impl ImageStage<Rgba<u8>> for RainStage {
+    fn execute(&self, img: &Image<Rgba<u8>>) -> (Image<Rgba<u8>>, Tags) {
+        const RAIN: [f32; 3] = [200., 205., 215.];
+        let tags = Tags(HashSet::from_iter([Label::Rain]));
+        let (width, height) = img.dimensions();
+        if width == 0 || height == 0 {
+            return (img.clone(), tags);
+        }
+
+        let (dx, dy) = self.angle.to_radians().sin_cos();
+        let length = self.length as f32;
+        // Streaks may start above or beside the image as long as they slant into it
+        let (min_x, max_x) = ((-dx * length).min(0.), width as f32 - (dx * length).min(0.));
+        let (min_y, max_y) = (-dy * length, height as f32);
+
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let count = (self.density * width as f32 * height as f32 / 100.).round() as usize;
+        let mut mask = vec![0f32; (width * height) as usize];
+        for _ in 0..count {
+            let x0 = rng.gen_range(min_x..=max_x);
+            let y0 = rng.gen_range(min_y..=max_y);
+            let opacity = rng.gen_range(0.25f32..=0.6);
+            for t in 0..self.length {
+                let (x, y) = (x0 + dx * t as f32, y0 + dy * t as f32);
+                if x < 0. || y < 0. || x >= width as f32 || y >= height as f32 {
+                    continue;
+                }
+                let m = &mut mask[(y as u32 * width + x as u32) as usize];
+                *m = m.max(opacity);
+            }
+        }
+
+        let mut out = img.clone();
+        for ((x, y, pixel), m) in out.enumerate_pixels_mut().zip(mask) {
+            if self.motion_blur {
+                // A short box filter along the streaks
+                let mut sum = [0.; 3];
+                for t in -2..=2 {
+                    let sample =
+                        sample_bilinear(img, x as f32 + dx * t as f32, y as f32 + dy * t as f32);
+                    for c in 0..3 {
+                        sum[c] += sample[c] as f32 / 5.;
+                    }
+                }
+                for c in 0..3 {
+                    pixel[c] = sum[c].round() as u8;
+                }
+            }
+            for c in 0..3 {
+                pixel[c] = (pixel[c] as f32 * (1. - m) + RAIN[c] * m).round() as u8;
+            }
+        }
+
+        (out, tags)
+    }
+
+    fn name(&self) -> Cow<str> {
+        format!("rain_{:.1}_{}px_{}", self.density, self.length, self.id).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({
+            "density": self.density,
+            "length": self.length,
+            "angle": self.angle,
+            "motion_blur": self.motion_blur,
+            "seed": self.seed,
+        }))
+    }
+}
+
+/// A builder that will create `samples` stages, each of which hazes the image over with a light gray
+/// fog of a random density strictly between `min_density` and `max_density` (as a fraction of full
+/// opacity). The fog is densest at the top of the image, where the distance usually is, and thins
+/// out toward the bottom, unless `patchy` is set, in which case it's spread in random low-frequency
+/// patches instead.
+pub struct FogBuilder {
+    /// The number of foggy variants to create.
+    pub samples: usize,
+    /// The exclusive lower bound of the density of the fog.
+    pub min_density: f32,
+    /// The exclusive upper bound of the density of the fog.
+    pub max_density: f32,
+    /// Whether the fog is spread in patches rather than by height.
+    pub patchy: bool,
+}
+
+impl<R: Rng> StageBuilder<Rgba<u8>, R> for FogBuilder {
+    fn variations(&self) -> usize {
+        self.samples
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !tags.0.contains(&Label::Fog)
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<Rgba<u8>> + Send + Sync>> {
+        let min = self.min_density.max(0.).min(1.);
+        let max = self.max_density.max(0.).min(1.);
+        // A density of 0 (or an empty interval) would leave the image as it is
+        if min >= max {
+            return vec![];
+        }
+
+        (0..self.samples)
+            .map(|_| {
+                let density = loop {
+                    let density = rng.gen_range(min..max);
+                    if density > min {
+                        break density;
+                    }
+                };
+                Box::new(FogStage {
+                    density,
+                    seed: if self.patchy { Some(rng.gen()) } else { None },
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        let name = if self.patchy {
+            "fog_{density}_patchy"
+        } else {
+            "fog_{density}"
+        };
+        vec![StageDescriptor::new(name, [Label::Fog])]
+    }
+}
+
+/// The actual stage which blends the image toward light gray by up to `density`. Without a `seed`,
+/// the blend fades linearly from `density` at the top row to a fifth of it at the bottom. With one,
+/// it's scaled by value noise (random values on a coarse grid, seeded by `seed` and smoothly
+/// interpolated) between half and all of `density`. Alpha is left alone.
+pub struct FogStage {
+    /// The densest the fog gets.
+    density: f32,
+    /// The seed of the noise the fog is spread with, if it's patchy.
+    seed: Option<u64>,
+}
+
+impl FogStage {
+    /// The number of noise cells along the longer side of the image.
+    const CELLS: u32 = 4;
+}
+
+impl ImageStage<Rgba<u8>> for FogStage {
+    fn execute(&self, img: &Image<Rgba<u8>>) -> (Image<Rgba<u8>>, Tags) {
+        const FOG: f32 = 215.;
+        let tags = Tags(HashSet::from_iter([Label::Fog]));
+        let (width, height) = img.dimensions();
+        if width == 0 || height == 0 {
+            return (img.clone(), tags);
+        }
+
+        let cell = width.max(height) as f32 / Self::CELLS as f32;
+        let (columns, rows) = (
+            (width as f32 / cell).ceil() as usize + 1,
+            (height as f32 / cell).ceil() as usize + 1,
+        );
+        let noise: Vec<f32> = match self.seed {
+            Some(seed) => {
+                let mut rng = StdRng::seed_from_u64(seed);
+                (0..columns * rows).map(|_| rng.gen()).collect()
+            }
+            None => vec![],
+        };
+        let density_at = |x: u32, y: u32| {
+            if noise.is_empty() {
+                let depth = 1. - y as f32 / (height - 1).max(1) as f32;
+                return self.density * (0.2 + 0.8 * depth);
+            }
+            let (fx, fy) = (x as f32 / cell, y as f32 / cell);
+            let (cx, cy) = (fx as usize, fy as usize);
+            let (tx, ty) = (
+                smoothstep(0., 1., fx.fract()),
+                smoothstep(0., 1., fy.fract()),
+            );
+            let at = |col: usize, row: usize| noise[row * columns + col];
+            let top = at(cx, cy) * (1. - tx) + at(cx + 1, cy) * tx;
+            let bottom = at(cx, cy + 1) * (1. - tx) + at(cx + 1, cy + 1) * tx;
+            self.density * (0.5 + 0.5 * (top * (1. - ty) + bottom * ty))
+        };
+
+        let mut out = img.clone();
+        for (x, y, pixel) in out.enumerate_pixels_mut() {
+            let d = density_at(x, y);
+            for c in 0..3 {
+                pixel[c] = (pixel[c] as f32 * (1. - d) + FOG * d).round() as u8;
+            }
+        }
+
+        (out, tags)
+    }
+
+    fn name(&self) -> Cow<str> {
+        match self.seed {
+            Some(_) => format!("fog_{:.2}_patchy", self.density).into(),
+            None => format!("fog_{:.2}", self.density).into(),
+        }
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({ "density": self.density, "seed": self.seed }))
+    }
+}
+
+/// A builder that will create `samples` stages, each of which adds a procedural lens flare to the
+/// image, as if the sun were shining into the lens from near the top of the frame.
+pub struct SunFlareBuilder {
+    /// The number of flared variants to create.
+    pub samples: usize,
+}
+
+impl<R: Rng> StageBuilder<Rgba<u8>, R> for SunFlareBuilder {
+    fn variations(&self) -> usize {
+        self.samples
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !tags.0.contains(&Label::SunFlare)
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<Rgba<u8>> + Send + Sync>> {
+        (0..self.samples)
+            .map(|_| {
+                Box::new(SunFlareStage { seed: rng.gen() }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new("flare_{hash}", [Label::SunFlare])]
+    }
+}
+
+/// A translucent disc of light in a [`SunFlareStage`], with its position and radius as fractions
+/// of the image's diagonal.
+///
+/// [`SunFlareStage`]: about:blank
+struct FlareDisc {
+    /// The center of the disc.
+    center: (f32, f32),
+    /// The radius of the disc.
+    radius: f32,
+    /// The opacity of the disc at its center.
+    opacity: f32,
+    /// The color of the disc.
+    color: [f32; 3],
+}
+
+/// The actual stage which adds a lens flare to the image. An RNG seeded by `seed` places the sun
+/// somewhere near the top of the frame (possibly just outside it), as a bright radial glow, with a
+/// few fainter ghost discs strung along the line from it through the center of the image. The light
+/// is screen-blended over the image, so it only ever brightens it. Alpha is left alone.
+pub struct SunFlareStage {
+    /// The seed of the RNG the flare is laid out with.
+    seed: u64,
+}
+
+impl SunFlareStage {
+    /// Lays out the sun followed by its ghosts, relative to an image `width` by `height` pixels in
+    /// size.
+    fn discs(&self, width: f32, height: f32) -> Vec<FlareDisc> {
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let diagonal = width.hypot(height);
+        let sun = (
+            rng.gen_range(-0.1..=1.1) * width,
+            rng.gen_range(-0.15..=0.3) * height,
+        );
+        let mut discs = vec![FlareDisc {
+            center: sun,
+            radius: rng.gen_range(0.15..=0.35) * diagonal,
+            opacity: rng.gen_range(0.6..=0.95),
+            color: [255., 245., 220.],
+        }];
+
+        let (dx, dy) = (width / 2. - sun.0, height / 2. - sun.1);
+        for _ in 0..rng.gen_range(3..=5) {
+            // Ghosts lie along the axis of the lens, on both sides of its center
+            let t = rng.gen_range(0.4..=1.8);
+            discs.push(FlareDisc {
+                center: (sun.0 + dx * t, sun.1 + dy * t),
+                radius: rng.gen_range(0.02..=0.08) * diagonal,
+                opacity: rng.gen_range(0.08..=0.25),
+                color: [
+                    rng.gen_range(180.0..=255.),
+                    rng.gen_range(180.0..=255.),
+                    rng.gen_range(180.0..=255.),
+                ],
+            });
+        }
+
+        discs
+    }
+}
+
+impl ImageStage<Rgba<u8>> for SunFlareStage {
+    fn execute(&self, img: &Image<Rgba<u8>>) -> (Image<Rgba<u8>>, Tags) {
+        let (width, height) = img.dimensions();
+        let discs = self.discs(width as f32, height as f32);
+
+        let mut out = img.clone();
+        for (x, y, pixel) in out.enumerate_pixels_mut() {
+            let (x, y) = (x as f32 + 0.5, y as f32 + 0.5);
+            let mut light = [0f32; 3];
+            for (i, disc) in discs.iter().enumerate() {
+                let d = (x - disc.center.0).hypot(y - disc.center.1) / disc.radius.max(1.);
+                let strength = if i == 0 {
+                    // The sun's glow falls off smoothly
+                    disc.opacity * (1. - d).max(0.).powi(2)
+                } else {
+                    // While the ghosts are discs with soft edges
+                    disc.opacity * (1. - smoothstep(0.8, 1., d))
+                };
+                for c in 0..3 {
+                    light[c] = 1. - (1. - light[c]) * (1. - strength * disc.color[c] / 255.);
+                }
+            }
+            for c in 0..3 {
+                pixel[c] = (255. - (255. - pixel[c] as f32) * (1. - light[c])).round() as u8;
+            }
+        }
+
+        (out, Tags(HashSet::from_iter([Label::SunFlare])))
+    }
+
+    fn name(&self) -> Cow<str> {
+        let hash = stable_hash(&self.seed.to_le_bytes()) as u32;
+        format!("flare_{:08x}", hash).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({ "seed": self.seed }))
+    }
+}
+
+/// A builder that will create `samples` stages, each of which remaps the levels of the image like
+/// the Levels tool of an image editor, raising the black point by up to `max_black_lift` and
+/// lowering the white point by up to `max_white_drop`, then stretching what's between them over
+/// the full range.
+pub struct LevelsBuilder {
+    /// The number of remapped variants to create.
+    samples: usize,
+    /// The most the black point is raised by.
+    max_black_lift: u8,
+    /// The most the white point is lowered by.
+    max_white_drop: u8,
+}
+
+impl LevelsBuilder {
+    /// Creates a builder sampling `samples` pairs of black and white points.
+    ///
+    /// # Panics
+    ///
+    /// If the black point could reach the white point, i.e. if `max_black_lift` and
+    /// `max_white_drop` add up to 255 or more.
+    pub fn new(samples: usize, max_black_lift: u8, max_white_drop: u8) -> Self {
+        assert!(
+            (max_black_lift as u16 + max_white_drop as u16) < 255,
+            "the black point lift {} and white point drop {} must leave a range between them",
+            max_black_lift,
+            max_white_drop
+        );
+
+        Self {
+            samples,
+            max_black_lift,
+            max_white_drop,
+        }
+    }
+}
+
+impl<P, R> StageBuilder<P, R> for LevelsBuilder
+where
+    P: Pixel<Subpixel = u8> + 'static,
+    R: Rng,
+{
+    fn variations(&self) -> usize {
+        // Leaving both points alone is a no-op
+        if self.max_black_lift == 0 && self.max_white_drop == 0 {
+            0
+        } else {
+            self.samples
+        }
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !tags.0.contains(&Label::Levels)
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<P> + Send + Sync>> {
+        if self.max_black_lift == 0 && self.max_white_drop == 0 {
+            return vec![];
+        }
+
+        (0..self.samples)
+            .map(|_| {
+                let (black, white) = loop {
+                    let black = rng.gen_range(0..=self.max_black_lift);
+                    let white = 255 - rng.gen_range(0..=self.max_white_drop);
+                    if black != 0 || white != 255 {
+                        break (black, white);
+                    }
+                };
+                let range = (white - black) as f32;
+                let mut lut = [0; 256];
+                for (level, out) in lut.iter_mut().enumerate() {
+                    let value = (level as f32 - black as f32) / range * 255.;
+                    *out = value.round().max(0.).min(255.) as u8;
+                }
+
+                Box::new(LevelsStage { black, white, lut }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new(
+            "levels_b{black}_w{white}",
+            [Label::Levels],
+        )]
+    }
+}
+
+/// The actual stage which maps every color channel through `lut`, which sends `black` and anything
+/// below it to 0, `white` and anything above it to 255, and stretches the levels in between
+/// linearly. Alpha is left alone.
+pub struct LevelsStage {
+    /// The level mapped to black.
+    black: u8,
+    /// The level mapped to white.
+    white: u8,
+    /// The value each level is mapped to.
+    lut: [u8; 256],
+}
+
+impl<P: Pixel<Subpixel = u8> + 'static> ImageStage<P> for LevelsStage {
+    fn execute(&self, img: &Image<P>) -> (Image<P>, Tags) {
+        let colors = color_channels::<P>();
+        let mut img = img.clone();
+        for pixel in img.pixels_mut() {
+            for c in pixel.channels_mut().iter_mut().take(colors) {
+                *c = self.lut[*c as usize];
+            }
+        }
+
+        (img, Tags(HashSet::from_iter([Label::Levels])))
+    }
+
+    fn name(&self) -> Cow<str> {
+        format!("levels_b{}_w{}", self.black, self.white).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({ "black": self.black, "white": self.white }))
+    }
+}
+
+/// A builder that will create `samples` stages, each of which gives the image a color cast by
+/// blending it toward a random color from `palette` (or a random fully saturated hue, if it's empty),
+/// by a random strength up to `max_strength`. Images already tinted, or toned (e.g. sepia), are
+/// skipped, as tinting them again is pointless.
+pub struct TintBuilder {
+    /// The number of tinted variants to create.
+    pub samples: usize,
+    /// The strongest the tint may be, as a fraction of the way to the color.
+    pub max_strength: f32,
+    /// The colors to choose between, or none for any hue.
+    pub palette: Vec<Rgb<u8>>,
+}
+
+impl<R: Rng> StageBuilder<Rgba<u8>, R> for TintBuilder {
+    fn variations(&self) -> usize {
+        if self.max_strength > 0. {
+            self.samples
+        } else {
+            0
+        }
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        ![Label::Tinted, Label::Sepia, Label::Toned]
+            .iter()
+            .any(|label| tags.0.contains(label))
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<Rgba<u8>> + Send + Sync>> {
+        // A strength of 0 would leave the image as it is
+        if self.max_strength <= 0. {
+            return vec![];
+        }
+
+        let max = self.max_strength.min(1.);
+        (0..self.samples)
+            .map(|_| {
+                let color = match self.palette.choose(rng) {
+                    Some(color) => color.0,
+                    None => hsv_to_rgb([rng.gen_range(0f32..360.), 1., 1.]),
+                };
+                Box::new(TintStage {
+                    color,
+                    strength: max - rng.gen_range(0. ..max),
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new(
+            "tint_{color}_{strength}",
+            [Label::Tinted],
+        )]
+    }
+}
+
+/// The actual stage which blends the red, green and blue channels of every pixel toward `color` by
+/// `strength`. Alpha is left alone.
+pub struct TintStage {
+    /// The color tinted toward.
+    color: [u8; 3],
+    /// The fraction of the way to the color each pixel is blended.
+    strength: f32,
+}
+
+impl ImageStage<Rgba<u8>> for TintStage {
+    fn execute(&self, img: &Image<Rgba<u8>>) -> (Image<Rgba<u8>>, Tags) {
+        let mut img = img.clone();
+        for pixel in img.pixels_mut() {
+            for (channel, &target) in pixel.0.iter_mut().zip(&self.color) {
+                *channel = (*channel as f32 * (1. - self.strength) + target as f32 * self.strength)
+                    .round() as u8;
+            }
+        }
+
+        (img, Tags(HashSet::from_iter([Label::Tinted])))
+    }
+
+    fn name(&self) -> Cow<str> {
+        let [r, g, b] = self.color;
+        format!("tint_{:02x}{:02x}{:02x}_{:.2}", r, g, b, self.strength).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({ "color": self.color, "strength": self.strength }))
+    }
+}
+
+/// A builder that will create `samples` stages, each of which damages the image like a scan of old
+/// film, with a random number of thin scratches from `scratch_count_range` and of dust specks from
+/// `dust_count_range`.
+pub struct ScratchDustBuilder {
+    /// The number of damaged variants to create.
+    pub samples: usize,
+    /// The range of the number of scratches.
+    pub scratch_count_range: RangeInclusive<u32>,
+    /// The range of the number of dust specks.
+    pub dust_count_range: RangeInclusive<u32>,
+}
+
+impl<R: Rng> StageBuilder<Rgba<u8>, R> for ScratchDustBuilder {
+    fn variations(&self) -> usize {
+        self.samples
+    }
+
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !tags.0.contains(&Label::FilmDamage)
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<Rgba<u8>> + Send + Sync>> {
+        (0..self.samples)
+            .map(|id| {
+                Box::new(ScratchDustStage {
+                    scratches: rng.gen_range(self.scratch_count_range.clone()),
+                    dust: rng.gen_range(self.dust_count_range.clone()),
+                    seed: rng.gen(),
+                    id,
+                }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new(
+            "damage_{scratches}s_{dust}d_{id}",
+            [Label::FilmDamage],
+        )]
+    }
+}
+
+/// The actual stage which draws `scratches` scratches and `dust` dust specks over the image, all laid
+/// out by an RNG seeded by `seed`. Scratches are thin antialiased lines running roughly top to bottom
+/// (wavering slightly along the way), specks are small discs, and each is either bright or dark and
+/// partly translucent. Alpha is left alone.
+pub struct ScratchDustStage {
+    /// The number of scratches.
+    scratches: u32,
+    /// The number of dust specks.
+    dust: u32,
+    /// The seed of the RNG the damage is laid out with.
+    seed: u64,
+    /// The index of the stage in its builder, as stages may only differ by their seed.
+    id: usize,
+}
+
+impl ScratchDustStage {
+    /// The height of each straight piece of a scratch, in pixels.
+    const SEGMENT: i32 = 12;
+}
+
+impl ImageStage<Rgba<u8>> for ScratchDustStage {
+    fn execute(&self, img: &Image<Rgba<u8>>) -> (Image<Rgba<u8>>, Tags) {
+        let tags = Tags(HashSet::from_iter([Label::FilmDamage]));
+        let (width, height) = img.dimensions();
+        if width == 0 || height == 0 {
+            return (img.clone(), tags);
+        }
+
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let shade = |rng: &mut StdRng| {
+            let level = if rng.gen() {
+                rng.gen_range(220..=255)
+            } else {
+                rng.gen_range(0..=40)
+            };
+            (
+                Rgba([level, level, level, 255]),
+                rng.gen_range(0.3f32..=0.8),
+            )
+        };
+
+        let mut out = img.clone();
+        for _ in 0..self.scratches {
+            let (color, opacity) = shade(&mut rng);
+            let length = (height as f32 * rng.gen_range(0.3..=1.)) as i32;
+            let top = rng.gen_range(0..=height as i32 - length);
+            let mut x = rng.gen_range(0. ..width as f32);
+            let drift = rng.gen_range(-0.1f32..=0.1);
+            for y in (top..top + length).step_by(Self::SEGMENT as usize) {
+                let next = x + (drift + rng.gen_range(-0.15..=0.15)) * Self::SEGMENT as f32;
+                let end = (y + Self::SEGMENT).min(top + length);
+                draw_antialiased_line_segment_mut(
+                    &mut out,
+                    (x.round() as i32, y),
+                    (next.round() as i32, end),
+                    color,
+                    |line, original, weight| {
+                        let mut blended = interpolate(line, original, weight * opacity);
+                        blended[3] = original[3];
+                        blended
+                    },
+                );
+                x = next;
+            }
+        }
+
+        for _ in 0..self.dust {
+            let (color, opacity) = shade(&mut rng);
+            let (cx, cy) = (
+                rng.gen_range(0..width) as i64,
+                rng.gen_range(0..height) as i64,
+            );
+            let radius = rng.gen_range(0..=2i64);
+            for y in (cy - radius).max(0)..=(cy + radius).min(height as i64 - 1) {
+                for x in (cx - radius).max(0)..=(cx + radius).min(width as i64 - 1) {
+                    if (x - cx).pow(2) + (y - cy).pow(2) > radius * radius {
+                        continue;
+                    }
+                    let pixel = out.get_pixel_mut(x as u32, y as u32);
+                    for c in 0..3 {
+                        pixel[c] = (pixel[c] as f32 * (1. - opacity) + color[c] as f32 * opacity)
+                            .round() as u8;
+                    }
+                }
+            }
+        }
+
+        (out, tags)
+    }
+
+    fn name(&self) -> Cow<str> {
+        format!("damage_{}s_{}d_{}", self.scratches, self.dust, self.id).into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({
+            "scratches": self.scratches,
+            "dust": self.dust,
+            "seed": self.seed,
+        }))
+    }
+}
+
+/// A builder that will create `samples` stages, each of which grows the canvas on all four sides by
+/// a random fraction of the image's width (for the left and right) or height (for the top and
+/// bottom) between `min_pad_frac` and `max_pad_frac`, filling it with the image mirrored at its
+/// edges. This is handy before heavy rotations or warps, so that no solid fill shows in the result.
+/// With `symmetric` set, all four sides share the same fraction, otherwise each gets its own.
+pub struct ReflectPadBuilder {
+    /// The number of padded variants to create.
+    pub samples: usize,
+    /// The smallest fraction a side is padded by.
+    pub min_pad_frac: f32,
+    /// The largest fraction a side is padded by.
+    pub max_pad_frac: f32,
+    /// Whether all four sides are padded by the same fraction.
+    pub symmetric: bool,
+}
+
+impl<P: Pixel + 'static, R: Rng> StageBuilder<P, R> for ReflectPadBuilder {
+    fn should_execute(&self, tags: &Tags) -> bool {
+        !tags.0.contains(&Label::Padded)
+    }
+
+    fn variations(&self) -> usize {
+        self.samples
+    }
+
+    fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<P> + Send + Sync>> {
+        let min = self.min_pad_frac.max(0.);
+        let max = self.max_pad_frac.max(min);
+        (0..self.samples)
+            .map(|_| {
+                let pads = if self.symmetric {
+                    [rng.gen_range(min..=max); 4]
+                } else {
+                    [
+                        rng.gen_range(min..=max),
+                        rng.gen_range(min..=max),
+                        rng.gen_range(min..=max),
+                        rng.gen_range(min..=max),
+                    ]
+                };
+                Box::new(ReflectPadStage { pads }) as Box<dyn ImageStage<_> + Send + Sync>
+            })
+            .collect()
+    }
+
+    fn describe(&self) -> Vec<StageDescriptor> {
+        vec![StageDescriptor::new(
+            "rpad_{left}_{top}_{right}_{bottom}pct",
+            [Label::Padded],
+        )]
+    }
+}
+
+/// The actual stage which pads the image with its own reflection, by `pads` (the left, top, right
+/// and bottom fractions, of the width for the sides and of the height for the top and bottom,
+/// rounded to whole pixels). Unlike most stages, this grows the image's dimensions. Padding wider
+/// than the image reflects back and forth as often as needed. Images whose padding rounds to
+/// nothing are skipped.
+pub struct ReflectPadStage {
+    /// The fractions the left, top, right and bottom sides are padded by.
+    pads: [f32; 4],
+}
+
+impl ReflectPadStage {
+    /// The left, top, right and bottom padding of a `width` by `height` image in pixels, or `None`
+    /// if there's nothing to pad.
+    fn pixel_pads(&self, width: u32, height: u32) -> Option<[u32; 4]> {
+        if width == 0 || height == 0 {
+            return None;
+        }
+
+        let [left, top, right, bottom] = self.pads;
+        let pads = [
+            (width as f32 * left).round() as u32,
+            (height as f32 * top).round() as u32,
+            (width as f32 * right).round() as u32,
+            (height as f32 * bottom).round() as u32,
+        ];
+        if pads.iter().all(|&pad| pad == 0) {
+            None
+        } else {
+            Some(pads)
+        }
+    }
+}
+
+impl<P: Pixel + 'static> ImageStage<P> for ReflectPadStage {
+    fn execute(&self, img: &Image<P>) -> (Image<P>, Tags) {
+        let (width, height) = img.dimensions();
+        let [left, top, right, bottom] = match self.pixel_pads(width, height) {
+            Some(pads) => pads,
+            None => return (img.clone(), Tags::default()),
+        };
+
+        let padded = Image::from_fn(width + left + right, height + top + bottom, |x, y| {
+            Fill::<P>::Reflect.pixel(img, x as i64 - left as i64, y as i64 - top as i64)
+        });
+
+        (padded, Tags(HashSet::from_iter([Label::Padded])))
+    }
+
+    fn execute_many(&self, img: &Image<P>) -> Vec<(Image<P>, Tags)> {
+        match self.pixel_pads(img.width(), img.height()) {
+            Some(_) => vec![self.execute(img)],
+            None => vec![],
+        }
+    }
+
+    fn name(&self) -> Cow<str> {
+        let [left, top, right, bottom] = self.pads;
+        format!(
+            "rpad_{:.0}_{:.0}_{:.0}_{:.0}pct",
+            left * 100.,
+            top * 100.,
+            right * 100.,
+            bottom * 100.
+        )
+        .into()
+    }
+
+    fn params(&self) -> Option<Value> {
+        Some(json!({ "pads": self.pads }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use image::Rgba;
+    use imageproc::definitions::Image;
+
+    use crate::labels::Label;
+    use imageproc::geometric_transformations::{rotate_about_center, Interpolation};
+
+    use crate::stages::{
+        AnaglyphOffsetStage, AspectDistortStage, AutoCropStage, DeskewStage, PatchShuffleStage,
+        PoissonNoiseStage, ReflectPadStage,
+    };
+    use crate::traits::ImageStage;
+
+    #[test]
+    fn anaglyph_translates_red_and_blue() {
+        let img = Image::from_fn(8, 2, |x, _| {
+            Rgba([x as u8 * 10, x as u8, x as u8 * 20, 200])
+        });
+        let stage = AnaglyphOffsetStage {
+            offset: 3,
+            swapped: false,
+        };
+        let (out, tags) = stage.execute(&img);
+
+        for (x, _, pixel) in out.enumerate_pixels() {
+            // Red is read from the right (moving it left), blue from the left, clamped at the edges
+            assert_eq!(pixel[0], (x + 3).min(7) as u8 * 10);
+            assert_eq!(pixel[1], x as u8);
+            assert_eq!(pixel[2], x.saturating_sub(3) as u8 * 20);
+            assert_eq!(pixel[3], 200);
+        }
+        assert!(tags.0.contains(&Label::ChannelOffset));
+    }
+
+    #[test]
+    fn aspect_distort_keeps_dimensions() {
+        let img = Image::from_pixel(40, 30, Rgba([255, 255, 255, 255]));
+        let black = Rgba([0, 0, 0, 255]);
+
+        // Stretched horizontally, the padded image fits the width and gets bars above and below
+        let stage = AspectDistortStage {
+            x_scale: 1.2,
+            y_scale: 0.9,
+            crop: false,
+            fill: black,
+        };
+        let (out, tags) = stage.execute(&img);
+        assert_eq!(out.dimensions(), (40, 30));
+        assert_eq!(*out.get_pixel(20, 0), black);
+        assert_eq!(*out.get_pixel(20, 29), black);
+        assert_eq!(*out.get_pixel(0, 15), Rgba([255, 255, 255, 255]));
+        assert!(tags.0.contains(&Label::AspectDistorted));
+
+        // While the cropped image covers the whole canvas
+        let (out, _) = AspectDistortStage {
+            crop: true,
+            ..stage
+        }
+        .execute(&img);
+        assert_eq!(out.dimensions(), (40, 30));
+        assert!(out.pixels().all(|px| *px == Rgba([255, 255, 255, 255])));
+    }
+
+    #[test]
+    fn autocrop_trims_uniform_margins() {
+        let stage = AutoCropStage {
+            tolerance: 10.,
+            min_remaining_frac: 0.,
+        };
+        let mut img = Image::from_pixel(6, 5, Rgba([250, 250, 250, 255]));
+        img.put_pixel(0, 0, Rgba([255, 255, 255, 255]));
+        img.put_pixel(2, 1, Rgba([0, 0, 0, 255]));
+        img.put_pixel(3, 2, Rgba([0, 0, 0, 255]));
+
+        let outputs = stage.execute_many(&img);
+        assert_eq!(outputs.len(), 1);
+        let (cropped, tags) = &outputs[0];
+        assert_eq!(cropped.dimensions(), (2, 2));
+        assert_eq!(cropped.get_pixel(0, 0)[0], 0);
+        assert!(tags.0.contains(&Label::AutoCropped));
+
+        // Nothing to crop, so nothing to output
+        let blank = Image::from_pixel(6, 5, Rgba([250, 250, 250, 255]));
+        assert!(stage.execute_many(&blank).is_empty());
+    }
+
+    #[test]
+    fn deskew_estimates_rotation() {
+        let mut img: Image<Rgba<u8>> = Image::from_pixel(200, 200, Rgba([255, 255, 255, 255]));
+        for y in (20..180).step_by(16) {
+            for x in 20..180 {
+                img.put_pixel(x, y, Rgba([0, 0, 0, 255]));
+                img.put_pixel(x, y + 1, Rgba([0, 0, 0, 255]));
+            }
+        }
+        let stage = DeskewStage::new(1., Rgba([255, 255, 255, 255]));
+        assert!(stage.estimate(&img).abs() < 0.2);
+        assert!(stage.execute_many(&img).is_empty());
+
+        let skewed = rotate_about_center(
+            &img,
+            7f32.to_radians(),
+            Interpolation::Bilinear,
+            Rgba([255, 255, 255, 255]),
+        );
+        let degrees = stage.estimate(&skewed);
+        assert!((degrees - 7.).abs() < 0.3, "estimated {}", degrees);
+        assert_eq!(stage.name_for(&skewed), format!("deskew_{:.1}deg", degrees));
+        let run = stage.run(&skewed);
+        assert_eq!(run.name, format!("deskew_{:.1}deg", degrees));
+        assert_eq!(run.params.unwrap()["degrees"], degrees);
+        assert!(run.outputs[0].1 .0.contains(&Label::OffAxis));
+    }
+
+    #[test]
+    fn patch_shuffle_keeps_every_pixel() {
+        // Every pixel is unique, so any dropped or duplicated one changes the sorted pixels
+        let img = Image::from_fn(11, 7, |x, y| Rgba([x as u8, y as u8, 0, 255]));
+        let mut expected: Vec<_> = img.pixels().map(|px| px.0).collect();
+        expected.sort_unstable();
+
+        for grid in 2..=4 {
+            let stage = PatchShuffleStage { grid, seed: 3 };
+            let (out, tags) = stage.execute(&img);
+            let mut pixels: Vec<_> = out.pixels().map(|px| px.0).collect();
+            pixels.sort_unstable();
+
+            assert_eq!(pixels, expected, "grid {}", grid);
+            assert_ne!(out, img, "grid {}", grid);
+            assert!(tags.0.contains(&Label::PatchShuffled));
+        }
+        assert!(PatchShuffleStage { grid: 4, seed: 3 }
+            .execute_many(&Image::<Rgba<u8>>::new(1, 5))
+            .is_empty());
+    }
+
+    #[test]
+    fn reflect_pad_mirrors_edges() {
+        let img = Image::from_fn(4, 1, |x, _| Rgba([x as u8, 0, 0, 255]));
+        let stage = ReflectPadStage {
+            pads: [1.5, 0., 0.5, 0.],
+        };
+        let (padded, tags) = stage.execute(&img);
+
+        // 6 pixels on the left reflect past the far edge and back, 2 on the right
+        let row: Vec<u8> = padded.pixels().map(|p| p[0]).collect();
+        assert_eq!(row, vec![0, 1, 2, 3, 2, 1, 0, 1, 2, 3, 2, 1]);
+        assert!(tags.0.contains(&Label::Padded));
+    }
+
+    #[test]
+    fn shot_noise_tracks_mean() {
+        let img = Image::from_pixel(64, 64, Rgba([128, 128, 128, 255]));
+        for &scale in &[0.25, 1., 4.] {
+            let stage = PoissonNoiseStage { scale, seed: 7 };
+            let (noisy, tags) = stage.execute(&img);
+
+            let count = (noisy.width() * noisy.height()) as f64;
+            let mean = noisy.pixels().map(|p| p[1] as f64).sum::<f64>() / count;
+            let variance = noisy
+                .pixels()
+                .map(|p| (p[1] as f64 - mean).powi(2))
+                .sum::<f64>()
+                / count;
+            // The mean is preserved, while the variance grows with the scale (128 * scale)
+            assert!((mean - 128.).abs() < 1., "mean {} at scale {}", mean, scale);
+            assert!(
+                (variance / (128. * scale as f64) - 1.).abs() < 0.2,
+                "variance {} at scale {}",
+                variance,
+                scale
+            );
+            assert!(noisy.pixels().all(|p| p[3] == 255));
+            assert!(tags.0.contains(&Label::Noisy));
+        }
     }
 }
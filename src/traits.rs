@@ -1,11 +1,12 @@
 //! Common traits used throughout the crate.
 
-use std::borrow::Cow;
+use std::{borrow::Cow, io, path::Path};
 
-use crate::Tags;
+use crate::{labels::Label, Tags};
 use image::Pixel;
 use imageproc::definitions::Image;
 use rand::Rng;
+use serde_json::Value;
 
 /// Something that can output an `ImageStage`, when an executor is build, you pass a collection
 /// of `StageBuilders` which will then pass a per-image RNG to `build_stage`, used to generate
@@ -28,6 +29,54 @@ pub(crate) trait StageBuilder<P: Pixel, R: Rng> {
     /// Builds out the `ImageStage` with the given `rng`, yielding a concrete transformer
     /// for an image.
     fn build_stage(&self, rng: &mut R) -> Vec<Box<dyn ImageStage<P> + Send + Sync>>;
+
+    /// Describes every stage this builder can output without needing an image or RNG, e.g. for
+    /// generating documentation or checking a configuration. Stages whose names depend on sampled
+    /// parameters are described by a pattern with the parameters in braces (`"blur_{sigma}"`),
+    /// deterministic stages by their concrete names.
+    fn describe(&self) -> Vec<StageDescriptor>;
+
+    /// The group of mutually exclusive builders this builder belongs to, if any. The executor
+    /// skips every combination applying stages from two builders in the same group, for pairs of
+    /// stages which are pointless together, such as blurring and sharpening (which are both in
+    /// the `"sharpness"` group). Defaults to no group, i.e. conflicting with nothing.
+    fn conflict_group(&self) -> Option<Cow<str>> {
+        None
+    }
+
+    /// A human readable name for the builder, used in diagnostics. Defaults to the name of the
+    /// implementing type without its path or generics (e.g. `"BlurBuilder"`).
+    fn name(&self) -> Cow<str> {
+        let name = std::any::type_name::<Self>();
+        let name = name.split('<').next().unwrap_or(name);
+        name.rsplit("::").next().unwrap_or(name).into()
+    }
+}
+
+/// A description of a stage a `StageBuilder` can output, see `StageBuilder::describe`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub(crate) struct StageDescriptor {
+    /// The name of the stage, or a pattern of it with its sampled parameters in braces.
+    pub name: String,
+    /// The labels the stage adds to an image's tags.
+    pub labels: Vec<Label>,
+}
+
+impl StageDescriptor {
+    /// Creates a descriptor of a stage named `name` which adds `labels`. Any part of `name` in
+    /// braces stands for a parameter sampled when the stage is built, rather than literal text, so
+    /// e.g. `"blur_{sigma}"` describes stages named like `blur_7.3`, while a name without braces
+    /// is the stage's exact name.
+    pub fn new<S, L>(name: S, labels: L) -> Self
+    where
+        S: Into<String>,
+        L: IntoIterator<Item = Label>,
+    {
+        Self {
+            name: name.into(),
+            labels: labels.into_iter().collect(),
+        }
+    }
 }
 
 /// A concrete image stage which will transform an input image in a consistent way every time.
@@ -38,8 +87,72 @@ pub(crate) trait ImageStage<P: Pixel> {
     /// a set of new Tags to apply to the image.
     fn execute(&self, img: &Image<P>) -> (Image<P>, Tags);
 
+    /// Executes the stage, yielding any number of output images along with their new Tags, for
+    /// stages which naturally fan out (e.g. splitting an image into tiles). Each output then goes
+    /// through the rest of the pipeline on its own, and when there's more than one, its index is
+    /// appended to the stage's name in the output's filename. Stages which would leave some images
+    /// untouched can yield no outputs at all for them, dropping the rest of the pipeline rather than
+    /// writing out duplicates.
+    ///
+    /// Defaults to the single output of [`execute`].
+    ///
+    /// [`execute`]: about:blank
+    fn execute_many(&self, img: &Image<P>) -> Vec<(Image<P>, Tags)> {
+        vec![self.execute(img)]
+    }
+
     /// The name that should be appended to the image's filename, generally a shortened name
     /// of the stage and, if applicable, the degree of the transformation (e.g. `"rot_29.1_deg"`
     /// for a rotation of 29.1 degrees).
     fn name(&self) -> Cow<str>;
+
+    /// The name to append for the output of executing the stage on `img`, for the rare stages whose
+    /// name depends on what they find in the image rather than only their own parameters (such as
+    /// an estimated angle). Defaults to [`name`].
+    ///
+    /// [`name`]: about:blank
+    fn name_for(&self, _img: &Image<P>) -> Cow<str> {
+        self.name()
+    }
+
+    /// The exact parameters the stage applies (e.g. `{"sigma": 7.3125}` for a blur), recorded in
+    /// the provenance of each output so augmentations can be exactly reconstructed, unlike from
+    /// the lossy `name`. Defaults to `None`, for stages without any parameters.
+    fn params(&self) -> Option<Value> {
+        None
+    }
+
+    /// Executes the stage on `img` as the executor does, yielding the outputs along with the name
+    /// and parameters to record for them. Stages whose name or parameters depend on what they find
+    /// in the image (such as an estimated angle) can override this to examine it only once.
+    /// Defaults to [`name_for`], [`params`] and [`execute_many`].
+    ///
+    /// [`name_for`]: about:blank
+    /// [`params`]: about:blank
+    /// [`execute_many`]: about:blank
+    fn run(&self, img: &Image<P>) -> StageRun<'_, P> {
+        StageRun {
+            name: self.name_for(img),
+            params: self.params(),
+            outputs: self.execute_many(img),
+        }
+    }
+}
+
+/// The result of running an `ImageStage` on an image, see `ImageStage::run`.
+pub(crate) struct StageRun<'a, P: Pixel> {
+    /// The name to append to the outputs' filenames.
+    pub name: Cow<'a, str>,
+    /// The exact parameters applied to the image.
+    pub params: Option<Value>,
+    /// The output images along with their new Tags.
+    pub outputs: Vec<(Image<P>, Tags)>,
+}
+
+/// Somewhere the encoded outputs of an executor are written to, in place of saving them as loose
+/// files. Outputs are written from many threads at once, so implementations must synchronize any
+/// shared writer themselves.
+pub trait OutputSink: Send + Sync {
+    /// Writes the encoded image `data` as `path`, which is relative to the output directory.
+    fn write(&self, path: &Path, data: &[u8]) -> io::Result<()>;
 }
@@ -1,7 +1,10 @@
 //! Helper utilities
-use std::ops::AddAssign;
+use std::{borrow::Cow, fs::File, io::BufReader, ops::AddAssign, path::Path};
 
-use num::Integer;
+use conv::ValueInto;
+use image::{DynamicImage, Pixel};
+use imageproc::definitions::{Clamp, Image};
+use num::{Integer, Zero};
 
 /// Converts an `Iterator` over any integral primitive type into `SetVariationIterator`,
 /// which will enumerate every variation of the numbers in the list. This is blanket implemented
@@ -97,9 +100,301 @@ where
     }
 }
 
+/// Reads the EXIF orientation flag (a value between `1` and `8`) of the image at `path`, yielding
+/// `None` if the file has no EXIF data or it can't be read.
+pub fn exif_orientation<P: AsRef<Path>>(path: P) -> Option<u32> {
+    let file = File::open(path).ok()?;
+    let exif = exif::Reader::new()
+        .read_from_container(&mut BufReader::new(file))
+        .ok()?;
+
+    exif.get_field(exif::Tag::Orientation, exif::In::PRIMARY)?
+        .value
+        .get_uint(0)
+}
+
+/// Applies the EXIF `orientation` to `img`, yielding an upright image. Unknown orientations (and `1`,
+/// which is already upright) leave the image untouched.
+pub fn apply_orientation(img: DynamicImage, orientation: u32) -> DynamicImage {
+    match orientation {
+        2 => img.fliph(),
+        3 => img.rotate180(),
+        4 => img.flipv(),
+        5 => img.rotate90().fliph(),
+        6 => img.rotate90(),
+        7 => img.rotate270().fliph(),
+        8 => img.rotate270(),
+        _ => img,
+    }
+}
+
+/// Converts an 8-bit RGB color to HSV, yielding its hue in degrees (between `0` and `360`), and its
+/// saturation and value (both between `0` and `1`).
+pub fn rgb_to_hsv(rgb: [u8; 3]) -> [f32; 3] {
+    let [r, g, b] = [
+        rgb[0] as f32 / 255.,
+        rgb[1] as f32 / 255.,
+        rgb[2] as f32 / 255.,
+    ];
+    let max = r.max(g).max(b);
+    let delta = max - r.min(g).min(b);
+
+    let hue = if delta == 0. {
+        0.
+    } else if max == r {
+        60. * ((g - b) / delta).rem_euclid(6.)
+    } else if max == g {
+        60. * ((b - r) / delta + 2.)
+    } else {
+        60. * ((r - g) / delta + 4.)
+    };
+    let saturation = if max == 0. { 0. } else { delta / max };
+
+    [hue, saturation, max]
+}
+
+/// Converts an HSV color back to 8-bit RGB, the inverse of [`rgb_to_hsv`]. The hue wraps around
+/// (so `-30` and `330` are the same hue), while the saturation and value are clamped between `0`
+/// and `1`.
+///
+/// [`rgb_to_hsv`]: about:blank
+pub fn hsv_to_rgb(hsv: [f32; 3]) -> [u8; 3] {
+    let hue = hsv[0].rem_euclid(360.) / 60.;
+    let (saturation, value) = (hsv[1].max(0.).min(1.), hsv[2].max(0.).min(1.));
+
+    let chroma = value * saturation;
+    let x = chroma * (1. - (hue.rem_euclid(2.) - 1.).abs());
+    let (r, g, b) = match hue as u32 {
+        0 => (chroma, x, 0.),
+        1 => (x, chroma, 0.),
+        2 => (0., chroma, x),
+        3 => (0., x, chroma),
+        4 => (x, 0., chroma),
+        _ => (chroma, 0., x),
+    };
+    let min = value - chroma;
+
+    [r, g, b].map(|channel| ((channel + min) * 255.).round() as u8)
+}
+
+/// Scales the saturation of an 8-bit RGB color by `factor` in HSV space, so `0` yields a gray of
+/// the same value, `1` leaves the color untouched, and anything above `1` oversaturates it (up to
+/// fully saturated).
+pub fn scale_saturation(rgb: [u8; 3], factor: f32) -> [u8; 3] {
+    let [hue, saturation, value] = rgb_to_hsv(rgb);
+    hsv_to_rgb([hue, saturation * factor, value])
+}
+
+/// Converts an sRGB encoded channel between `0` and `1` into linear light.
+pub fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Converts a channel in linear light between `0` and `1` back into sRGB, the inverse of
+/// [`srgb_to_linear`].
+///
+/// [`srgb_to_linear`]: about:blank
+pub fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// A 64 bit FNV-1a hash of `bytes`. Unlike the standard library's hashers this is guaranteed to be
+/// stable across runs, platforms and compiler versions, so it's safe to use in output filenames.
+pub fn stable_hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// Shortens `name` to at most `max_len` bytes (if it's longer) so it fits within filesystem limits,
+/// by cutting it short and appending a hash of the full name (e.g. `"foo_blur_1.5~89ab..."`), so
+/// distinct names stay distinct and the same name is always shortened the same way. Yields `None`
+/// if `name` is too long while `max_len` can't even fit the 17 byte hash.
+pub fn truncate_with_hash(name: &str, max_len: usize) -> Option<Cow<str>> {
+    if name.len() <= max_len {
+        return Some(name.into());
+    }
+
+    let hash = format!("~{:016x}", stable_hash(name.as_bytes()));
+    if max_len < hash.len() {
+        return None;
+    }
+    let mut end = max_len - hash.len();
+    while !name.is_char_boundary(end) {
+        end -= 1;
+    }
+    Some(format!("{}{}", &name[..end], hash).into())
+}
+
+/// The glyph for `c` in a tiny 3x5 pixel font, where each row is 3 bits (most significant bit on
+/// the left) and the first row is in the most significant bits. Only digits, letters (case
+/// insensitive), and `_.-+` are supported, anything else is drawn as a blank space.
+fn glyph(c: char) -> u16 {
+    match c.to_ascii_lowercase() {
+        '0' => 0b111_101_101_101_111,
+        '1' => 0b010_110_010_010_111,
+        '2' => 0b111_001_111_100_111,
+        '3' => 0b111_001_111_001_111,
+        '4' => 0b101_101_111_001_001,
+        '5' => 0b111_100_111_001_111,
+        '6' => 0b111_100_111_101_111,
+        '7' => 0b111_001_001_001_001,
+        '8' => 0b111_101_111_101_111,
+        '9' => 0b111_101_111_001_111,
+        'a' => 0b010_101_111_101_101,
+        'b' => 0b110_101_110_101_110,
+        'c' => 0b011_100_100_100_011,
+        'd' => 0b110_101_101_101_110,
+        'e' => 0b111_100_110_100_111,
+        'f' => 0b111_100_110_100_100,
+        'g' => 0b011_100_101_101_011,
+        'h' => 0b101_101_111_101_101,
+        'i' => 0b111_010_010_010_111,
+        'j' => 0b001_001_001_101_010,
+        'k' => 0b101_101_110_101_101,
+        'l' => 0b100_100_100_100_111,
+        'm' => 0b101_111_111_101_101,
+        'n' => 0b110_101_101_101_101,
+        'o' => 0b010_101_101_101_010,
+        'p' => 0b110_101_110_100_100,
+        'q' => 0b010_101_101_110_011,
+        'r' => 0b110_101_110_101_101,
+        's' => 0b011_100_010_001_110,
+        't' => 0b111_010_010_010_010,
+        'u' => 0b101_101_101_101_111,
+        'v' => 0b101_101_101_101_010,
+        'w' => 0b101_101_111_111_101,
+        'x' => 0b101_101_010_101_101,
+        'y' => 0b101_101_010_010_010,
+        'z' => 0b111_001_010_100_111,
+        '_' => 0b000_000_000_000_111,
+        '.' => 0b000_000_000_000_010,
+        '-' => 0b000_000_111_000_000,
+        '+' => 0b000_010_111_010_000,
+        _ => 0,
+    }
+}
+
+/// Draws `text` onto `img` in a tiny built-in bitmap font, with its top-left corner at `x` and `y`.
+/// Each pixel of the font is drawn as a `scale` by `scale` square, and anything falling outside of
+/// the image is clipped.
+pub fn draw_label<P: Pixel + 'static>(
+    img: &mut Image<P>,
+    text: &str,
+    x: u32,
+    y: u32,
+    scale: u32,
+    color: P,
+) {
+    let (width, height) = img.dimensions();
+    for (idx, c) in text.chars().enumerate() {
+        let glyph = glyph(c);
+        let left = x + idx as u32 * 4 * scale;
+        for bit in (0..15).filter(|bit| glyph & (1 << (14 - bit)) != 0) {
+            let (gx, gy) = (left + bit % 3 * scale, y + bit / 3 * scale);
+            for py in (gy..gy + scale).take_while(|py| *py < height) {
+                for px in (gx..gx + scale).take_while(|px| *px < width) {
+                    img.put_pixel(px, py, color);
+                }
+            }
+        }
+    }
+}
+
+/// The number of color channels of `P`, i.e. every channel but alpha, which is always the last
+/// channel of pixels having one.
+pub fn color_channels<P: Pixel>() -> usize {
+    P::CHANNEL_COUNT as usize - P::COLOR_MODEL.ends_with('A') as usize
+}
+
+/// A pixel of type `P` with every channel zeroed, i.e. black (and fully transparent for pixels with
+/// an alpha channel), for any number of channels.
+pub fn zero_pixel<P: Pixel>() -> P {
+    let channels = vec![Zero::zero(); P::CHANNEL_COUNT as usize];
+    *P::from_slice(&channels)
+}
+
+/// Linearly interpolates between the pixels `a` and `b` channel by channel, yielding `a` when `t`
+/// is `0` and `b` when `t` is `1`.
+pub fn lerp_pixel<P>(a: &P, b: &P, t: f32) -> P
+where
+    P: Pixel,
+    P::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    a.map2(b, |a, b| {
+        let (a, b): (f32, f32) = (a.value_into().unwrap(), b.value_into().unwrap());
+        P::Subpixel::clamp(a + (b - a) * t)
+    })
+}
+
+/// Hermite interpolation between `0` and `1` as `x` goes from `edge0` to `edge1`, yielding a smooth
+/// falloff for blending masks.
+pub fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).max(0.).min(1.);
+    t * t * (3. - 2. * t)
+}
+
+/// Computes the dimensions of the smallest canvas holding an image `width` by `height` pixels in
+/// size after rotating it by `radians`. Sizes are rounded up to whole pixels, ignoring the slight
+/// floating point error which would otherwise grow e.g. a 90 degree rotation by a pixel.
+pub fn rotated_bounds(width: u32, height: u32, radians: f64) -> (u32, u32) {
+    let (sin, cos) = (radians.sin().abs(), radians.cos().abs());
+    let (width, height) = (width as f64, height as f64);
+    let fit = |len: f64| (len - 1e-6).ceil().max(0.) as u32;
+    (
+        fit(width * cos + height * sin),
+        fit(width * sin + height * cos),
+    )
+}
+
+/// Samples `img` at the (possibly fractional) coordinates `x` and `y` with bilinear interpolation,
+/// coordinates outside of the image are clamped to its edges. `img` must not be empty.
+pub fn sample_bilinear<P>(img: &Image<P>, x: f32, y: f32) -> P
+where
+    P: Pixel + 'static,
+    P::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    let (width, height) = img.dimensions();
+    let x = x.max(0.).min((width - 1) as f32);
+    let y = y.max(0.).min((height - 1) as f32);
+    let (x0, y0) = (x.floor() as u32, y.floor() as u32);
+    let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+    let (fx, fy) = (x - x0 as f32, y - y0 as f32);
+
+    let corners = [
+        img.get_pixel(x0, y0).channels(),
+        img.get_pixel(x1, y0).channels(),
+        img.get_pixel(x0, y1).channels(),
+        img.get_pixel(x1, y1).channels(),
+    ];
+    let mut out = *img.get_pixel(x0, y0);
+    for (idx, channel) in out.channels_mut().iter_mut().enumerate() {
+        let [c00, c10, c01, c11]: [f32; 4] = corners.map(|c| c[idx].value_into().unwrap());
+        let top = c00 + (c10 - c00) * fx;
+        let bottom = c01 + (c11 - c01) * fx;
+        *channel = P::Subpixel::clamp(top + (bottom - top) * fy);
+    }
+    out
+}
+
 #[cfg(test)]
 mod test {
-    use crate::util::SetEnumerator;
+    use image::{Luma, LumaA, Rgb, Rgba};
+    use imageproc::definitions::Image;
+
+    use crate::util::{
+        color_channels, hsv_to_rgb, linear_to_srgb, rgb_to_hsv, rotated_bounds, sample_bilinear,
+        scale_saturation, srgb_to_linear, stable_hash, truncate_with_hash, zero_pixel,
+        SetEnumerator,
+    };
 
     #[test]
     fn power_set() {
@@ -151,4 +446,123 @@ mod test {
 
         assert_eq!(maxes.into_iter().possibilities().next(), None);
     }
+
+    #[test]
+    fn hsv_primaries() {
+        assert_eq!(rgb_to_hsv([255, 0, 0]), [0., 1., 1.]);
+        assert_eq!(rgb_to_hsv([0, 255, 0]), [120., 1., 1.]);
+        assert_eq!(rgb_to_hsv([0, 0, 255]), [240., 1., 1.]);
+        assert_eq!(rgb_to_hsv([0, 0, 0]), [0., 0., 0.]);
+        assert_eq!(rgb_to_hsv([255, 255, 255]), [0., 0., 1.]);
+
+        assert_eq!(hsv_to_rgb([60., 1., 1.]), [255, 255, 0]);
+        assert_eq!(hsv_to_rgb([180., 1., 1.]), [0, 255, 255]);
+        assert_eq!(hsv_to_rgb([-60., 1., 1.]), [255, 0, 255]);
+        assert_eq!(hsv_to_rgb([0., 0., 0.5]), [128, 128, 128]);
+        assert_eq!(hsv_to_rgb([420., 1., 1.]), [255, 255, 0]);
+        assert_eq!(hsv_to_rgb([0., 1.5, 2.]), [255, 0, 0]);
+    }
+
+    #[test]
+    fn hsv_round_trip() {
+        for rgb in [
+            [12, 200, 77],
+            [255, 128, 0],
+            [90, 90, 91],
+            [1, 2, 3],
+            [250, 10, 250],
+        ] {
+            assert_eq!(hsv_to_rgb(rgb_to_hsv(rgb)), rgb);
+        }
+    }
+
+    #[test]
+    fn saturation_scaling() {
+        assert_eq!(scale_saturation([200, 100, 100], 1.), [200, 100, 100]);
+        assert_eq!(scale_saturation([200, 100, 100], 0.5), [200, 150, 150]);
+        assert_eq!(scale_saturation([200, 100, 100], 0.), [200, 200, 200]);
+        assert_eq!(scale_saturation([200, 100, 100], 2.), [200, 0, 0]);
+        assert_eq!(scale_saturation([200, 100, 100], 5.), [200, 0, 0]);
+        assert_eq!(scale_saturation([20, 60, 100], 1.5), [0, 50, 100]);
+        assert_eq!(scale_saturation([90, 90, 90], 3.), [90, 90, 90]);
+    }
+
+    #[test]
+    fn srgb_round_trip() {
+        assert_eq!(srgb_to_linear(0.0), 0.0);
+        assert!((srgb_to_linear(1.0) - 1.0).abs() < 1e-6);
+        assert!((srgb_to_linear(0.5) - 0.214).abs() < 1e-3);
+        for c in 0..=255u8 {
+            let c = c as f32 / 255.0;
+            assert!((linear_to_srgb(srgb_to_linear(c)) - c).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn stable_hash_known_values() {
+        assert_eq!(stable_hash(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(stable_hash(b"a"), 0xaf63_dc4c_8601_ec8c);
+    }
+
+    #[test]
+    fn truncated_names() {
+        assert_eq!(truncate_with_hash("short", 10).unwrap(), "short");
+
+        let long = "a_very_long_name_rot90cw_blur_7.31";
+        let truncated = truncate_with_hash(long, 24).unwrap();
+        assert_eq!(truncated.len(), 24);
+        assert!(truncated.starts_with("a_very_"));
+        assert_eq!(truncated, truncate_with_hash(long, 24).unwrap());
+        assert_ne!(
+            truncated,
+            truncate_with_hash("a_very_long_name_rot90ccw", 24).unwrap()
+        );
+
+        // Never cuts a character in half
+        assert!(truncate_with_hash("ééééééééééééééééé", 20).unwrap().len() <= 20);
+
+        // Too short a limit to fit the hash at all, though short names still fit
+        assert_eq!(truncate_with_hash(long, 17).unwrap().len(), 17);
+        assert!(truncate_with_hash(long, 16).is_none());
+        assert!(truncate_with_hash(long, 0).is_none());
+        assert_eq!(truncate_with_hash("short", 16).unwrap(), "short");
+    }
+
+    #[test]
+    fn alpha_excluded_from_color_channels() {
+        assert_eq!(color_channels::<Luma<u8>>(), 1);
+        assert_eq!(color_channels::<LumaA<u8>>(), 1);
+        assert_eq!(color_channels::<Rgb<u8>>(), 3);
+        assert_eq!(color_channels::<Rgba<u16>>(), 3);
+    }
+
+    #[test]
+    fn zeroed_pixels() {
+        assert_eq!(zero_pixel::<Luma<u8>>(), Luma([0]));
+        assert_eq!(zero_pixel::<Rgb<u8>>(), Rgb([0; 3]));
+        assert_eq!(zero_pixel::<Rgba<u16>>(), Rgba([0; 4]));
+    }
+
+    #[test]
+    fn bilinear_sampling() {
+        let img = Image::from_fn(2, 2, |x, y| Luma([(x * 100 + y * 20) as u8]));
+
+        assert_eq!(sample_bilinear(&img, 0.5, 0.), Luma([50]));
+        assert_eq!(sample_bilinear(&img, 0.5, 0.5), Luma([60]));
+        assert_eq!(sample_bilinear(&img, -3., 7.), Luma([20]));
+        assert_eq!(sample_bilinear(&img, 9., -1.), Luma([100]));
+    }
+
+    #[test]
+    fn rotation_bounds() {
+        use std::f64::consts::{FRAC_PI_2, FRAC_PI_4};
+
+        assert_eq!(rotated_bounds(100, 50, 0.), (100, 50));
+        assert_eq!(rotated_bounds(100, 50, FRAC_PI_2), (50, 100));
+        assert_eq!(rotated_bounds(100, 50, -FRAC_PI_2), (50, 100));
+        // 100 * sqrt(2) = 141.42..., rounded up
+        assert_eq!(rotated_bounds(100, 100, FRAC_PI_4), (142, 142));
+        // (200 + 100) / sqrt(2) = 212.13...
+        assert_eq!(rotated_bounds(200, 100, -FRAC_PI_4), (213, 213));
+    }
 }